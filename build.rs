@@ -0,0 +1,211 @@
+//! 根据 `src/assets/manifest.toml` 生成资源的注册代码
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+const MANIFEST_PATH: &str = "src/assets/manifest.toml";
+const ASSETS_DIR: &str = "src/assets";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    assets: BTreeMap<String, Asset>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Asset {
+    id: String,
+    kind: Kind,
+    path: String,
+    sampler: Option<Sampler>,
+    #[serde(default)]
+    slices: Vec<[u32; 4]>,
+    audio: Option<AudioMeta>,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+    Texture,
+    Audio,
+    Scene,
+    Package,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Sampler {
+    #[serde(default)]
+    filter: Filter,
+    #[serde(default)]
+    address_mode: AddressMode,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Filter {
+    #[default]
+    Nearest,
+    Linear,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+enum AddressMode {
+    #[default]
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AudioMeta {
+    #[serde(default)]
+    bus: Bus,
+    #[serde(default = "default_volume")]
+    volume: f32,
+    #[serde(default)]
+    stream: bool,
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Bus {
+    Music,
+    #[default]
+    Sfx,
+    Ambient,
+    Ui,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={MANIFEST_PATH}");
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let manifest_dir = Path::new(&manifest_dir);
+    let text = std::fs::read_to_string(manifest_dir.join(MANIFEST_PATH))
+        .unwrap_or_else(|err| panic!("Failed to read {MANIFEST_PATH}: {err}"));
+    let manifest: Manifest =
+        toml::from_str(&text).unwrap_or_else(|err| panic!("Invalid {MANIFEST_PATH}: {err}"));
+
+    let mut ids = HashMap::new();
+    let mut code = String::new();
+    for (name, asset) in &manifest.assets {
+        validate(manifest_dir, name, asset);
+        // UUID 的格式在编译 AssetsId::new 时检查，这里只检查是否重复
+        if let Some(other) = ids.insert(asset.id.to_ascii_lowercase(), name) {
+            panic!("{name} and {other} have the same id {}", asset.id);
+        }
+
+        let kind = match asset.kind {
+            Kind::Texture => "Texture",
+            Kind::Audio => "Audio",
+            Kind::Scene => "Scene",
+            Kind::Package => "Package",
+        };
+        let sampler = asset.sampler.as_ref().map_or_else(
+            || "SamplerSettings::DEFAULT".to_owned(),
+            |sampler| {
+                format!(
+                    "SamplerSettings {{ filter: TextureFilter::{:?}, address_mode: TextureAddressMode::{:?} }}",
+                    sampler.filter, sampler.address_mode
+                )
+            },
+        );
+        let audio = asset.audio.as_ref().map_or_else(
+            || "AudioSettings::DEFAULT".to_owned(),
+            |audio| {
+                format!(
+                    "AudioSettings {{ bus: AudioBus::{:?}, volume: {:?}, stream: {:?} }}",
+                    audio.bus, audio.volume, audio.stream
+                )
+            },
+        );
+        let slices: Vec<String> = asset
+            .slices
+            .iter()
+            .map(|[x0, y0, x1, y1]| format!("Rect::new_u32({x0}, {y0}, {x1}, {y1})"))
+            .collect();
+        let full_path = manifest_dir.join(ASSETS_DIR).join(&asset.path);
+        println!("cargo:rerun-if-changed={}", full_path.display());
+        writeln!(
+            code,
+            "pub static {name}: AssetEntry = AssetEntry {{
+    id: AssetsId::new({id:?}),
+    kind: AssetKind::{kind},
+    path: {path:?},
+    embedded: embedded!({full_path:?}),
+    sampler: {sampler},
+    slices: &[{slices}],
+    audio: {audio},
+}};",
+            id = asset.id,
+            path = asset.path,
+            full_path = full_path.to_str().expect("Asset path must be UTF-8"),
+            slices = slices.join(", "),
+        )
+        .unwrap();
+    }
+    writeln!(
+        code,
+        "/// 清单中的所有资源\npub static MANIFEST: [&AssetEntry; {}] = [{}];",
+        manifest.assets.len(),
+        manifest
+            .assets
+            .keys()
+            .map(|name| format!("&{name}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+    .unwrap();
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("assets.rs"), code).unwrap();
+}
+
+fn validate(manifest_dir: &Path, name: &str, asset: &Asset) {
+    let valid_name = name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    assert!(valid_name, "Asset name {name} must be SCREAMING_SNAKE_CASE");
+    assert!(
+        !asset.path.contains("..") && !asset.path.starts_with('/'),
+        "{name}: path {} must stay inside {ASSETS_DIR}",
+        asset.path
+    );
+    assert!(
+        manifest_dir.join(ASSETS_DIR).join(&asset.path).is_file(),
+        "{name}: {} does not exist",
+        asset.path
+    );
+    if asset.kind != Kind::Texture {
+        assert!(
+            asset.sampler.is_none() && asset.slices.is_empty(),
+            "{name}: only textures can have sampler and slices"
+        );
+    }
+    if let Some(audio) = &asset.audio {
+        assert!(
+            asset.kind == Kind::Audio,
+            "{name}: only audio can have audio settings"
+        );
+        assert!(
+            (0.0..=1.0).contains(&audio.volume),
+            "{name}: audio volume {} must be between 0 and 1",
+            audio.volume
+        );
+    }
+    for [x0, y0, x1, y1] in &asset.slices {
+        assert!(
+            x0 <= x1 && y0 <= y1,
+            "{name}: slice [{x0}, {y0}, {x1}, {y1}] must have x0 <= x1 and y0 <= y1"
+        );
+    }
+}
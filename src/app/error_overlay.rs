@@ -0,0 +1,185 @@
+use crate::{
+    rasterize_text, AssetsId, Camera2D, CameraPass, Color, ErrorLog, PositionType, Render,
+    RenderGroup, Sprite, Style, TextureStore, Transform, UiNodeId, UiSprite, UiTree, Val,
+};
+use glam::{Vec2, Vec3};
+use std::time::Duration;
+use winit::dpi::PhysicalSize;
+
+/// 最多显示最近的几条错误
+const SHOWN_ERRORS: usize = 4;
+/// 每条错误最多显示的字符数，超出的部分省略
+const MAX_LINE_CHARS: usize = 96;
+/// 没有新的错误后继续显示的时间
+const DISPLAY_DURATION: Duration = Duration::from_secs(8);
+
+/// 在画面左下角显示最近的错误，不依赖 egui，所有构建中都可用
+///
+/// 错误变化后在 prepare 中用内置的点阵字体重新生成文字的纹理。
+#[derive(Debug)]
+pub(super) struct ErrorOverlay {
+    camera: Camera2D,
+    /// 窗口的 DPI 缩放
+    scale_factor: f32,
+    ui: UiTree,
+    ui_panel: UiNodeId,
+    panel: UiSprite,
+    texture_id: Option<AssetsId>,
+    /// 最近一次显示的 ErrorLog::revision
+    revision: u64,
+    /// 需要重新生成文字的纹理
+    if_dirty: bool,
+    /// 最后一次出现新的错误后经过的时间
+    shown: Duration,
+}
+
+impl ErrorOverlay {
+    pub(super) fn new(window_size: PhysicalSize<u32>) -> ErrorOverlay {
+        let mut ui = UiTree::default();
+        let ui_panel = ui.new_root(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            bottom: Val::Px(8.0),
+            ..Default::default()
+        });
+        ErrorOverlay {
+            camera: Camera2D::new(Vec2::new(
+                window_size.width as f32,
+                window_size.height as f32,
+            )),
+            scale_factor: 1.0,
+            ui,
+            ui_panel,
+            panel: UiSprite {
+                sprite: Sprite {
+                    transform: Transform::from_translation(Vec3::new(0.0, 0.0, 900.0)),
+                    color: Color::new([255, 110, 110, 255]),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            texture_id: None,
+            revision: 0,
+            if_dirty: false,
+            shown: DISPLAY_DURATION,
+        }
+    }
+
+    pub(super) fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+        if self.scale_factor != scale_factor {
+            self.scale_factor = scale_factor;
+            self.if_dirty = true;
+        }
+    }
+
+    pub(super) fn update(&mut self, delta: Duration, errors: &ErrorLog) {
+        if errors.revision() != self.revision {
+            self.revision = errors.revision();
+            self.if_dirty = true;
+            self.shown = Duration::ZERO;
+        } else {
+            self.shown = (self.shown + delta).min(DISPLAY_DURATION);
+        }
+        if errors.errors().is_empty() {
+            self.shown = DISPLAY_DURATION;
+        }
+    }
+
+    pub(super) fn if_visible(&self) -> bool {
+        self.shown < DISPLAY_DURATION
+    }
+
+    /// 错误变化后重新生成文字的纹理
+    pub(super) fn prepare(
+        &mut self,
+        errors: &ErrorLog,
+        render: &Render,
+        texture_store: &mut TextureStore,
+    ) {
+        if self.if_dirty && self.if_visible() {
+            let image = rasterize_text(
+                error_lines(errors).iter().map(String::as_str),
+                (self.scale_factor * 2.0).round().max(1.0) as u32,
+                [0, 0, 0, 160],
+            );
+            let size = Vec2::new(image.width() as f32, image.height() as f32);
+            let texture_id = match self.texture_id {
+                Some(texture_id) => {
+                    texture_store.replace_texture(render, texture_id, image);
+                    texture_id
+                }
+                None => texture_store.load_texture(render, &image),
+            };
+            self.texture_id = Some(texture_id);
+            self.panel.sprite.texture_id = texture_id;
+            self.ui
+                .set_content_size(self.ui_panel, Some(size / self.scale_factor));
+            self.if_dirty = false;
+        }
+        self.ui
+            .compute_layout(self.camera.viewport_size, self.scale_factor);
+        self.panel
+            .update(self.ui.layout(self.ui_panel), &self.camera, Duration::ZERO);
+    }
+
+    pub(super) fn render(&self) -> Option<RenderGroup<'_>> {
+        if !self.if_visible() || self.texture_id.is_none() {
+            return None;
+        }
+        Some(RenderGroup {
+            passes: vec![CameraPass::new(&self.camera)],
+            sprites: vec![&self.panel.sprite],
+        })
+    }
+}
+
+/// 最近的几条错误，每条只显示第一行
+fn error_lines(errors: &ErrorLog) -> Vec<String> {
+    let errors = errors.errors();
+    errors[errors.len().saturating_sub(SHOWN_ERRORS)..]
+        .iter()
+        .map(|err| {
+            let message = err.to_string();
+            let line = message.lines().next().unwrap_or_default();
+            if line.chars().count() > MAX_LINE_CHARS {
+                let mut line: String = line.chars().take(MAX_LINE_CHARS - 3).collect();
+                line.push_str("...");
+                line
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn shows_recent_errors_until_they_go_stale() {
+        let mut errors = ErrorLog::default();
+        let mut overlay = ErrorOverlay::new(PhysicalSize::new(1280, 720));
+        overlay.update(Duration::from_millis(16), &errors);
+        assert!(!overlay.if_visible());
+
+        for index in 0..6 {
+            errors.report(Error::Scene(format!("error {index}\nsecond line")));
+        }
+        errors.report(Error::Scene("x".repeat(200)));
+        overlay.update(Duration::from_millis(16), &errors);
+        assert!(overlay.if_visible() && overlay.if_dirty);
+        let lines = error_lines(&errors);
+        assert_eq!(lines.len(), SHOWN_ERRORS);
+        assert!(lines[0].ends_with("error 3"));
+        assert!(lines[3].ends_with("...") && lines[3].chars().count() == MAX_LINE_CHARS);
+
+        overlay.update(DISPLAY_DURATION, &errors);
+        assert!(!overlay.if_visible());
+        errors.clear();
+        overlay.update(Duration::ZERO, &errors);
+        assert!(!overlay.if_visible());
+    }
+}
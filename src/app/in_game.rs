@@ -1,11 +1,15 @@
+use crate::app::pause_menu::PauseMenu;
+use crate::app::state::{GameState, StateContext, Transition};
 use crate::assets::{
-    AssetsId, BG_CHECKER, PACKAGE_SIDEBOARD, SCENE_SIDEBOARD, UI_ZOOM_IN, UI_ZOOM_IN_SLICE,
-    UI_ZOOM_OUT, UI_ZOOM_OUT_SLICE,
+    AssetServer, AssetsId, BG_CHECKER, PACKAGE_SIDEBOARD, SCENE_SIDEBOARD, UI_ZOOM_IN, UI_ZOOM_OUT,
 };
-use crate::input::Input;
+use crate::input::{Action, GamepadButton, GamepadSide, Input, InputLayer};
 use crate::utils::collect_sprites;
 use crate::{
-    Audio, Camera2D, Color, Rect, Render, ScreenRepeat, Sprite, TextureStore, Transform, UiSprite,
+    Audio, Autosave, Camera2D, CameraPass, ClearPolicy, Color, EasingAnimator, Error,
+    FlexDirection, Listener, PositionType, Rect, Render, RenderGroup, RenderLayers, SaveCamera,
+    SaveData, ScreenRepeat, Sprite, Style, TextureStore, Transform, UiTree, Val, WidgetId,
+    WidgetVisual, WidgetVisuals, Widgets, AUTOSAVE_SLOT, QUICKSAVE_SLOT,
 };
 use glam::{Vec2, Vec3};
 use isometric_engine::{MetaModel, Package, Scene, SerdeFrom};
@@ -13,175 +17,427 @@ use std::collections::HashMap;
 use std::time::Duration;
 use winit::dpi::PhysicalSize;
 use winit::event::MouseButton;
-use winit::keyboard::KeyCode;
 
+/// 双指缩放累计超过这个比例时切换一档缩放
+const PINCH_ZOOM_STEP: f32 = 1.5;
+/// 每个固定步长镜头移动的距离
+const CAMERA_MOVE_STEP: f32 = 4.0;
+
+/// 每帧从输入中得到的操作，留给之后的 fixed_update 使用
 #[derive(Debug, Default)]
+struct PendingInput {
+    cursor_world_pos: Vec2,
+    /// 每个固定步长镜头移动的方向
+    camera_move: Vec2,
+    /// 双指平移累计的镜头偏移
+    camera_pan: Vec2,
+    click_type: u8,
+}
+
+/// 玩家在游戏中发起的存档操作，在 update 的最后读写存档
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SaveRequest {
+    QuickSave,
+    QuickLoad,
+}
+
+#[derive(Debug)]
 pub struct InGame {
     camera: Camera2D,
+    /// 固定不动的屏幕空间镜头，用于渲染 UI
+    ui_camera: Camera2D,
     sprites: Vec<Sprite>,
     screen_repeat: ScreenRepeat,
-    ui_zoom_in_sprite: UiSprite,
-    ui_zoom_out_sprite: UiSprite,
+    /// 窗口的 DPI 缩放
+    scale_factor: f32,
+    ui: UiTree,
+    widgets: Widgets,
+    ui_zoom_in: WidgetId,
+    ui_zoom_out: WidgetId,
+    /// 累计的双指缩放比例
+    pinch_zoom: f32,
+    pending: PendingInput,
+    /// 最近两次 fixed_update 后的镜头位置，渲染时在两者之间插值
+    previous_translation: Vec2,
+    translation: Vec2,
     package: Option<Package>,
     scene: Option<Scene>,
     image_map: HashMap<MetaModel, AssetsId>,
+    /// 资源包中还没有上传到 GPU 的图片
+    pending_images: Vec<MetaModel>,
+    /// 还没有交给 AppData 显示的错误
+    errors: Vec<Error>,
+    save_request: Option<SaveRequest>,
+    autosave: Autosave,
 }
 
 impl InGame {
-    pub fn new(
+    /// 把资源包中的图片上传到 GPU，超过 budget 后留到下一次调用，全部上传完成时返回 true
+    pub fn upload_images(
+        &mut self,
         render: &Render,
         texture_store: &mut TextureStore,
-        window_size: PhysicalSize<u32>,
-    ) -> InGame {
+        budget: Duration,
+    ) -> bool {
+        let Some(package) = &self.package else {
+            return true;
+        };
+        let start = instant::Instant::now();
+        while let Some(key) = self.pending_images.pop() {
+            let image = &package.sprite_image_map[&key];
+            self.image_map
+                .insert(key, texture_store.load_texture(render, image));
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        self.pending_images.is_empty()
+    }
+
+    pub fn if_images_uploaded(&self) -> bool {
+        self.package.is_none() || self.pending_images.is_empty()
+    }
+
+    /// 已经上传的图片数和资源包中图片的总数
+    pub fn image_progress(&self) -> (usize, usize) {
+        (
+            self.image_map.len(),
+            self.image_map.len() + self.pending_images.len(),
+        )
+    }
+
+    /// 不加载任何 GPU 资源，只用于驱动游戏逻辑，比如无窗口重放录制的输入
+    ///
+    /// 场景和资源包需要提前在 assets 中加载完成，zoom 为镜头一开始的缩放
+    pub fn new_headless(assets: &AssetServer, window_size: PhysicalSize<u32>, zoom: u8) -> InGame {
         let mut camera = Camera2D::new(Vec2::new(
             window_size.width as f32,
             window_size.height as f32,
         ));
-        camera.set_zoom(2);
+        camera.set_zoom(zoom);
+        let ui_camera = Camera2D::new(camera.viewport_size);
 
         let screen_repeat = ScreenRepeat {
-            texture_id: BG_CHECKER.0,
+            texture_id: BG_CHECKER.id,
             offset: Vec2::ZERO,
             scale: 1.0 / camera.get_scale(),
             color: Color::from((107, 13, 56)),
         };
-        let ui_zoom_in_sprite = UiSprite {
-            sprite: Sprite {
-                transform: Transform::from_translation(Vec3::new(0.0, 0.0, 500.0)),
-                texture_id: UI_ZOOM_IN.0,
-                rect: Some(UI_ZOOM_IN_SLICE[0]),
-                ..Default::default()
-            },
-            size: Vec2::new(36.0, 36.0).into(),
-            left: Some(60.0.into()),
-            top: Some(16.0.into()),
+        // 右上角的缩放按钮
+        let mut ui = UiTree::default();
+        let ui_toolbar = ui.new_root(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(16.0),
+            top: Val::Px(16.0),
+            flex_direction: FlexDirection::Row,
+            gap: Val::Px(8.0),
+            ..Default::default()
+        });
+        let ui_button_style = Style {
+            width: Val::Px(36.0),
+            height: Val::Px(36.0),
             ..Default::default()
         };
-        let ui_zoom_out_sprite = UiSprite {
-            sprite: Sprite {
-                transform: Transform::from_translation(Vec3::new(0.0, 0.0, 500.0)),
-                texture_id: UI_ZOOM_OUT.0,
-                rect: Some(UI_ZOOM_OUT_SLICE[0]),
-                ..Default::default()
-            },
-            size: Vec2::new(36.0, 36.0).into(),
-            left: Some(16.0.into()),
-            top: Some(16.0.into()),
+        let ui_button_sprite = Sprite {
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 500.0)),
+            layers: RenderLayers::UI,
             ..Default::default()
         };
+        let mut widgets = Widgets::default();
+        let ui_zoom_in = widgets.add_button(
+            ui.new_child(ui_toolbar, ui_button_style),
+            ui_button_sprite,
+            zoom_button_visuals(UI_ZOOM_IN.id, UI_ZOOM_IN.slices),
+        );
+        let ui_zoom_out = widgets.add_button(
+            ui.new_child(ui_toolbar, ui_button_style),
+            ui_button_sprite,
+            zoom_button_visuals(UI_ZOOM_OUT.id, UI_ZOOM_OUT.slices),
+        );
 
-        let mut scene = Scene::from_bytes(SCENE_SIDEBOARD);
-        // NOTE 目前每个 PA 排序完后的 logic_order_table 序列化是不存储的，所以重新加载时，需要重新进行一遍排序来更新 logic_order_table
-        //  用于之后的光标选择判断
-        scene.update_all();
-        camera.set_translation(Vec2::new(
-            scene.size()[0] as f32 / 2.0,
-            scene.size()[1] as f32 / 2.0,
-        ));
-        let package = Package::unpack_from_bytes(PACKAGE_SIDEBOARD).unwrap();
-        let mut image_map: HashMap<MetaModel, AssetsId> =
-            HashMap::with_capacity(package.sprite_image_map.len());
-        for (key, image) in package.sprite_image_map.iter() {
-            image_map.insert(*key, texture_store.load_texture(render, image));
-        }
+        let mut errors = Vec::new();
+        let scene = match assets.get(&SCENE_SIDEBOARD.id) {
+            Some(bytes) => {
+                let mut scene = Scene::from_bytes(&bytes);
+                // NOTE 目前每个 PA 排序完后的 logic_order_table 序列化是不存储的，所以重新加载时，需要重新进行一遍排序来更新 logic_order_table
+                //  用于之后的光标选择判断
+                scene.update_all();
+                camera.set_translation(Vec2::new(
+                    scene.size()[0] as f32 / 2.0,
+                    scene.size()[1] as f32 / 2.0,
+                ));
+                Some(scene)
+            }
+            None => {
+                errors.push(Error::Scene(format!(
+                    "Scene {} is not loaded",
+                    SCENE_SIDEBOARD.path
+                )));
+                None
+            }
+        };
+        // 资源包无法加载时场景依旧可以显示，只是没有物品
+        let package = match assets
+            .get(&PACKAGE_SIDEBOARD.id)
+            .ok_or_else(|| format!("{} is not loaded", PACKAGE_SIDEBOARD.path))
+            .and_then(|bytes| Package::unpack_from_bytes(&bytes))
+        {
+            Ok(package) => Some(package),
+            Err(err) => {
+                errors.push(Error::Scene(format!("Failed to unpack package: {err}")));
+                None
+            }
+        };
+
+        let pending_images = package
+            .as_ref()
+            .map(|package| package.sprite_image_map.keys().copied().collect())
+            .unwrap_or_default();
 
         InGame {
+            previous_translation: camera.get_translation(),
+            translation: camera.get_translation(),
             camera,
+            ui_camera,
             sprites: Vec::new(),
             screen_repeat,
-            ui_zoom_in_sprite,
-            ui_zoom_out_sprite,
-            package: Some(package),
-            scene: Some(scene),
-            image_map,
+            scale_factor: 1.0,
+            ui,
+            widgets,
+            ui_zoom_in,
+            ui_zoom_out,
+            pinch_zoom: 1.0,
+            pending: PendingInput::default(),
+            package,
+            scene,
+            image_map: HashMap::new(),
+            pending_images,
+            errors,
+            save_request: None,
+            autosave: Autosave::default(),
         }
     }
 
-    pub fn resize(&mut self, window_size: PhysicalSize<u32>) {
-        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
     }
 
-    pub fn update(&mut self, delta: Duration, input: &Input, audio: &mut Audio) {
-        let cursor_world_pos = self.camera.viewport_to_world(input.cursor_pos()).truncate();
-
-        let camera = &mut self.camera;
-        if !camera.if_can_zoom_in() {
-            self.ui_zoom_in_sprite.sprite.color.set_opacity(0.5);
-            self.ui_zoom_in_sprite.stop_anima();
-        } else {
-            if self.ui_zoom_in_sprite.contains(cursor_world_pos) {
-                self.ui_zoom_in_sprite.sprite.color.set_opacity(1.0);
-                self.ui_zoom_in_sprite.start_anima(&UI_ZOOM_IN_SLICE);
-                if input.if_mouse_just_pressed(&MouseButton::Left) {
-                    // TODO 这里与 ui 交互了，就不能与场景交互了
-                    camera.zoom_in();
-                }
-            } else {
-                self.ui_zoom_in_sprite.sprite.color.set_opacity(0.7);
-                self.ui_zoom_in_sprite.stop_anima();
+    /// 保存当前的游戏，重放时不保存，避免覆盖玩家的存档
+    fn save(&mut self, slot: &str, ctx: &mut StateContext) {
+        if ctx.if_replaying {
+            return;
+        }
+        let (Some(storage), Some(save)) = (ctx.storage.as_deref_mut(), self.to_save()) else {
+            return;
+        };
+        match crate::save_slot(storage, slot, &save) {
+            Ok(()) => {
+                log::info!("Saved game to slot {slot}");
+                self.autosave.reset();
             }
+            Err(err) => ctx.errors.report(err),
         }
+    }
 
-        if !camera.if_can_zoom_out() {
-            self.ui_zoom_out_sprite.sprite.color.set_opacity(0.5);
-            self.ui_zoom_out_sprite.stop_anima();
-        } else {
-            if self.ui_zoom_out_sprite.contains(cursor_world_pos) {
-                self.ui_zoom_out_sprite.sprite.color.set_opacity(1.0);
-                self.ui_zoom_out_sprite.start_anima(&UI_ZOOM_OUT_SLICE);
-                if input.if_mouse_just_pressed(&MouseButton::Left) {
-                    camera.zoom_out();
-                }
-            } else {
-                self.ui_zoom_out_sprite.sprite.color.set_opacity(0.7);
-                self.ui_zoom_out_sprite.stop_anima();
+    pub(super) fn load(&mut self, slot: &str, ctx: &mut StateContext) {
+        let Some(storage) = ctx.storage.as_deref() else {
+            return;
+        };
+        match crate::load_slot(storage, slot) {
+            Ok(Some(save)) => {
+                self.load_save(&save);
+                self.autosave.reset();
+                log::info!("Loaded game from slot {slot}");
             }
+            Ok(None) => log::info!("No save in slot {slot}"),
+            Err(err) => ctx.errors.report(err),
         }
+    }
 
-        let camera_move_step = 4.0;
-        if input.if_keyboard_pressed(&KeyCode::ArrowLeft) {
-            camera.add_translation(Vec2::new(-camera_move_step, 0.0));
-        }
-        if input.if_keyboard_pressed(&KeyCode::ArrowRight) {
-            camera.add_translation(Vec2::new(camera_move_step, 0.0));
-        }
-        if input.if_keyboard_pressed(&KeyCode::ArrowUp) {
-            camera.add_translation(Vec2::new(0.0, camera_move_step));
+    /// 生成当前场景和镜头的存档，场景没有加载时为 None
+    pub fn to_save(&self) -> Option<SaveData> {
+        let scene = self.scene.as_ref()?;
+        let camera = SaveCamera {
+            translation: self.translation,
+            pixel_zoom: self.camera.pixel_zoom,
+        };
+        let mut save = SaveData::new(Some(camera), scene.to_bytes());
+        save.sanitize();
+        Some(save)
+    }
+
+    /// 用存档替换当前的场景和镜头
+    pub fn load_save(&mut self, save: &SaveData) {
+        let mut scene = Scene::from_bytes(&save.scene);
+        scene.update_all();
+        let translation = match save.header.camera {
+            Some(camera) => {
+                self.camera.scale_animator = EasingAnimator::default();
+                self.camera.set_zoom(camera.pixel_zoom);
+                camera.translation
+            }
+            None => Vec2::new(scene.size()[0] as f32 / 2.0, scene.size()[1] as f32 / 2.0),
+        };
+        self.camera.set_translation(translation);
+        self.previous_translation = translation;
+        self.translation = translation;
+        self.pending = PendingInput::default();
+        self.scene = Some(scene);
+    }
+
+    /// 最后一次 fixed_step 后镜头的位置，不包括渲染时的插值和镜头特效
+    pub fn camera_translation(&self) -> Vec2 {
+        self.translation
+    }
+
+    /// 下一次 fixed_step 镜头的位移，之后还会被限制在场景的边界内
+    pub fn pending_camera_offset(&self) -> Vec2 {
+        self.pending.camera_move * CAMERA_MOVE_STEP + self.pending.camera_pan
+    }
+
+    /// 下一次 fixed_step 会把点击交给场景
+    pub fn if_click_pending(&self) -> bool {
+        self.pending.click_type != 0 && self.scene.is_some() && self.package.is_some()
+    }
+
+    pub fn into_scene(self) -> Option<Scene> {
+        self.scene
+    }
+
+    pub fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+        self.ui_camera.viewport_size = self.camera.viewport_size;
+        self.scale_factor = scale_factor;
+    }
+
+    /// 丢弃还没有执行的操作，比如暂停后恢复时不会继续移动镜头
+    pub fn clear_pending(&mut self) {
+        self.pending = PendingInput::default();
+    }
+
+    /// 处理这一帧的输入，不需要窗口，无窗口重放时也使用这个方法
+    pub fn update_input(&mut self, delta: Duration, input: &mut Input) {
+        let cursor_world_pos = self.camera.viewport_to_world(input.cursor_pos()).truncate();
+
+        self.ui
+            .compute_layout(self.ui_camera.viewport_size, self.scale_factor);
+        self.widgets
+            .set_enabled(self.ui_zoom_in, self.camera.if_can_zoom_in());
+        self.widgets
+            .set_enabled(self.ui_zoom_out, self.camera.if_can_zoom_out());
+        self.widgets.update(&self.ui, input, &self.ui_camera, delta);
+        // 输入已经被 egui 或者 ui 占用时，就不能与场景交互了
+        let pointer_free = input.pointer_available(InputLayer::Scene);
+        let keyboard_free = input.keyboard_available(InputLayer::Scene);
+
+        let camera = &mut self.camera;
+        if self.widgets.clicked(self.ui_zoom_in) {
+            camera.zoom_in();
         }
-        if input.if_keyboard_pressed(&KeyCode::ArrowDown) {
-            camera.add_translation(Vec2::new(0.0, -camera_move_step));
+        if self.widgets.clicked(self.ui_zoom_out) {
+            camera.zoom_out();
         }
-        if input.cursor_pos().x < 48.0 {
-            if !input.if_keyboard_pressed(&KeyCode::ArrowLeft)
-                && !input.if_keyboard_pressed(&KeyCode::ArrowRight)
-            {
-                camera.add_translation(Vec2::new(-camera_move_step, 0.0));
+
+        // 镜头的移动在 fixed_update 中按固定步长执行，这里只记录移动的方向
+        let mut camera_move = Vec2::ZERO;
+        if keyboard_free {
+            if input.if_action_pressed(Action::CameraLeft) {
+                camera_move.x -= 1.0;
             }
-            // let boundary_deep = 96.0 - input.cursor_pos().x;
+            if input.if_action_pressed(Action::CameraRight) {
+                camera_move.x += 1.0;
+            }
+            if input.if_action_pressed(Action::CameraUp) {
+                camera_move.y += 1.0;
+            }
+            if input.if_action_pressed(Action::CameraDown) {
+                camera_move.y -= 1.0;
+            }
+            // 右摇杆移动镜头，左摇杆用于移动虚拟光标
+            camera_move += input.gamepad_stick(GamepadSide::Right);
         }
-        if input.cursor_pos().x > (camera.viewport_size.x - 48.0) {
-            if !input.if_keyboard_pressed(&KeyCode::ArrowLeft)
-                && !input.if_keyboard_pressed(&KeyCode::ArrowRight)
-            {
-                camera.add_translation(Vec2::new(camera_move_step, 0.0));
+        // 双指平移和缩放镜头，缩放只有固定的几档，所以累计到一定比例才切换
+        if pointer_free {
+            let pan = input.touch_pan();
+            self.pending.camera_pan += Vec2::new(-pan.x, pan.y) * camera.get_scale().x;
+            self.pinch_zoom *= input.pinch_scale();
+            if self.pinch_zoom > PINCH_ZOOM_STEP {
+                camera.zoom_in();
+                self.pinch_zoom = 1.0;
+            } else if self.pinch_zoom < 1.0 / PINCH_ZOOM_STEP {
+                camera.zoom_out();
+                self.pinch_zoom = 1.0;
             }
-            // let boundary_deep = 96.0 - input.cursor_pos().x;
         }
-        if input.cursor_pos().y < 48.0 {
-            if !input.if_keyboard_pressed(&KeyCode::ArrowUp)
-                && !input.if_keyboard_pressed(&KeyCode::ArrowDown)
-            {
-                camera.add_translation(Vec2::new(0.0, camera_move_step));
+        if input.touch_count() < 2 {
+            self.pinch_zoom = 1.0;
+        }
+
+        // 光标在 ui 上时不触发屏幕边缘移动镜头，触摸时也没有悬停的光标
+        if pointer_free && !input.if_touch_pointer() {
+            let horizontal = input.if_action_pressed(Action::CameraLeft)
+                || input.if_action_pressed(Action::CameraRight);
+            let vertical = input.if_action_pressed(Action::CameraUp)
+                || input.if_action_pressed(Action::CameraDown);
+            if input.cursor_pos().x < 48.0 && !horizontal {
+                camera_move.x -= 1.0;
             }
+            if input.cursor_pos().x > (camera.viewport_size.x - 48.0) && !horizontal {
+                camera_move.x += 1.0;
+            }
+            if input.cursor_pos().y < 48.0 && !vertical {
+                camera_move.y += 1.0;
+            }
+            if input.cursor_pos().y > (camera.viewport_size.y - 48.0) && !vertical {
+                camera_move.y -= 1.0;
+            }
+        }
+        self.pending.camera_move = camera_move;
+        self.pending.cursor_world_pos = cursor_world_pos;
+
+        camera.update_anima(delta);
+        camera.update_effects(delta);
+
+        if keyboard_free && input.if_action_just_pressed(Action::QuickSave) {
+            self.save_request = Some(SaveRequest::QuickSave);
         }
-        if input.cursor_pos().y > (camera.viewport_size.y - 48.0) {
-            if !input.if_keyboard_pressed(&KeyCode::ArrowUp)
-                && !input.if_keyboard_pressed(&KeyCode::ArrowDown)
-            {
-                camera.add_translation(Vec2::new(0.0, -camera_move_step));
+        if keyboard_free && input.if_action_just_pressed(Action::QuickLoad) {
+            self.save_request = Some(SaveRequest::QuickLoad);
+        }
+        if let Some(scene) = &mut self.scene {
+            if keyboard_free && input.if_action_just_pressed(Action::TakeOutItem) {
+                if let Err(err) = scene.take_out_new_item() {
+                    self.errors
+                        .push(Error::Scene(format!("Failed to take out new item: {err}")));
+                }
             }
         }
 
+        // 手柄的 South 和 East 相当于鼠标左右键，点击位置为虚拟光标的位置
+        // 触摸的轻点和长按同样相当于鼠标左右键
+        let click_type = if !pointer_free {
+            0
+        } else if input.if_mouse_just_pressed(&MouseButton::Left)
+            || input.tap().is_some()
+            || (keyboard_free && input.if_gamepad_just_pressed(&GamepadButton::South))
+        {
+            1
+        } else if input.if_mouse_just_pressed(&MouseButton::Right)
+            || input.long_press().is_some()
+            || (keyboard_free && input.if_gamepad_just_pressed(&GamepadButton::East))
+        {
+            2
+        } else {
+            0
+        };
+        // 点击会保留到下一次 fixed_update，保证不会因为这一帧没有执行 fixed_update 而丢失
+        if click_type != 0 {
+            self.pending.click_type = click_type;
+        }
+    }
+
+    /// 按固定步长移动镜头并同步场景
+    pub fn fixed_step(&mut self, step: Duration, input: &mut Input, audio: &mut Audio) {
         let Some(scene) = &self.scene else {
             return;
         };
@@ -190,64 +446,202 @@ impl InGame {
             Vec2::new(scene.size()[0] as f32, scene.size()[1] as f32 - 48.0),
         );
 
-        camera.update_anima(delta);
+        let camera = &mut self.camera;
+        camera.set_translation(self.translation);
+        camera.add_translation(self.pending.camera_move * CAMERA_MOVE_STEP);
+        camera.add_translation(std::mem::take(&mut self.pending.camera_pan));
         camera.update_word_boundary(Some(scene_world_boundary));
-        self.screen_repeat.scale = 1.0 / camera.get_scale();
-
-        let scene_center = Vec2::new(scene.size()[0] as f32, scene.size()[1] as f32);
-        self.screen_repeat.offset = (camera.get_translation() - scene_center) * 0.4;
-        self.ui_zoom_in_sprite.update(camera, delta);
-        self.ui_zoom_out_sprite.update(camera, delta);
+        self.previous_translation = self.translation;
+        self.translation = camera.get_translation();
 
+        let click_type = std::mem::take(&mut self.pending.click_type);
+        let cursor_world_pos = self.pending.cursor_world_pos;
         if let (Some(scene), Some(package)) = (&mut self.scene, &mut self.package) {
-            if input.if_keyboard_just_pressed(&KeyCode::KeyS) {
-                // TODO 这里不能简单的直接序列化，存档需要有一些额外的操作，有些物品是不需要持久化状态的，所以需要保存时将状态重置到初始状态。
-                std::fs::write("src/assets/scenes/SideBoardScene.json", scene.to_bytes()).unwrap();
-                log::info!("Saved scene");
-            }
-            if input.if_keyboard_just_pressed(&KeyCode::KeyT) {
-                scene
-                    .take_out_new_item()
-                    .expect("Failed to take out-new-item");
-            }
-
-            let click_type = if input.if_mouse_just_pressed(&MouseButton::Left) {
-                1
-            } else if input.if_mouse_just_pressed(&MouseButton::Right) {
-                2
-            } else {
-                0
-            };
-            let _sync_result = scene
-                .sync(
-                    delta.as_micros() as u64,
-                    [cursor_world_pos.x as i32, cursor_world_pos.y as i32],
-                    click_type,
-                    package.items.as_slice(),
-                    audio,
-                )
-                .expect("failed to sync scene");
+            let mut feedback = SceneFeedback::new(audio, cursor_world_pos);
+            if let Err(err) = scene.sync(
+                step.as_micros() as u64,
+                [cursor_world_pos.x as i32, cursor_world_pos.y as i32],
+                click_type,
+                package.items.as_slice(),
+                &mut feedback,
+            ) {
+                self.errors
+                    .push(Error::Scene(format!("Failed to sync scene: {err}")));
+            }
+            if feedback.picked_up {
+                self.camera
+                    .effects
+                    .punch_zoom(0.04, Duration::from_millis(180));
+            }
+            if feedback.placed {
+                self.camera.effects.add_trauma(0.35);
+                input.rumble(0.4, Duration::from_millis(120));
+                self.camera
+                    .effects
+                    .flash(Color::new([255, 255, 255, 40]), Duration::from_millis(120));
+            }
 
             self.sprites = collect_sprites(&scene, &self.image_map);
         }
     }
 
-    pub fn render(
-        &self,
-        render: &Render,
-        texture_store: &TextureStore,
-        #[cfg(feature = "editor_mode")] egui_render: &mut crate::egui_render::EguiRender,
-    ) {
+    /// 镜头还在移动或者播放动画
+    pub fn if_animating(&self) -> bool {
+        !self.camera.scale_animator.if_finished()
+            || self.camera.effects.if_active()
+            || self.pending.camera_move != Vec2::ZERO
+            || self.pending.camera_pan != Vec2::ZERO
+            || self.pending.click_type != 0
+            || self.previous_translation != self.translation
+    }
+}
+
+impl GameState for InGame {
+    fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> Transition {
+        self.update_input(delta, ctx.input);
+        // 场景的音效根据镜头的位置和缩放计算声像和衰减
+        ctx.audio
+            .set_listener(Some(Listener::from_camera(&self.camera)));
+        match self.save_request.take() {
+            Some(SaveRequest::QuickSave) => self.save(QUICKSAVE_SLOT, ctx),
+            Some(SaveRequest::QuickLoad) => self.load(QUICKSAVE_SLOT, ctx),
+            None => {}
+        }
+        if self.autosave.tick(delta) {
+            self.save(AUTOSAVE_SLOT, ctx);
+        }
+        ctx.errors.extend(self.take_errors());
+        // 暂停菜单盖在游戏上，暂停时场景不再同步
+        let input = &ctx.input;
+        if input.keyboard_available(InputLayer::Scene)
+            && (input.if_action_just_pressed(Action::Pause)
+                || input.if_gamepad_just_pressed(&GamepadButton::Start))
+        {
+            return Transition::Push(Box::new(PauseMenu::new(ctx.window_size)));
+        }
+        Transition::None
+    }
+
+    fn fixed_update(&mut self, step: Duration, ctx: &mut StateContext) {
+        self.fixed_step(step, ctx.input, ctx.audio);
+        ctx.errors.extend(self.take_errors());
+    }
+
+    /// 游戏中循环播放环境音
+    fn enter(&mut self, ctx: &mut StateContext) {
+        ctx.audio.play_ambient("ambient");
+    }
+
+    /// 离开游戏或者关闭窗口时自动存档
+    fn exit(&mut self, ctx: &mut StateContext) {
+        self.save(AUTOSAVE_SLOT, ctx);
+        ctx.audio.stop_ambient();
+        ctx.audio.set_listener(None);
+    }
+
+    /// 暂停时丢弃还没有执行的操作，恢复后不会继续移动镜头
+    fn pause(&mut self, _ctx: &mut StateContext) {
+        self.clear_pending();
+    }
+
+    fn render(&mut self, alpha: f32) -> RenderGroup<'_> {
+        self.camera
+            .set_translation(self.previous_translation.lerp(self.translation, alpha));
+        self.screen_repeat.scale = 1.0 / self.camera.get_scale();
+        if let Some(scene) = &self.scene {
+            let scene_center = Vec2::new(scene.size()[0] as f32, scene.size()[1] as f32);
+            self.screen_repeat.offset = (self.camera.get_translation() - scene_center) * 0.4;
+        }
+
         let mut sprites: Vec<&Sprite> = self.sprites.iter().collect();
-        sprites.push(&self.ui_zoom_in_sprite.sprite);
-        sprites.push(&self.ui_zoom_out_sprite.sprite);
-        render.render(
-            texture_store,
-            &self.camera,
-            &sprites,
-            Some(&self.screen_repeat),
-            #[cfg(feature = "editor_mode")]
-            egui_render,
-        );
+        sprites.extend(self.widgets.sprites());
+        RenderGroup {
+            passes: vec![
+                CameraPass {
+                    clear: ClearPolicy::Color(Color::new([255, 255, 255, 255])),
+                    screen_repeat: Some(&self.screen_repeat),
+                    ..CameraPass::new(&self.camera)
+                },
+                CameraPass {
+                    layers: RenderLayers::UI,
+                    ..CameraPass::new(&self.ui_camera)
+                },
+            ],
+            sprites,
+        }
+    }
+
+    fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        InGame::resize(self, window_size, scale_factor);
+    }
+
+    fn if_animating(&self) -> bool {
+        InGame::if_animating(self)
+    }
+
+    fn if_gameplay(&self) -> bool {
+        true
+    }
+}
+
+fn zoom_button_visuals(texture_id: AssetsId, slice: &'static [Rect]) -> WidgetVisuals {
+    let normal = WidgetVisual {
+        texture_id,
+        rect: Some(slice[0]),
+        opacity: 0.7,
+        anima: None,
+    };
+    let hovered = WidgetVisual {
+        opacity: 1.0,
+        anima: Some(slice),
+        ..normal
+    };
+    WidgetVisuals {
+        normal,
+        hovered,
+        pressed: hovered,
+        disabled: WidgetVisual {
+            opacity: 0.5,
+            ..normal
+        },
+    }
+}
+
+/// 转发 scene 播放的音效，同时记录物品被拿起或放下，用于触发镜头反馈
+struct SceneFeedback<'a> {
+    audio: &'a mut Audio,
+    /// 这一步点击的位置，场景的音效在这里播放
+    position: Vec2,
+    picked_up: bool,
+    placed: bool,
+}
+
+impl<'a> SceneFeedback<'a> {
+    fn new(audio: &'a mut Audio, position: Vec2) -> Self {
+        Self {
+            audio,
+            position,
+            picked_up: false,
+            placed: false,
+        }
+    }
+}
+
+impl isometric_engine::Audio for SceneFeedback<'_> {
+    fn play(&mut self, sound: &str) -> bool {
+        match sound {
+            "pickup" => self.picked_up = true,
+            "place" => self.placed = true,
+            _ => {}
+        }
+        self.audio.play_scene_sound(sound, Some(self.position))
+    }
+
+    fn pause(&mut self, sound: &str) -> bool {
+        isometric_engine::Audio::pause(self.audio, sound)
+    }
+
+    fn resume(&mut self, sound: &str) -> bool {
+        isometric_engine::Audio::resume(self.audio, sound)
     }
 }
@@ -0,0 +1,361 @@
+use crate::app::in_game::InGame;
+use crate::app::state::{GameState, GpuContext, ScreenTransition, StateContext, Transition};
+use crate::assets::{AssetEntry, AssetServer, LoadState};
+use crate::{
+    decode_image, AudioBus, AudioData, AudioSource, Camera2D, CameraPass, ClearPolicy, Color,
+    Error, RenderGroup, Sprite, Transform, TransitionEffect, WHITE_TEXTURE,
+};
+use glam::{Vec2, Vec3};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use winit::dpi::PhysicalSize;
+
+/// 每帧上传纹理最多使用的时间，超过后留到下一帧，保证加载画面依旧流畅
+const UPLOAD_BUDGET: Duration = Duration::from_millis(4);
+/// 进度条的宽度占画面宽度的比例
+const PROGRESS_BAR_WIDTH: f32 = 0.4;
+const PROGRESS_BAR_HEIGHT: f32 = 8.0;
+/// 加载完成后以圆形开口展开游戏画面的时间
+const IRIS_DURATION: Duration = Duration::from_millis(600);
+
+/// 需要加载的资源
+#[derive(Debug, Copy, Clone)]
+pub enum LoadJob {
+    /// 解码后上传到 GPU，之后释放原始数据
+    Texture(&'static AssetEntry),
+    /// 以这个名称注册到 Audio 中，短音效解码成 PCM 后释放原始数据，边读边解码的音乐不需要读取原始数据
+    Audio(&'static str, &'static AssetEntry),
+    /// 保留原始数据，比如每局游戏都会用到的场景和资源包
+    Keep(&'static AssetEntry),
+}
+
+impl LoadJob {
+    fn entry(&self) -> &'static AssetEntry {
+        match self {
+            LoadJob::Texture(entry) | LoadJob::Audio(_, entry) | LoadJob::Keep(entry) => entry,
+        }
+    }
+}
+
+/// 后台解码完成的资源
+enum Decoded {
+    Texture(
+        &'static AssetEntry,
+        Result<image::RgbaImage, image::ImageError>,
+    ),
+    Audio(&'static str, AudioSource, Result<(), Error>),
+}
+
+/// 加载画面，加载资源并创建新的一局游戏
+///
+/// 原始数据由 AssetServer 加载，native 上在后台线程中解码，web 上在之后的 future 中解码，
+/// 解码完成的纹理每帧在 prepare 中按 UPLOAD_BUDGET 上传到 GPU。资源全部加载完成后创建 InGame，
+/// 资源包中的图片同样分多帧上传。
+pub struct Loading {
+    camera: Camera2D,
+    /// 进度条的背景和填充部分
+    progress_bar: [Sprite; 2],
+    /// 还在等待 AssetServer 加载的资源
+    fetching: Vec<LoadJob>,
+    sender: Sender<Decoded>,
+    receiver: Receiver<Decoded>,
+    /// 解码完成，等待上传到 GPU 的图片
+    uploads: VecDeque<(
+        &'static AssetEntry,
+        Result<image::RgbaImage, image::ImageError>,
+    )>,
+    in_game: Option<InGame>,
+    /// 创建游戏后读取的存档位，比如从自动存档继续游戏
+    save: Option<&'static str>,
+    /// 资源的总数和已经完成的资源数，加载失败的资源同样算作完成
+    total: usize,
+    done: usize,
+}
+
+impl Loading {
+    pub fn new(jobs: &[LoadJob], assets: &mut AssetServer) -> Loading {
+        for job in jobs {
+            if !matches!(job, LoadJob::Audio(_, entry) if stream_data(entry, assets).is_some()) {
+                assets.load(job.entry());
+            }
+        }
+        let (sender, receiver) = channel();
+        let bar = |color: Color, z: f32| Sprite {
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, z)),
+            texture_id: WHITE_TEXTURE,
+            anchor: Vec2::new(-0.5, 0.0),
+            color,
+            ..Default::default()
+        };
+        Loading {
+            camera: Camera2D::default(),
+            progress_bar: [
+                bar(Color::new([48, 48, 48, 255]), 400.0),
+                bar(Color::new([255, 255, 255, 255]), 401.0),
+            ],
+            fetching: jobs.to_vec(),
+            sender,
+            receiver,
+            uploads: VecDeque::new(),
+            in_game: None,
+            save: None,
+            total: jobs.len(),
+            done: 0,
+        }
+    }
+
+    pub fn with_save(self, save: Option<&'static str>) -> Loading {
+        Loading { save, ..self }
+    }
+
+    /// 推进加载，全部完成并且图片都上传到 GPU 后返回创建好的 InGame
+    fn load(&mut self, ctx: &mut StateContext) -> Option<InGame> {
+        self.start_decoding(ctx.assets, ctx.audio.output_sample_rate());
+
+        while let Ok(decoded) = self.receiver.try_recv() {
+            match decoded {
+                Decoded::Texture(entry, image) => self.uploads.push_back((entry, image)),
+                Decoded::Audio(key, source, result) => {
+                    ctx.audio.insert_source(key, source);
+                    if let Err(err) = result {
+                        ctx.errors.report(err);
+                    }
+                    self.done += 1;
+                }
+            }
+        }
+
+        if self.done < self.total {
+            return None;
+        }
+        // 资源都准备好之后才能解析场景，资源包中的图片在之后的 prepare 中上传
+        let Some(in_game) = &self.in_game else {
+            let mut in_game =
+                InGame::new_headless(ctx.assets, ctx.window_size, ctx.settings.default_zoom);
+            ctx.errors.extend(in_game.take_errors());
+            if let Some(slot) = self.save {
+                in_game.load(slot, ctx);
+            }
+            self.in_game = Some(in_game);
+            return None;
+        };
+        if in_game.if_images_uploaded() {
+            self.in_game.take()
+        } else {
+            None
+        }
+    }
+
+    /// 在 UPLOAD_BUDGET 内上传解码完成的纹理，之后是资源包中的图片
+    fn upload(&mut self, gpu: &mut GpuContext) {
+        let start = instant::Instant::now();
+        while start.elapsed() < UPLOAD_BUDGET {
+            let Some((entry, image)) = self.uploads.pop_front() else {
+                break;
+            };
+            if let Err(err) = gpu.texture_store.load_decoded(gpu.render, entry, image) {
+                gpu.errors.report(err);
+            }
+            self.done += 1;
+        }
+        if let Some(in_game) = &mut self.in_game {
+            let budget = UPLOAD_BUDGET.saturating_sub(start.elapsed());
+            if !budget.is_zero() {
+                in_game.upload_images(gpu.render, gpu.texture_store, budget);
+            }
+        }
+    }
+
+    /// 取出 AssetServer 加载完成的原始数据，交给后台解码，音效转换成输出设备的采样率
+    fn start_decoding(&mut self, assets: &mut AssetServer, sample_rate: u32) {
+        let mut index = 0;
+        while index < self.fetching.len() {
+            let job = self.fetching[index];
+            let entry = job.entry();
+            if let LoadJob::Audio(key, entry) = job {
+                if let Some(data) = stream_data(entry, assets) {
+                    let source = AudioSource::from_data(data).with_settings(entry.audio);
+                    self.decode_audio(key, source, sample_rate);
+                    self.fetching.swap_remove(index);
+                    continue;
+                }
+            }
+            match assets.state(&entry.id) {
+                LoadState::Loading => {
+                    index += 1;
+                    continue;
+                }
+                // 加载失败的错误由 AssetServer::poll 返回
+                LoadState::Failed(_) | LoadState::NotLoaded => self.done += 1,
+                LoadState::Loaded => match job {
+                    LoadJob::Keep(_) => self.done += 1,
+                    LoadJob::Texture(entry) => {
+                        let bytes = assets.get(&entry.id).unwrap_or_default();
+                        assets.unload(&entry.id);
+                        let sender = self.sender.clone();
+                        spawn_decode(move || {
+                            let _ = sender.send(Decoded::Texture(entry, decode_image(&bytes)));
+                        });
+                    }
+                    LoadJob::Audio(key, entry) => {
+                        let source = AudioSource::new(assets.get(&entry.id).unwrap_or_default())
+                            .with_settings(entry.audio);
+                        assets.unload(&entry.id);
+                        self.decode_audio(key, source, sample_rate);
+                    }
+                },
+            }
+            self.fetching.swap_remove(index);
+        }
+    }
+
+    /// 短音效解码成 PCM，边读边解码的音乐只检查能否解码
+    fn decode_audio(&self, key: &'static str, mut source: AudioSource, sample_rate: u32) {
+        let sender = self.sender.clone();
+        spawn_decode(move || {
+            let result = if source.settings.stream {
+                source.check(key).inspect(|()| {
+                    // 播放列表需要音乐的长度才能在结束前开始交叉淡入淡出
+                    if source.settings.bus == AudioBus::Music {
+                        source.measure_duration();
+                    }
+                })
+            } else {
+                source.decode_to_pcm(key, sample_rate)
+            };
+            let _ = sender.send(Decoded::Audio(key, source, result));
+        });
+    }
+
+    /// 0 到 1 之间的加载进度，创建 InGame 和上传资源包中的图片算作一个资源
+    pub fn progress(&self) -> f32 {
+        let mut done = self.done as f32;
+        if let Some(in_game) = &self.in_game {
+            let (uploaded, images) = in_game.image_progress();
+            done += uploaded as f32 / images.max(1) as f32;
+        }
+        done / (self.total + 1) as f32
+    }
+}
+
+impl GameState for Loading {
+    fn update(&mut self, _delta: Duration, ctx: &mut StateContext) -> Transition {
+        match self.load(ctx) {
+            // 转场中只更新游戏，加载画面再更新会重复创建游戏
+            Some(in_game) => Transition::Replace(Box::new(in_game))
+                .with_effect(ScreenTransition::new(TransitionEffect::Iris, IRIS_DURATION)),
+            None => Transition::None,
+        }
+    }
+
+    fn prepare(&mut self, gpu: &mut GpuContext) {
+        self.upload(gpu);
+    }
+
+    fn render(&mut self, _alpha: f32) -> RenderGroup<'_> {
+        let width = self.camera.viewport_size.x * PROGRESS_BAR_WIDTH;
+        let progress = self.progress();
+        for (bar, bar_width) in self.progress_bar.iter_mut().zip([width, width * progress]) {
+            bar.transform.translation.x = -width / 2.0;
+            bar.custom_size = Some(Vec2::new(bar_width, PROGRESS_BAR_HEIGHT));
+        }
+        RenderGroup {
+            passes: vec![CameraPass {
+                clear: ClearPolicy::Color(Color::new([0, 0, 0, 255])),
+                ..CameraPass::new(&self.camera)
+            }],
+            sprites: self.progress_bar.iter().collect(),
+        }
+    }
+
+    fn resize(&mut self, window_size: PhysicalSize<u32>, _scale_factor: f32) {
+        self.camera = Camera2D::new(Vec2::new(
+            window_size.width as f32,
+            window_size.height as f32,
+        ));
+    }
+
+    fn if_animating(&self) -> bool {
+        // 需要每帧推进加载和更新进度条
+        true
+    }
+}
+
+/// 边读边解码的音频不需要 AssetServer 读取原始数据，web 上没有本地文件，依旧整个下载
+fn stream_data(entry: &'static AssetEntry, assets: &AssetServer) -> Option<AudioData> {
+    if !entry.audio.stream {
+        return None;
+    }
+    if let Some(bytes) = entry.embedded {
+        return Some(AudioData::Static(bytes));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Some(AudioData::File(assets.source().path(entry)))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = assets;
+        None
+    }
+}
+
+/// native 上在后台线程中执行，web 上没有线程，让出这一帧之后再在 future 中执行
+fn spawn_decode(task: impl FnOnce() + Send + 'static) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(task);
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(async move {
+        yield_to_browser().await;
+        task();
+    });
+}
+
+/// 通过 setTimeout 让浏览器先处理其他事件和渲染
+#[cfg(target_arch = "wasm32")]
+async fn yield_to_browser() {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let scheduled = web_sys::window()
+            .and_then(|window| window.set_timeout_with_callback(&resolve).ok())
+            .is_some();
+        if !scheduled {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::state::TestContext;
+    use crate::{save_slot, SaveCamera, SaveData, AUTOSAVE_SLOT};
+
+    #[test]
+    fn continue_loads_the_save() {
+        let mut data = TestContext::new();
+        let camera = SaveCamera {
+            translation: Vec2::new(12.0, 34.0),
+            pixel_zoom: 2,
+        };
+        let storage = data.storage.as_deref_mut().unwrap();
+        save_slot(
+            storage,
+            AUTOSAVE_SLOT,
+            &SaveData::new(Some(camera), Vec::new()),
+        )
+        .unwrap();
+
+        let mut loading = Loading::new(&[], &mut data.assets).with_save(Some(AUTOSAVE_SLOT));
+        assert!(loading.load(&mut data.ctx()).is_none());
+        let in_game = loading.load(&mut data.ctx()).expect("game");
+        assert_eq!(in_game.camera_translation(), camera.translation);
+
+        // 新游戏不读取存档
+        let mut loading = Loading::new(&[], &mut data.assets);
+        assert!(loading.load(&mut data.ctx()).is_none());
+        let in_game = loading.load(&mut data.ctx()).expect("game");
+        assert_ne!(in_game.camera_translation(), camera.translation);
+    }
+}
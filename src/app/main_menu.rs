@@ -1,82 +1,198 @@
-use crate::assets::{START_HOVER, START_NORMAL};
-use crate::input::Input;
-use crate::{AppState, Audio, Camera2D, Rect, Render, Sprite, TextureStore, Transform};
-use glam::{Vec2, Vec3, Vec3Swizzles};
+use crate::app::loading::{LoadJob, Loading};
+use crate::app::pause_menu::{block_sprite, block_visuals};
+use crate::app::state::{GameState, GpuContext, ScreenTransition, StateContext, Transition};
+use crate::assets::{PACKAGE_SIDEBOARD, SCENE_SIDEBOARD, START_HOVER, START_NORMAL};
+use crate::{
+    AlignItems, Camera2D, CameraPass, ClearPolicy, Color, FlexDirection, JustifyContent,
+    PositionType, RenderGroup, Sprite, Storage, Style, TextureStore, Transform, TransitionEffect,
+    UiNodeId, UiTree, Val, WidgetId, WidgetVisual, WidgetVisuals, Widgets, AUTOSAVE_SLOT,
+};
+use glam::{Vec2, Vec3};
 use std::time::Duration;
 use winit::dpi::PhysicalSize;
-use winit::event::MouseButton;
 
-#[derive(Debug, Default)]
+/// 从主菜单淡出到加载画面的时间
+const FADE_DURATION: Duration = Duration::from_millis(400);
+
+#[derive(Debug)]
 pub struct MainMenu {
     camera: Camera2D,
-    start: Sprite,
+    /// 窗口的 DPI 缩放
+    scale_factor: f32,
+    ui: UiTree,
+    /// 开始按钮的布局节点，大小跟随按钮的纹理
+    ui_start: UiNodeId,
+    widgets: Widgets,
+    start: WidgetId,
+    /// 从自动存档继续游戏，没有自动存档时禁用
+    continue_game: WidgetId,
 }
 
 impl MainMenu {
-    pub fn new(
-        _render: &Render,
-        _texture_store: &mut TextureStore,
-        window_size: PhysicalSize<u32>,
-    ) -> Self {
-        let start = Sprite {
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 400.0)),
-            texture_id: START_NORMAL.0,
-            ..Default::default()
-        };
-
+    /// 纹理可能还没有加载，按钮的大小在 prepare 时更新
+    pub fn new(window_size: PhysicalSize<u32>) -> Self {
         let camera = Camera2D::new(Vec2::new(
             window_size.width as f32,
             window_size.height as f32,
         ));
 
-        MainMenu { camera, start }
+        // 开始按钮和下面的继续按钮居中显示
+        let mut ui = UiTree::default();
+        let ui_screen = ui.new_root(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            right: Val::Px(0.0),
+            top: Val::Px(0.0),
+            bottom: Val::Px(0.0),
+            flex_direction: FlexDirection::Column,
+            gap: Val::Px(12.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        });
+        let ui_start = ui.new_child(ui_screen, Style::default());
+        let start_sprite = Sprite {
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 400.0)),
+            ..Default::default()
+        };
+        let hovered = WidgetVisual::new(START_HOVER.id);
+        let mut widgets = Widgets::default();
+        let start = widgets.add_button(
+            ui_start,
+            start_sprite,
+            WidgetVisuals {
+                normal: WidgetVisual::new(START_NORMAL.id),
+                hovered,
+                pressed: hovered,
+                disabled: WidgetVisual::new(START_NORMAL.id),
+            },
+        );
+        let continue_game = widgets.add_button(
+            ui.new_child(
+                ui_screen,
+                Style {
+                    width: Val::Px(240.0),
+                    height: Val::Px(48.0),
+                    ..Default::default()
+                },
+            ),
+            block_sprite(Color::new([96, 200, 120, 255]), 400.0),
+            block_visuals(),
+        );
+        widgets.set_enabled(continue_game, false);
+
+        MainMenu {
+            camera,
+            scale_factor: 1.0,
+            ui,
+            ui_start,
+            widgets,
+            start,
+            continue_game,
+        }
     }
 
-    pub fn resize(&mut self, window_size: PhysicalSize<u32>) {
-        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+    fn update_continue(&mut self, storage: Option<&dyn Storage>) {
+        let if_autosaved = storage
+            .and_then(|storage| crate::list_slots(storage).ok())
+            .is_some_and(|slots| slots.iter().any(|slot| slot == AUTOSAVE_SLOT));
+        self.widgets.set_enabled(self.continue_game, if_autosaved);
     }
 
-    pub fn update(
-        &mut self,
-        _delta: Duration,
-        input: &Input,
-        _audio: &mut Audio,
-        texture_store: &TextureStore,
-        next_app_state: &mut Option<AppState>,
-    ) {
-        let cursor_world_pos = self
-            .camera
-            .viewport_to_world(Vec2::new(
-                input.cursor_pos().x as f32,
-                input.cursor_pos().y as f32,
-            ))
-            .truncate();
-        if let Some((size, _)) = texture_store.get(&START_NORMAL.0) {
-            let rect = Rect::from_center_size(self.start.transform.translation.xy(), *size);
-            if rect.contains(cursor_world_pos) {
-                self.start.texture_id = START_HOVER.0;
-                if input.if_mouse_just_pressed(&MouseButton::Left) {
-                    *next_app_state = Some(AppState::InGame);
-                }
-            } else {
-                self.start.texture_id = START_NORMAL.0;
-            }
-        }
+    fn update_content_size(&mut self, texture_store: &TextureStore) {
+        self.ui.set_content_size(
+            self.ui_start,
+            texture_store.get(&START_NORMAL.id).map(|(size, _)| *size),
+        );
+    }
+}
+
+impl GameState for MainMenu {
+    fn enter(&mut self, ctx: &mut StateContext) {
+        self.update_continue(ctx.storage.as_deref());
     }
 
-    pub fn render(
-        &self,
-        render: &Render,
-        texture_store: &TextureStore,
-        #[cfg(feature = "editor_mode")] egui_render: &mut crate::egui_render::EguiRender,
-    ) {
-        render.render(
-            texture_store,
-            &self.camera,
-            &vec![&self.start],
-            None,
-            #[cfg(feature = "editor_mode")]
-            egui_render,
+    /// 离开游戏时会自动存档，回到主菜单后继续按钮可能变为可用
+    fn resume(&mut self, ctx: &mut StateContext) {
+        self.update_continue(ctx.storage.as_deref());
+    }
+
+    fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> Transition {
+        self.ui
+            .compute_layout(self.camera.viewport_size, self.scale_factor);
+        self.widgets
+            .update(&self.ui, ctx.input, &self.camera, delta);
+        let save = if self.widgets.clicked(self.start) {
+            None
+        } else if self.widgets.clicked(self.continue_game) {
+            Some(AUTOSAVE_SLOT)
+        } else {
+            return Transition::None;
+        };
+        // 在加载画面中创建新的一局游戏，不在这一帧里阻塞
+        let loading = Loading::new(
+            &[
+                LoadJob::Keep(&SCENE_SIDEBOARD),
+                LoadJob::Keep(&PACKAGE_SIDEBOARD),
+            ],
+            ctx.assets,
         );
+        Transition::Push(Box::new(loading.with_save(save))).with_effect(ScreenTransition::new(
+            TransitionEffect::Fade(Color::new([0, 0, 0, 255])),
+            FADE_DURATION,
+        ))
+    }
+
+    fn prepare(&mut self, gpu: &mut GpuContext) {
+        self.update_content_size(gpu.texture_store);
+    }
+
+    fn render(&mut self, _alpha: f32) -> RenderGroup<'_> {
+        RenderGroup {
+            passes: vec![CameraPass {
+                clear: ClearPolicy::Color(Color::new([0, 0, 0, 255])),
+                ..CameraPass::new(&self.camera)
+            }],
+            sprites: self.widgets.sprites().collect(),
+        }
+    }
+
+    fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+        self.scale_factor = scale_factor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::state::TestContext;
+    use crate::{save_slot, SaveData};
+
+    #[test]
+    fn buttons_are_centered() {
+        let mut menu = MainMenu::new(PhysicalSize::new(1280, 720));
+        menu.ui
+            .set_content_size(menu.ui_start, Some(Vec2::new(200.0, 80.0)));
+        menu.ui.compute_layout(menu.camera.viewport_size, 1.0);
+        // 两个按钮和间隔共 140 像素高
+        let rect = menu.ui.layout(menu.ui_start);
+        assert_eq!(rect.min, Vec2::new(540.0, 290.0));
+        assert_eq!(rect.max, Vec2::new(740.0, 370.0));
+        let rect = menu.ui.layout(menu.widgets.get(menu.continue_game).node);
+        assert_eq!(rect.min, Vec2::new(520.0, 382.0));
+    }
+
+    #[test]
+    fn continue_needs_an_autosave() {
+        let mut data = TestContext::new();
+        let mut menu = MainMenu::new(PhysicalSize::new(1280, 720));
+        menu.enter(&mut data.ctx());
+        assert!(!menu.widgets.get(menu.continue_game).enabled);
+
+        let storage = data.storage.as_deref_mut().unwrap();
+        save_slot(storage, AUTOSAVE_SLOT, &SaveData::new(None, Vec::new())).unwrap();
+        menu.resume(&mut data.ctx());
+        assert!(menu.widgets.get(menu.continue_game).enabled);
     }
 }
@@ -1,53 +1,80 @@
+mod error_overlay;
 mod in_game;
+mod loading;
 mod main_menu;
+mod pause_menu;
+mod replay;
+mod settings_screen;
+mod state;
+
+pub use loading::LoadJob;
+pub use replay::{replay_headless, ReplayOutcome, INPUT_RECORD_ENV, INPUT_REPLAY_ENV};
+pub use state::*;
 
 use super::assets::*;
-use crate::app::in_game::InGame;
+use crate::app::error_overlay::ErrorOverlay;
+use crate::app::loading::Loading;
 use crate::app::main_menu::MainMenu;
-use crate::input::Input;
-use crate::{App, AppConfig, Audio, Fps, Render, Sprite, TextureStore, Transform};
+#[cfg(any(feature = "editor_mode", feature = "windows_wallpaper"))]
+use crate::input::InputLayer;
+use crate::input::{Gamepads, Input, InputReplay, ViewportInfo};
+use crate::{
+    App, AppConfig, Audio, Ducking, Error, ErrorLog, Fps, Render, RenderError, Settings, Sprite,
+    Storage, TextureStore, Transform,
+};
 use glam::{Vec2, Vec3};
 use std::sync::Arc;
 use std::time::Duration;
-use wgpu::SurfaceError;
 use winit::dpi::PhysicalSize;
 use winit::event::WindowEvent;
+#[cfg(feature = "windows_wallpaper")]
 use winit::keyboard::KeyCode;
 use winit::window::Window;
 
 pub struct AppData {
     config: AppConfig,
+    /// 加载资源和运行中出现的错误，显示在画面左下角，editor_mode 下还会显示在调试窗口中
+    errors: ErrorLog,
+    error_overlay: ErrorOverlay,
     input: Input,
+    gamepads: Gamepads,
     render: Render,
     #[cfg(feature = "editor_mode")]
     egui_render: crate::egui_render::EguiRender,
     texture_store: TextureStore,
+    assets: AssetServer,
     audio: Audio,
     size: PhysicalSize<u32>,
+    /// 窗口的 DPI 缩放，用于 UI 布局
+    scale_factor: f32,
     if_size_changed: bool,
     fps: Fps,
     /// 因为 web 上运行时，需要玩家点击了窗口后，才能初始化 AudioContext 所以需要检测第一次点击，重新初始化一遍 audio
     #[cfg(target_arch = "wasm32")]
     if_focused: bool,
     ui_cursor: Sprite,
-    app_state: AppState,
-    next_app_state: Option<AppState>,
-    main_menu: MainMenu,
-    in_game: InGame,
-}
-
-#[derive(Debug, Default, Eq, PartialEq)]
-pub enum AppState {
-    #[default]
-    MainMenu,
-    InGame,
+    /// 主菜单、加载画面和游戏等状态
+    states: StateStack,
+    /// 最后一次同步给状态的画面尺寸，录制和重放都以这个尺寸为准
+    game_viewport: ViewportInfo,
+    /// 录制的输入在退出时保存到这里
+    record_path: Option<std::path::PathBuf>,
+    /// 重放中的录制，重放时忽略实时的输入事件
+    replay: Option<InputReplay>,
+    /// 重放时这一帧需要执行 fixed_update 的次数，重放时不使用 AppHandler 计算的步数
+    replay_fixed_steps: u32,
+    /// 存档和设置的存储，找不到平台的数据目录或者浏览器不支持时为 None
+    storage: Option<Box<dyn Storage>>,
+    /// 玩家的设置，在设置界面中修改，离开设置界面时保存
+    settings: Settings,
 }
 
 impl App for AppData {
-    async fn new(window: Arc<Window>) -> Self {
-        let render = Render::new(window.clone())
-            .await
-            .expect("Failed to create render");
+    async fn new(window: Arc<Window>) -> Result<Self, Error> {
+        let render = Render::new(window.clone()).await?;
+        // 目前只有 web 上打开存储时会出现错误，资源的错误在加载画面中报告
+        #[cfg_attr(not(target_arch = "wasm32"), allow(unused_mut))]
+        let mut errors = ErrorLog::default();
 
         #[cfg(feature = "editor_mode")]
         let egui_render = crate::egui_render::EguiRender::new(
@@ -58,55 +85,97 @@ impl App for AppData {
             &render.config,
         );
 
+        let mut assets = AssetServer::new(AssetSource::default());
         let mut audio = Audio::default();
         audio.resume_audio_context();
-        audio.load_source("pickup", AUDIO_PICKUP.into());
-        audio.load_source("place", AUDIO_PLACE.into());
-        audio.load_source("bgm", AUDIO_BGM.into());
-        audio.load_source("bgm2", AUDIO_BGM_2.into());
-        audio.load_source("ambient", AUDIO_AMBIENT.into());
-        audio.load_source("record_press", AUDIO_RECORD_PRESS.into());
-        // audio.play_sound("bgm");
-        // audio.play_sound("ambient");
-
-        let mut texture_store = TextureStore::default();
-        texture_store.load_texture_raw(&render, UI_CURSOR);
-        texture_store.load_texture_raw(&render, BG_CHECKER);
-        texture_store.load_texture_raw(&render, UI_ZOOM_IN);
-        texture_store.load_texture_raw(&render, UI_ZOOM_OUT);
-        texture_store.load_texture_raw(&render, START_NORMAL);
-        texture_store.load_texture_raw(&render, START_HOVER);
+        // 按下唱片机时压低背景音乐
+        audio.set_ducking(Ducking {
+            sources: vec!["record_press".to_string()],
+            ..Default::default()
+        });
+        let texture_store = TextureStore::new(&render);
 
         let ui_cursor = Sprite {
             transform: Transform::from_translation(Vec3::new(0.0, 0.0, 500.0)),
-            texture_id: UI_CURSOR.0,
+            texture_id: UI_CURSOR.id,
             anchor: Vec2::new(-0.5, 0.5),
             ..Default::default()
         };
 
-        let main_menu = MainMenu::new(&render, &mut texture_store, window.inner_size());
-        let in_game = InGame::new(&render, &mut texture_store, window.inner_size());
+        let mut input = Input::default();
+        let mut game_viewport = ViewportInfo {
+            size: [window.inner_size().width, window.inner_size().height],
+            scale_factor: 1.0,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let storage =
+            crate::FsStorage::platform().map(|storage| Box::new(storage) as Box<dyn Storage>);
+        #[cfg(target_arch = "wasm32")]
+        let storage = match crate::WebStorage::open().await {
+            Ok(storage) => Some(Box::new(storage) as Box<dyn Storage>),
+            Err(err) => {
+                errors.report(err);
+                None
+            }
+        };
+        // 设置无法读取时使用默认设置，下次保存时覆盖
+        let mut settings = match storage.as_deref().map(Settings::load) {
+            Some(Ok(settings)) => settings,
+            Some(Err(err)) => {
+                errors.report(err);
+                Settings::default()
+            }
+            None => Settings::default(),
+        };
+        let mut config = AppConfig::default();
+        settings.apply(&mut config, &mut audio, &mut input);
+
+        let record_path = replay::record_path_from_env();
+        let replay = replay::replay_from_env();
+        if let Some(replay) = &replay {
+            // 重放时使用录制时的画面尺寸和镜头缩放，从一局新的游戏开始
+            game_viewport = replay.recording().viewport;
+            settings.default_zoom = replay.recording().camera_zoom;
+        } else if record_path.is_some() {
+            input.start_recording(game_viewport, config.fixed_timestep, settings.default_zoom);
+        }
+        // 纹理和音频在后台解码，加载画面显示进度，加载完成后直接进入游戏
+        let loading = Loading::new(&startup_jobs(), &mut assets);
 
-        Self {
-            config: AppConfig::default(),
-            input: Input::default(),
+        let mut app = Self {
+            config,
+            errors,
+            error_overlay: ErrorOverlay::new(window.inner_size()),
+            input,
+            gamepads: Gamepads::new(),
             render,
             #[cfg(feature = "editor_mode")]
             egui_render,
             texture_store,
+            assets,
             audio,
             size: window.inner_size(),
+            scale_factor: window.scale_factor() as f32,
             // 默认为 true 确保渲染第一帧前会调整 surface 大小
             if_size_changed: true,
             fps: Fps::new(),
             #[cfg(target_arch = "wasm32")]
             if_focused: false,
             ui_cursor,
-            app_state: AppState::InGame,
-            next_app_state: None,
-            main_menu,
-            in_game,
-        }
+            states: StateStack::default(),
+            game_viewport,
+            record_path,
+            replay,
+            replay_fixed_steps: 0,
+            storage,
+            settings,
+        };
+        let main_menu = MainMenu::new(replay::viewport_size(game_viewport));
+        let (states, mut ctx) = app.split();
+        states.push(Box::new(main_menu), &mut ctx);
+        states.push(Box::new(loading), &mut ctx);
+        Ok(app)
     }
 
     fn get_config(&self) -> &AppConfig {
@@ -119,18 +188,50 @@ impl App for AppData {
     }
 
     fn on_window_input(&mut self, event: &WindowEvent) {
-        // 如果交互事件已经被 egui 消费了，就不需要再传递下去了
+        if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+            self.scale_factor = *scale_factor as f32;
+            self.if_size_changed = true;
+        }
+        // 事件总是同时交给 egui 和 input，由谁使用输入在 update 时按 egui -> ui -> 场景 的顺序决定
         // NOTE 这里包括 RedrawRequested 也会传递下来，所以 egui 也能接收到
         //  但是 input 只会处理输入事件，所以不需要担心
-        // TODO 只是这里依旧不严谨，应该是当 cursor hover 或者 focus 时由 egui 消费交互事件，否则由 app 消费
         #[cfg(feature = "editor_mode")]
-        if self.egui_render.handle_event(event).consumed {
-            return;
+        let _ = self.egui_render.handle_event(event);
+        if self.replay.is_none() {
+            self.input.handle_window_event(event);
+        }
+        if let WindowEvent::CloseRequested = event {
+            self.save_recording();
+            let (states, mut ctx) = self.split();
+            states.exit_all(&mut ctx);
         }
-        self.input.handle_window_event(event);
     }
 
     fn update(&mut self, delta: Duration) {
+        let mut delta = delta;
+        // 游戏开始后才开始重放，录制的第一帧就是新的一局游戏的第一帧
+        if let (Some(replay), true) = (&mut self.replay, self.states.if_gameplay()) {
+            if let Some(frame) = replay.next_frame() {
+                // 使用录制时的 delta 和 fixed_update 次数
+                delta = frame.delta;
+                self.replay_fixed_steps = frame.fixed_steps;
+                if let Some(viewport) = frame.resize {
+                    self.game_viewport = viewport;
+                    self.states
+                        .resize(replay::viewport_size(viewport), viewport.scale_factor);
+                }
+                self.input.replay_frame(frame);
+            } else {
+                log::info!("Input replay finished");
+                self.replay = None;
+            }
+        }
+        if self.replay.is_none() {
+            self.gamepads.poll(&mut self.input);
+        }
+        self.input
+            .begin_frame(delta, replay::viewport_vec2(self.game_viewport));
+
         #[cfg(target_arch = "wasm32")]
         if self
             .input
@@ -142,12 +243,9 @@ impl App for AppData {
             }
         }
 
+        self.audio.update(delta);
         self.audio.clean_finished_sink();
 
-        if let Some(next_app_state) = self.next_app_state.take() {
-            self.app_state = next_app_state;
-        }
-
         self.fps.update();
 
         #[cfg(feature = "editor_mode")]
@@ -155,72 +253,117 @@ impl App for AppData {
             egui::Window::new("Debug Window").show(ctx, |ui| {
                 ui.label("Debug Info:");
                 ui.label(format!("FPS: {:.0}", self.fps.fps));
+                if !self.errors.errors().is_empty() {
+                    ui.separator();
+                    for err in self.errors.errors() {
+                        ui.colored_label(egui::Color32::RED, err.to_string());
+                    }
+                    if ui.button("Clear Errors").clicked() {
+                        self.errors.clear();
+                    }
+                }
             });
         });
-
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyF) {
-            self.config.fullscreen = true;
-        }
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyB) {
-            self.config.decorations = false;
+        // 光标在 egui 窗口上或者 egui 正在输入文字时，由 egui 占用输入，重放时使用录制的结果
+        #[cfg(feature = "editor_mode")]
+        if self.replay.is_none() {
+            if self.egui_render.wants_pointer_input() {
+                self.input.claim_pointer(InputLayer::Egui);
+            }
+            if self.egui_render.wants_keyboard_input() {
+                self.input.claim_keyboard(InputLayer::Egui);
+            }
         }
+
         #[cfg(feature = "windows_wallpaper")]
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyW) {
+        if self.input.keyboard_claim() != Some(InputLayer::Egui)
+            && self.input.if_keyboard_just_pressed(&KeyCode::KeyW)
+        {
             self.config.set_as_wallpaper = true;
         }
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyL) {
-            if let Some(sink) = self.audio.get_sink(0) {
-                sink.set_volume(0.1);
-            }
-            if let Some(sink) = self.audio.get_sink(1) {
-                sink.set_volume(0.1);
-            }
+
+        let (states, mut ctx) = self.split();
+        let new_game = states.update(delta, &mut ctx);
+        if let (Some(replay), true) = (&self.replay, new_game) {
+            // 重放时使用录制时的输入状态
+            self.input = Input::from_snapshot(&replay.recording().initial);
         }
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyU) {
-            if let Some(sink) = self.audio.get_sink(0) {
-                sink.set_volume(1.0);
-            }
-            if let Some(sink) = self.audio.get_sink(1) {
-                sink.set_volume(1.0);
-            }
+
+        self.gamepads.apply_rumble(&mut self.input);
+        if self.states.if_paused() {
+            self.input.record_paused();
         }
-        if self.input.if_keyboard_just_pressed(&KeyCode::KeyP) {
-            self.audio.play_sound_with_volume("bgm", 0.4);
+        self.input.record_frame(delta);
+        self.input.fresh();
+        // 开始了新的一局游戏，之前的录制已经无法重放了，从下一帧开始重新录制
+        if self.input.if_recording() && new_game {
+            self.input.start_recording(
+                self.game_viewport,
+                self.config.fixed_timestep,
+                self.settings.default_zoom,
+            );
         }
 
-        match self.app_state {
-            AppState::MainMenu => {
-                self.main_menu.update(
-                    delta,
-                    &self.input,
-                    &mut self.audio,
-                    &self.texture_store,
-                    &mut self.next_app_state,
-                );
-                if self.next_app_state == Some(AppState::InGame) {
-                    self.in_game = InGame::new(&self.render, &mut self.texture_store, self.size);
-                }
-            }
-            AppState::InGame => {
-                self.in_game.update(delta, &self.input, &mut self.audio);
+        if let Some(replay) = &self.replay {
+            let step = replay.recording().fixed_timestep;
+            let fixed_steps = std::mem::take(&mut self.replay_fixed_steps);
+            let (states, mut ctx) = self.split();
+            for _ in 0..fixed_steps {
+                states.fixed_update(step, &mut ctx);
             }
         }
+        let asset_errors = self.assets.poll();
+        self.errors.extend(asset_errors);
+        if let Some(storage) = &mut self.storage {
+            self.errors.extend(storage.take_errors());
+        }
+        self.error_overlay.update(delta, &self.errors);
+    }
 
-        self.input.fresh();
+    fn fixed_update(&mut self, dt: Duration) {
+        // 重放时按录制的次数在 update 中执行
+        if self.replay.is_some() {
+            return;
+        }
+        let (states, mut ctx) = self.split();
+        states.fixed_update(dt, &mut ctx);
+        if self.states.if_gameplay() {
+            self.input.record_fixed_step();
+        }
     }
 
-    fn render(&mut self) -> Result<(), SurfaceError> {
+    fn if_need_redraw(&self) -> bool {
+        // 手柄的输入需要每帧主动读取，不会唤醒窗口
+        self.replay.is_some()
+            || self.input.if_any_pressed()
+            || self.input.gamepad_connected()
+            || self.states.if_animating()
+            || self.audio.if_animating()
+            || self.error_overlay.if_visible()
+    }
+
+    fn render(&mut self, alpha: f32) -> Result<(), RenderError> {
         #[cfg(feature = "profiling")]
         profiling::scope!("Render Frame");
+        self.render
+            .set_present_mode(self.config.present_mode, self.config.max_frame_latency);
         if self.if_size_changed {
-            self.main_menu.resize(self.size);
-            self.in_game.resize(self.size);
+            // 重放时游戏画面保持录制时的尺寸
+            if self.replay.is_none() {
+                self.game_viewport = ViewportInfo {
+                    size: [self.size.width, self.size.height],
+                    scale_factor: self.scale_factor,
+                };
+                self.states.resize(self.size, self.scale_factor);
+                self.input.record_resize(self.game_viewport);
+            }
             // NOTE 之前把 surface_configure 放在这里，发现缩放窗口时会卡顿，于是就移到了外面，每帧都重新 surface_configure
             //  但是后来发现有性能问题，帧率一直很低，只有 200-300 FPS，远低于 bevy 的性能
             //  于是用 Tracy Profiler 测试了一下，发现每帧大部分时间都花在了 surface_configure 上（3ms左右）
             //  现在将 surface_configure 移回这里，性能大大增高，能到 4000 FPS，同时，也没有发现缩放窗口卡顿的问题
             //  虽然不知道之前缩放窗口卡顿的问题是为什么，但先这样吧
             self.render.resize(self.size.width, self.size.height);
+            self.error_overlay.resize(self.size, self.scale_factor);
             #[cfg(feature = "editor_mode")]
             {
                 self.egui_render.context.set_pixels_per_point(1.0);
@@ -229,30 +372,119 @@ impl App for AppData {
             self.if_size_changed = false;
         }
 
+        self.states.prepare(&mut GpuContext {
+            render: &self.render,
+            texture_store: &mut self.texture_store,
+            errors: &mut self.errors,
+        });
+        self.error_overlay
+            .prepare(&self.errors, &self.render, &mut self.texture_store);
         // 窗口最小化时只更新数据不渲染画面
+        let mut result = Ok(());
         if self.size.width > 0 && self.size.height > 0 {
-            match self.app_state {
-                AppState::MainMenu => {
-                    self.main_menu.render(
-                        &self.render,
-                        &self.texture_store,
-                        #[cfg(feature = "editor_mode")]
-                        &mut self.egui_render,
-                    );
-                }
-                AppState::InGame => {
-                    self.in_game.render(
-                        &self.render,
-                        &self.texture_store,
-                        #[cfg(feature = "editor_mode")]
-                        &mut self.egui_render,
-                    );
-                }
-            }
+            let (mut groups, transition) = self.states.render(alpha);
+            groups.extend(self.error_overlay.render());
+            result = self.render.render(
+                &self.texture_store,
+                &groups,
+                transition.as_ref(),
+                #[cfg(feature = "editor_mode")]
+                &mut self.egui_render,
+            );
+        }
+        if result == Err(RenderError::DeviceLost) {
+            self.recover_device();
         }
         #[cfg(feature = "profiling")]
         profiling::finish_frame!();
 
-        Ok(())
+        result
     }
 }
+
+impl AppData {
+    /// 分别借用状态栈和其他共享数据，用于调用状态的方法
+    fn split(&mut self) -> (&mut StateStack, StateContext<'_>) {
+        (
+            &mut self.states,
+            StateContext {
+                assets: &mut self.assets,
+                audio: &mut self.audio,
+                input: &mut self.input,
+                config: &mut self.config,
+                errors: &mut self.errors,
+                storage: &mut self.storage,
+                settings: &mut self.settings,
+                window_size: replay::viewport_size(self.game_viewport),
+                scale_factor: self.game_viewport.scale_factor,
+                if_replaying: self.replay.is_some(),
+            },
+        )
+    }
+
+    /// 设备丢失后重新创建 Render，并用保留的图片重新上传所有纹理
+    fn recover_device(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Err(err) = pollster::block_on(self.render.recreate_device()) {
+                self.errors.report(err);
+                return;
+            }
+            self.texture_store.reload(&self.render);
+            #[cfg(feature = "editor_mode")]
+            {
+                self.egui_render = crate::egui_render::EguiRender::new(
+                    self.render.window().clone(),
+                    &self.render.device,
+                    crate::TEXTURE_FORMAT,
+                    crate::MASK_TEXTURE_FORMAT,
+                    &self.render.config,
+                );
+            }
+            self.if_size_changed = true;
+        }
+        // web 端不能阻塞等待重新创建设备，像关闭窗口一样退出所有状态自动存档后刷新页面，
+        // 刷新后可以在主菜单继续游戏
+        #[cfg(target_arch = "wasm32")]
+        if let Some(window) = web_sys::window() {
+            let (states, mut ctx) = self.split();
+            states.exit_all(&mut ctx);
+            let _ = window.location().reload();
+        }
+    }
+
+    /// 保存录制的输入，之后可以通过 INPUT_REPLAY 环境变量重放
+    fn save_recording(&mut self) {
+        let (Some(path), Some(recording)) = (&self.record_path, self.input.stop_recording()) else {
+            return;
+        };
+        match std::fs::write(path, recording.to_bytes()) {
+            Ok(_) => log::info!(
+                "Saved {} frames of input to {path:?}",
+                recording.frames.len()
+            ),
+            Err(source) => self.errors.report(Error::Io {
+                path: path.clone(),
+                source,
+            }),
+        }
+    }
+}
+
+/// 启动时加载的资源
+fn startup_jobs() -> Vec<LoadJob> {
+    STARTUP_TEXTURES
+        .iter()
+        .map(|entry| LoadJob::Texture(entry))
+        .chain(
+            STARTUP_AUDIO
+                .iter()
+                .map(|(key, entry)| LoadJob::Audio(key, entry)),
+        )
+        // 场景和资源包在每次开始新的一局游戏时都会用到，一直保留
+        .chain([
+            LoadJob::Keep(&SCENE_SIDEBOARD),
+            LoadJob::Keep(&PACKAGE_SIDEBOARD),
+        ])
+        .collect()
+}
@@ -0,0 +1,229 @@
+use crate::app::settings_screen::SettingsScreen;
+use crate::app::state::{GameState, GpuContext, ScreenTransition, StateContext, Transition};
+use crate::input::{Action, GamepadButton, Input, InputLayer};
+use crate::{
+    AlignItems, Camera2D, CameraPass, Color, FlexDirection, JustifyContent, Label, PositionType,
+    RenderGroup, Sprite, Style, Transform, TransitionEffect, UiNodeId, UiSprite, UiTree, Val,
+    WidgetId, WidgetVisual, WidgetVisuals, Widgets, WHITE_TEXTURE,
+};
+use glam::{Vec2, Vec3};
+use std::time::Duration;
+use winit::dpi::PhysicalSize;
+
+/// 回到主菜单时淡出的时间
+const FADE_DURATION: Duration = Duration::from_millis(400);
+
+/// 按钮上文字的点阵像素边长
+pub(super) const TEXT_SIZE: u32 = 2;
+/// 浅色按钮上的文字颜色
+pub(super) const BUTTON_TEXT_COLOR: [u8; 4] = [24, 24, 24, 255];
+
+/// 游戏中按下暂停键后覆盖在游戏上的菜单
+///
+/// 游戏留在栈中但不再更新，所以场景不会同步，画面保持在暂停前的最后一帧。
+/// 三个按钮从上到下依次为继续游戏、设置和回到主菜单，按钮上的文字用内置的点阵字体显示。
+#[derive(Debug)]
+pub struct PauseMenu {
+    camera: Camera2D,
+    /// 窗口的 DPI 缩放
+    scale_factor: f32,
+    ui: UiTree,
+    ui_screen: UiNodeId,
+    /// 压暗下面游戏画面的半透明背景
+    backdrop: UiSprite,
+    widgets: Widgets,
+    resume: WidgetId,
+    settings: WidgetId,
+    main_menu: WidgetId,
+    labels: Vec<Label>,
+}
+
+impl PauseMenu {
+    pub fn new(window_size: PhysicalSize<u32>) -> PauseMenu {
+        let camera = Camera2D::new(Vec2::new(
+            window_size.width as f32,
+            window_size.height as f32,
+        ));
+        let mut ui = UiTree::default();
+        let ui_screen = ui.new_root(screen_style());
+        let mut widgets = Widgets::default();
+        let mut labels = Vec::new();
+        let mut add_button = |text: &str, color: Color| {
+            let node = ui.new_child(ui_screen, button_style(240.0, 48.0));
+            labels.push(Label::new(
+                ui.new_child(node, Style::default()),
+                text,
+                TEXT_SIZE,
+                Color::new(BUTTON_TEXT_COLOR),
+                402.0,
+            ));
+            widgets.add_button(node, block_sprite(color, 401.0), block_visuals())
+        };
+        let resume = add_button("Resume", Color::new([96, 200, 120, 255]));
+        let settings = add_button("Settings", Color::new([220, 220, 220, 255]));
+        let main_menu = add_button("Main menu", Color::new([220, 96, 96, 255]));
+
+        PauseMenu {
+            camera,
+            scale_factor: 1.0,
+            ui,
+            ui_screen,
+            backdrop: UiSprite {
+                sprite: block_sprite(Color::new([0, 0, 0, 160]), 400.0),
+                ..Default::default()
+            },
+            widgets,
+            resume,
+            settings,
+            main_menu,
+            labels,
+        }
+    }
+}
+
+impl GameState for PauseMenu {
+    fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> Transition {
+        self.ui
+            .compute_layout(self.camera.viewport_size, self.scale_factor);
+        self.backdrop
+            .update(self.ui.layout(self.ui_screen), &self.camera, delta);
+        self.widgets
+            .update(&self.ui, ctx.input, &self.camera, delta);
+        for label in &mut self.labels {
+            label.update(&self.ui, &self.camera);
+        }
+
+        if self.widgets.clicked(self.resume) || if_back_pressed(ctx.input) {
+            return Transition::Pop;
+        }
+        if self.widgets.clicked(self.settings) {
+            return Transition::Push(Box::new(SettingsScreen::new(ctx.window_size, ctx.settings)));
+        }
+        if self.widgets.clicked(self.main_menu) {
+            // 离开游戏时游戏会自动存档
+            return Transition::PopToRoot.with_effect(ScreenTransition::new(
+                TransitionEffect::Fade(Color::new([0, 0, 0, 255])),
+                FADE_DURATION,
+            ));
+        }
+        Transition::None
+    }
+
+    fn prepare(&mut self, gpu: &mut GpuContext) {
+        for label in &mut self.labels {
+            label.prepare(
+                &mut self.ui,
+                gpu.render,
+                gpu.texture_store,
+                self.scale_factor,
+            );
+        }
+    }
+
+    fn render(&mut self, _alpha: f32) -> RenderGroup<'_> {
+        RenderGroup {
+            passes: vec![CameraPass::new(&self.camera)],
+            sprites: std::iter::once(&self.backdrop.sprite)
+                .chain(self.widgets.sprites())
+                .chain(self.labels.iter().filter_map(Label::sprite))
+                .collect(),
+        }
+    }
+
+    fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+        self.scale_factor = scale_factor;
+    }
+
+    fn if_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// 再次按下暂停键或者手柄的 Start 键时返回上一层
+pub(super) fn if_back_pressed(input: &Input) -> bool {
+    input.keyboard_available(InputLayer::Ui)
+        && (input.if_action_just_pressed(Action::Pause)
+            || input.if_gamepad_just_pressed(&GamepadButton::Start))
+}
+
+/// 铺满整个画面，子节点从上到下居中排列
+pub(super) fn screen_style() -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        left: Val::Px(0.0),
+        right: Val::Px(0.0),
+        top: Val::Px(0.0),
+        bottom: Val::Px(0.0),
+        flex_direction: FlexDirection::Column,
+        gap: Val::Px(12.0),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    }
+}
+
+/// 固定大小的按钮，文字在中间
+pub(super) fn button_style(width: f32, height: f32) -> Style {
+    Style {
+        width: Val::Px(width),
+        height: Val::Px(height),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    }
+}
+
+/// 纯色的方块，颜色由 sprite 决定，控件的外观只改变透明度
+pub(super) fn block_sprite(color: Color, z: f32) -> Sprite {
+    Sprite {
+        transform: Transform::from_translation(Vec3::new(0.0, 0.0, z)),
+        texture_id: WHITE_TEXTURE,
+        color,
+        ..Default::default()
+    }
+}
+
+pub(super) fn block_visuals() -> WidgetVisuals {
+    let normal = WidgetVisual {
+        opacity: 0.75,
+        ..WidgetVisual::new(WHITE_TEXTURE)
+    };
+    let hovered = WidgetVisual::new(WHITE_TEXTURE);
+    WidgetVisuals {
+        normal,
+        hovered,
+        pressed: hovered,
+        disabled: WidgetVisual {
+            opacity: 0.3,
+            ..normal
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_covers_the_viewport() {
+        const VIEWPORT: Vec2 = Vec2::new(1280.0, 720.0);
+        let mut menu = PauseMenu::new(PhysicalSize::new(1280, 720));
+        menu.ui.compute_layout(VIEWPORT, 1.0);
+        let screen = menu.ui.layout(menu.ui_screen);
+        assert_eq!((screen.min, screen.max), (Vec2::ZERO, VIEWPORT));
+        // 三个按钮和两个间隔共 168 像素高，在画面中间
+        let resume = menu.ui.layout(menu.widgets.get(menu.resume).node);
+        assert_eq!(resume.min, Vec2::new(520.0, 276.0));
+
+        // 按钮上的文字在按钮中间
+        let texts: Vec<&str> = menu.labels.iter().map(Label::text).collect();
+        assert_eq!(texts, ["Resume", "Settings", "Main menu"]);
+        menu.ui
+            .set_content_size(menu.labels[0].node, Some(Vec2::new(80.0, 24.0)));
+        menu.ui.compute_layout(VIEWPORT, 1.0);
+        let label = menu.ui.layout(menu.labels[0].node);
+        assert_eq!(label.center(), resume.center());
+        assert_eq!(label.size(), Vec2::new(80.0, 24.0));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::app::in_game::InGame;
+use crate::assets::AssetServer;
+use crate::input::{Input, InputRecording, InputReplay, ViewportInfo};
+use crate::Audio;
+use glam::Vec2;
+use isometric_engine::Scene;
+use winit::dpi::PhysicalSize;
+
+/// 设置后会录制游戏中的输入，退出时保存到这个路径
+pub const INPUT_RECORD_ENV: &str = "INPUT_RECORD";
+/// 设置后启动时直接进入游戏，并重放这个路径下的录制，重放结束后恢复实时输入
+pub const INPUT_REPLAY_ENV: &str = "INPUT_REPLAY";
+
+pub(crate) fn viewport_size(viewport: ViewportInfo) -> PhysicalSize<u32> {
+    PhysicalSize::new(viewport.size[0], viewport.size[1])
+}
+
+pub(crate) fn viewport_vec2(viewport: ViewportInfo) -> Vec2 {
+    Vec2::new(viewport.size[0] as f32, viewport.size[1] as f32)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn record_path_from_env() -> Option<std::path::PathBuf> {
+    std::env::var_os(INPUT_RECORD_ENV).map(Into::into)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn record_path_from_env() -> Option<std::path::PathBuf> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn replay_from_env() -> Option<InputReplay> {
+    let path = std::env::var_os(INPUT_REPLAY_ENV)?;
+    let recording = std::fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| InputRecording::from_bytes(&bytes).map_err(|err| err.to_string()));
+    match recording {
+        Ok(recording) => {
+            log::info!(
+                "Replaying {} frames of input from {path:?}",
+                recording.frames.len()
+            );
+            Some(InputReplay::new(recording))
+        }
+        Err(err) => {
+            log::error!("Failed to load input recording {path:?}: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn replay_from_env() -> Option<InputReplay> {
+    None
+}
+
+/// 无窗口重放结束时的状态
+#[derive(Debug)]
+pub struct ReplayOutcome {
+    /// 场景或者资源包没有加载时为 None
+    pub scene: Option<Scene>,
+    /// 最后一次 fixed_step 后镜头的位置
+    pub camera_translation: Vec2,
+    /// 输入让镜头移动的总距离，没有限制在场景的边界内
+    pub camera_offset: Vec2,
+    /// 交给场景的点击次数，暂停时的点击不会计入
+    pub clicks: usize,
+    /// 执行的 fixed_step 次数
+    pub fixed_steps: usize,
+}
+
+/// 不创建窗口和 GPU 资源，用录制的输入驱动一局新的游戏，返回重放结束时的状态
+///
+/// 每帧的处理顺序与 `AppData::update` 保持一致，所以只要录制时场景也是从头开始的，
+/// 就能得到与录制时完全相同的场景状态。场景和资源包需要提前在 assets 中加载完成。
+pub fn replay_headless(recording: &InputRecording, assets: &AssetServer) -> ReplayOutcome {
+    let mut viewport = recording.viewport;
+    let mut in_game = InGame::new_headless(assets, viewport_size(viewport), recording.camera_zoom);
+    in_game.resize(viewport_size(viewport), viewport.scale_factor);
+    let mut input = Input::from_snapshot(&recording.initial);
+    let mut audio = Audio::default();
+    let mut camera_offset = Vec2::ZERO;
+    let mut clicks = 0;
+    let mut fixed_steps = 0;
+
+    let mut replay = InputReplay::new(recording.clone());
+    // 暂停时的输入交给了暂停菜单，游戏只在暂停开始时丢弃还没有执行的操作
+    let mut paused = false;
+    while let Some(frame) = replay.next_frame() {
+        if let Some(resize) = frame.resize {
+            viewport = resize;
+            in_game.resize(viewport_size(viewport), viewport.scale_factor);
+        }
+        input.replay_frame(frame);
+        input.begin_frame(frame.delta, viewport_vec2(viewport));
+        if !paused {
+            in_game.update_input(frame.delta, &mut input);
+            if frame.paused {
+                in_game.clear_pending();
+            }
+        }
+        input.fresh();
+        paused = frame.paused;
+        if !paused {
+            for _ in 0..frame.fixed_steps {
+                camera_offset += in_game.pending_camera_offset();
+                if in_game.if_click_pending() {
+                    clicks += 1;
+                }
+                in_game.fixed_step(recording.fixed_timestep, &mut input, &mut audio);
+                fixed_steps += 1;
+            }
+        }
+    }
+    ReplayOutcome {
+        camera_translation: in_game.camera_translation(),
+        camera_offset,
+        scene: in_game.into_scene(),
+        clicks,
+        fixed_steps,
+    }
+}
@@ -0,0 +1,557 @@
+use crate::app::pause_menu::{
+    block_sprite, block_visuals, button_style, if_back_pressed, screen_style, BUTTON_TEXT_COLOR,
+    TEXT_SIZE,
+};
+use crate::app::state::{GameState, GpuContext, StateContext, Transition};
+use crate::input::{key_name, Action, GamepadButton, KeyBindings};
+use crate::{
+    AlignItems, Camera2D, CameraPass, Color, FlexDirection, Label, RenderGroup, Settings, Style,
+    UiNodeId, UiSprite, UiTree, Val, WidgetId, WidgetKind, WidgetVisuals, Widgets,
+};
+use glam::Vec2;
+use std::time::Duration;
+use winit::dpi::PhysicalSize;
+use winit::event::MouseButton;
+
+/// 默认缩放的滑块对应的档位
+const ZOOM_LEVELS: [u8; 4] = [1, 2, 4, 8];
+
+/// 设置的每一行中名字的宽度和整行的宽度，同样宽的行居中后左右对齐
+const ROW_WIDTHS: [f32; 2] = [200.0, 448.0];
+/// 按键绑定的每一行中名字的宽度和整行的宽度
+const BINDING_ROW_WIDTHS: [f32; 2] = [168.0, 320.0];
+const TEXT_COLOR: [u8; 4] = [240, 240, 240, 255];
+const SLIDER_COLOR: [u8; 4] = [96, 96, 96, 255];
+const TOGGLE_COLOR: [u8; 4] = [220, 220, 220, 255];
+const BINDING_COLOR: [u8; 4] = [120, 160, 220, 255];
+/// 等待玩家按下新按键时绑定按钮的颜色
+const WAITING_COLOR: [u8; 4] = [240, 200, 80, 255];
+
+/// 设置界面，修改后立即应用，离开时保存
+///
+/// 从上到下依次为主音量、音乐和音效的音量，全屏和窗口边框的开关，新游戏的默认缩放，
+/// 每个操作的按键绑定，恢复默认按键和返回，每一行左边显示名字，绑定按钮上显示当前的按键。
+/// 点击绑定按钮后按下新的按键完成绑定，
+/// 点击鼠标或者按手柄的 East 键取消，任何按键（包括 Esc）都可以绑定。
+#[derive(Debug)]
+pub struct SettingsScreen {
+    camera: Camera2D,
+    /// 窗口的 DPI 缩放
+    scale_factor: f32,
+    ui: UiTree,
+    ui_screen: UiNodeId,
+    backdrop: UiSprite,
+    widgets: Widgets,
+    master_volume: WidgetId,
+    music_volume: WidgetId,
+    sfx_volume: WidgetId,
+    fullscreen: WidgetId,
+    decorations: WidgetId,
+    default_zoom: WidgetId,
+    bindings: Vec<(Action, WidgetId)>,
+    reset_bindings: WidgetId,
+    back: WidgetId,
+    /// 每一行的名字和按钮上的文字
+    labels: Vec<Label>,
+    /// 绑定按钮上显示的按键，和 bindings 的顺序相同
+    key_labels: Vec<Label>,
+    /// 正在等待新按键的操作
+    rebinding: Option<Action>,
+    /// 设置被修改过，离开时需要保存
+    if_changed: bool,
+}
+
+impl SettingsScreen {
+    pub fn new(window_size: PhysicalSize<u32>, settings: &Settings) -> SettingsScreen {
+        let camera = Camera2D::new(Vec2::new(
+            window_size.width as f32,
+            window_size.height as f32,
+        ));
+        let mut ui = UiTree::default();
+        let ui_screen = ui.new_root(screen_style());
+        let row_style = Style {
+            flex_direction: FlexDirection::Row,
+            gap: Val::Px(8.0),
+            align_items: AlignItems::Center,
+            ..Default::default()
+        };
+        let slider_style = Style {
+            width: Val::Px(240.0),
+            height: Val::Px(24.0),
+            ..Default::default()
+        };
+        let square_style = |size: f32| Style {
+            width: Val::Px(size),
+            height: Val::Px(size),
+            ..Default::default()
+        };
+        let mut labels = Vec::new();
+        // 左边是固定宽度的名字，右边是控件
+        let mut add_row = |ui: &mut UiTree, parent: UiNodeId, text: &str, widths: [f32; 2]| {
+            let row = ui.new_child(
+                parent,
+                Style {
+                    width: Val::Px(widths[1]),
+                    ..row_style
+                },
+            );
+            let name = ui.new_child(
+                row,
+                Style {
+                    width: Val::Px(widths[0]),
+                    ..Default::default()
+                },
+            );
+            labels.push(Label::new(
+                ui.new_child(name, Style::default()),
+                text,
+                TEXT_SIZE,
+                Color::new(TEXT_COLOR),
+                402.0,
+            ));
+            row
+        };
+
+        let mut widgets = Widgets::default();
+        let add_slider =
+            |ui: &mut UiTree, widgets: &mut Widgets, row: UiNodeId, value: f32, step: f32| {
+                widgets.add_slider(
+                    ui.new_child(row, slider_style),
+                    block_sprite(Color::new(SLIDER_COLOR), 401.0),
+                    block_visuals(),
+                    block_visuals(),
+                    value,
+                    step,
+                )
+            };
+        let row = add_row(&mut ui, ui_screen, "Master volume", ROW_WIDTHS);
+        let master_volume = add_slider(&mut ui, &mut widgets, row, settings.master_volume, 0.1);
+        let row = add_row(&mut ui, ui_screen, "Music volume", ROW_WIDTHS);
+        let music_volume = add_slider(&mut ui, &mut widgets, row, settings.music_volume, 0.1);
+        let row = add_row(&mut ui, ui_screen, "SFX volume", ROW_WIDTHS);
+        let sfx_volume = add_slider(&mut ui, &mut widgets, row, settings.sfx_volume, 0.1);
+
+        let add_toggle = |ui: &mut UiTree, widgets: &mut Widgets, row: UiNodeId, on: bool| {
+            widgets.add_toggle(
+                ui.new_child(row, square_style(32.0)),
+                block_sprite(Color::new(TOGGLE_COLOR), 401.0),
+                toggle_off_visuals(),
+                block_visuals(),
+                on,
+            )
+        };
+        let row = add_row(&mut ui, ui_screen, "Fullscreen", ROW_WIDTHS);
+        let fullscreen = add_toggle(&mut ui, &mut widgets, row, settings.fullscreen);
+        let row = add_row(&mut ui, ui_screen, "Window border", ROW_WIDTHS);
+        let decorations = add_toggle(&mut ui, &mut widgets, row, settings.decorations);
+
+        let zoom_step = 1.0 / (ZOOM_LEVELS.len() - 1) as f32;
+        let row = add_row(&mut ui, ui_screen, "Default zoom", ROW_WIDTHS);
+        let default_zoom = widgets.add_slider(
+            ui.new_child(row, slider_style),
+            block_sprite(Color::new(SLIDER_COLOR), 401.0),
+            block_visuals(),
+            block_visuals(),
+            zoom_index(settings.default_zoom) as f32 * zoom_step,
+            zoom_step,
+        );
+
+        // 按键绑定分成两列，每个按钮上显示当前绑定的按键
+        let binding_columns = ui.new_child(
+            ui_screen,
+            Style {
+                gap: Val::Px(24.0),
+                ..row_style
+            },
+        );
+        let column_style = Style {
+            flex_direction: FlexDirection::Column,
+            gap: Val::Px(8.0),
+            ..Default::default()
+        };
+        let columns = [
+            ui.new_child(binding_columns, column_style),
+            ui.new_child(binding_columns, column_style),
+        ];
+        let mut key_labels = Vec::new();
+        let bindings = Action::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(index, action)| {
+                let column = columns[index * columns.len() / Action::ALL.len()];
+                let row = add_row(&mut ui, column, action.name(), BINDING_ROW_WIDTHS);
+                let node = ui.new_child(row, button_style(144.0, 28.0));
+                key_labels.push(Label::new(
+                    ui.new_child(node, Style::default()),
+                    key_name(settings.key_bindings.key(action)),
+                    TEXT_SIZE,
+                    Color::new(BUTTON_TEXT_COLOR),
+                    402.0,
+                ));
+                let id = widgets.add_button(
+                    node,
+                    block_sprite(Color::new(BINDING_COLOR), 401.0),
+                    block_visuals(),
+                );
+                (action, id)
+            })
+            .collect();
+
+        let buttons = ui.new_child(ui_screen, row_style);
+        let mut add_button = |text: &str, color: [u8; 4]| {
+            let node = ui.new_child(buttons, button_style(160.0, 48.0));
+            labels.push(Label::new(
+                ui.new_child(node, Style::default()),
+                text,
+                TEXT_SIZE,
+                Color::new(BUTTON_TEXT_COLOR),
+                402.0,
+            ));
+            widgets.add_button(
+                node,
+                block_sprite(Color::new(color), 401.0),
+                block_visuals(),
+            )
+        };
+        let reset_bindings = add_button("Reset keys", [220, 96, 96, 255]);
+        let back = add_button("Back", [96, 200, 120, 255]);
+
+        SettingsScreen {
+            camera,
+            scale_factor: 1.0,
+            ui,
+            ui_screen,
+            backdrop: UiSprite {
+                sprite: block_sprite(Color::new([0, 0, 0, 220]), 400.0),
+                ..Default::default()
+            },
+            widgets,
+            master_volume,
+            music_volume,
+            sfx_volume,
+            fullscreen,
+            decorations,
+            default_zoom,
+            bindings,
+            reset_bindings,
+            back,
+            labels,
+            key_labels,
+            rebinding: None,
+            if_changed: false,
+        }
+    }
+
+    /// 等待新按键时使用这一帧的输入完成绑定，返回是否还在等待
+    fn update_rebinding(&mut self, ctx: &mut StateContext) -> bool {
+        let Some(action) = self.rebinding else {
+            return false;
+        };
+        let input = &ctx.input;
+        let key = input.just_pressed_key();
+        // Esc 也可以绑定，不能用来取消，否则暂停改成其他按键后就改不回 Esc 了
+        let cancelled = input.if_mouse_just_pressed(&MouseButton::Left)
+            || input.if_mouse_just_pressed(&MouseButton::Right)
+            || input.if_gamepad_just_pressed(&GamepadButton::East);
+        if cancelled {
+            self.set_rebinding(None);
+        } else if let Some(key) = key {
+            ctx.settings.key_bindings.bind(action, key);
+            self.set_rebinding(None);
+            self.apply(ctx);
+        }
+        true
+    }
+
+    fn set_rebinding(&mut self, rebinding: Option<Action>) {
+        self.rebinding = rebinding;
+        for (action, id) in &self.bindings {
+            let color = if rebinding == Some(*action) {
+                WAITING_COLOR
+            } else {
+                BINDING_COLOR
+            };
+            self.widgets.get_mut(*id).sprite.sprite.color = Color::new(color);
+        }
+    }
+
+    /// 绑定按钮上显示当前的按键，等待新按键时显示省略号
+    fn update_key_labels(&mut self, key_bindings: &KeyBindings) {
+        for ((action, _), label) in self.bindings.iter().zip(&mut self.key_labels) {
+            if self.rebinding == Some(*action) {
+                label.set_text("...");
+            } else {
+                label.set_text(&key_name(key_bindings.key(*action)));
+            }
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut StateContext) {
+        ctx.settings.sanitize();
+        ctx.settings.apply(ctx.config, ctx.audio, ctx.input);
+        self.if_changed = true;
+    }
+}
+
+impl GameState for SettingsScreen {
+    fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> Transition {
+        self.ui
+            .compute_layout(self.camera.viewport_size, self.scale_factor);
+        self.backdrop
+            .update(self.ui.layout(self.ui_screen), &self.camera, delta);
+        // 等待新按键时先处理按键，避免同一个按键同时触发控件的导航
+        let waiting = self.update_rebinding(ctx);
+        self.update_key_labels(&ctx.settings.key_bindings);
+        for label in self.labels.iter_mut().chain(&mut self.key_labels) {
+            label.update(&self.ui, &self.camera);
+        }
+        if waiting {
+            return Transition::None;
+        }
+        self.widgets
+            .update(&self.ui, ctx.input, &self.camera, delta);
+
+        let mut changed = false;
+        let settings = &mut *ctx.settings;
+        for (id, volume) in [
+            (self.master_volume, &mut settings.master_volume),
+            (self.music_volume, &mut settings.music_volume),
+            (self.sfx_volume, &mut settings.sfx_volume),
+        ] {
+            if let Some(value) = self.widgets.slider_changed(id) {
+                *volume = value;
+                changed = true;
+            }
+        }
+        if let Some(on) = self.widgets.toggled(self.fullscreen) {
+            settings.fullscreen = on;
+            changed = true;
+        }
+        if let Some(on) = self.widgets.toggled(self.decorations) {
+            settings.decorations = on;
+            changed = true;
+        }
+        if let Some(value) = self.widgets.slider_changed(self.default_zoom) {
+            // 拖动时吸附到最近的档位
+            let index = (value * (ZOOM_LEVELS.len() - 1) as f32).round() as usize;
+            settings.default_zoom = ZOOM_LEVELS[index.min(ZOOM_LEVELS.len() - 1)];
+            if let WidgetKind::Slider { value, .. } =
+                &mut self.widgets.get_mut(self.default_zoom).kind
+            {
+                *value = index as f32 / (ZOOM_LEVELS.len() - 1) as f32;
+            }
+            changed = true;
+        }
+        if self.widgets.clicked(self.reset_bindings) {
+            settings.key_bindings.reset();
+            changed = true;
+        }
+        if changed {
+            self.apply(ctx);
+        }
+
+        if let Some((action, _)) = self
+            .bindings
+            .iter()
+            .find(|(_, id)| self.widgets.clicked(*id))
+        {
+            self.set_rebinding(Some(*action));
+        } else if self.widgets.clicked(self.back) || if_back_pressed(ctx.input) {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    /// 重放时不保存，避免覆盖玩家的设置
+    fn exit(&mut self, ctx: &mut StateContext) {
+        if !self.if_changed || ctx.if_replaying {
+            return;
+        }
+        if let Some(storage) = ctx.storage.as_deref_mut() {
+            if let Err(err) = ctx.settings.save(storage) {
+                ctx.errors.report(err);
+            }
+        }
+    }
+
+    fn prepare(&mut self, gpu: &mut GpuContext) {
+        for label in self.labels.iter_mut().chain(&mut self.key_labels) {
+            label.prepare(
+                &mut self.ui,
+                gpu.render,
+                gpu.texture_store,
+                self.scale_factor,
+            );
+        }
+    }
+
+    fn render(&mut self, _alpha: f32) -> RenderGroup<'_> {
+        RenderGroup {
+            passes: vec![CameraPass::new(&self.camera)],
+            sprites: std::iter::once(&self.backdrop.sprite)
+                .chain(self.widgets.sprites())
+                .chain(
+                    self.labels
+                        .iter()
+                        .chain(&self.key_labels)
+                        .filter_map(Label::sprite),
+                )
+                .collect(),
+        }
+    }
+
+    fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        self.camera.viewport_size = (window_size.width as f32, window_size.height as f32).into();
+        self.scale_factor = scale_factor;
+    }
+
+    fn if_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// 关闭状态的开关更暗，和打开状态区分开
+fn toggle_off_visuals() -> WidgetVisuals {
+    let mut visuals = block_visuals();
+    for visual in [
+        &mut visuals.normal,
+        &mut visuals.hovered,
+        &mut visuals.pressed,
+        &mut visuals.disabled,
+    ] {
+        visual.opacity *= 0.4;
+    }
+    visuals
+}
+
+fn zoom_index(zoom: u8) -> usize {
+    ZOOM_LEVELS
+        .iter()
+        .position(|level| *level >= zoom)
+        .unwrap_or(ZOOM_LEVELS.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::state::TestContext;
+    use crate::input::InputEvent;
+    use winit::keyboard::KeyCode;
+
+    #[test]
+    fn screen_covers_the_viewport() {
+        let viewport = Vec2::new(1280.0, 720.0);
+        let mut screen = SettingsScreen::new(PhysicalSize::new(1280, 720), &Settings::default());
+        screen.ui.compute_layout(viewport, 1.0);
+        let rect = screen.ui.layout(screen.ui_screen);
+        assert_eq!((rect.min, rect.max), (Vec2::ZERO, viewport));
+
+        // 文字按点阵字体的大小排版后，所有控件都在画面内，名字不会盖住控件
+        for label in screen.labels.iter().chain(&screen.key_labels) {
+            let columns = label.text().chars().count() as f32;
+            let size = Vec2::new(columns * 12.0 + 8.0, 24.0);
+            screen.ui.set_content_size(label.node, Some(size));
+        }
+        screen.ui.compute_layout(viewport, 1.0);
+        for (_, id) in &screen.bindings {
+            let button = screen.ui.layout(screen.widgets.get(*id).node);
+            assert!(button.min.cmpge(Vec2::ZERO).all() && button.max.cmple(viewport).all());
+        }
+        let back = screen.ui.layout(screen.widgets.get(screen.back).node);
+        assert!(back.max.y <= viewport.y);
+        let controls = [
+            screen.master_volume,
+            screen.music_volume,
+            screen.sfx_volume,
+            screen.fullscreen,
+            screen.decorations,
+            screen.default_zoom,
+        ]
+        .into_iter()
+        .chain(screen.bindings.iter().map(|(_, id)| *id));
+        let mut lefts = Vec::new();
+        for (label, id) in screen.labels.iter().zip(controls) {
+            let name = screen.ui.layout(label.node);
+            let control = screen.ui.layout(screen.widgets.get(id).node);
+            assert!(name.max.x <= control.min.x, "{}", label.text());
+            lefts.push(name.min.x);
+        }
+        // 同一组的名字左对齐
+        assert!(lefts[..6].iter().all(|left| *left == lefts[0]));
+        assert!(lefts[6..10].iter().all(|left| *left == lefts[6]));
+    }
+
+    #[test]
+    fn escape_can_be_rebound() {
+        let mut data = TestContext::new();
+        let mut ctx = data.ctx();
+        ctx.settings.key_bindings.bind(Action::Pause, KeyCode::KeyP);
+        let mut screen = SettingsScreen::new(ctx.window_size, ctx.settings);
+        screen.set_rebinding(Some(Action::Pause));
+
+        ctx.input.fresh();
+        ctx.input.handle_event(InputEvent::Keyboard {
+            key_code: KeyCode::Escape,
+            pressed: true,
+            repeat: false,
+        });
+        assert!(matches!(
+            screen.update(Duration::ZERO, &mut ctx),
+            Transition::None
+        ));
+        assert_eq!(screen.rebinding, None);
+        assert_eq!(
+            ctx.settings.key_bindings.key(Action::Pause),
+            KeyCode::Escape
+        );
+
+        // 点击鼠标取消绑定
+        screen.set_rebinding(Some(Action::Pause));
+        ctx.input.fresh();
+        ctx.input.handle_event(InputEvent::Mouse {
+            button: MouseButton::Left,
+            pressed: true,
+        });
+        screen.update(Duration::ZERO, &mut ctx);
+        assert_eq!(screen.rebinding, None);
+        assert_eq!(
+            ctx.settings.key_bindings.key(Action::Pause),
+            KeyCode::Escape
+        );
+    }
+
+    #[test]
+    fn bindings_show_their_keys() {
+        let mut data = TestContext::new();
+        let mut ctx = data.ctx();
+        ctx.settings.key_bindings.bind(Action::Pause, KeyCode::KeyP);
+        let mut screen = SettingsScreen::new(ctx.window_size, ctx.settings);
+        let names: Vec<&str> = screen.labels.iter().map(Label::text).collect();
+        assert!(names.contains(&"Master volume"));
+        assert!(Action::ALL
+            .iter()
+            .all(|action| names.contains(&action.name())));
+        let key_text = |screen: &SettingsScreen, action: Action| {
+            let index = Action::ALL.iter().position(|a| *a == action).unwrap();
+            screen.key_labels[index].text().to_owned()
+        };
+        assert_eq!(key_text(&screen, Action::Pause), "P");
+        assert_eq!(key_text(&screen, Action::QuickSave), "S");
+
+        screen.set_rebinding(Some(Action::QuickSave));
+        ctx.input.fresh();
+        screen.update(Duration::ZERO, &mut ctx);
+        assert_eq!(key_text(&screen, Action::QuickSave), "...");
+
+        // 交换按键后两个按钮上的文字都会更新
+        ctx.input.fresh();
+        ctx.input.handle_event(InputEvent::Keyboard {
+            key_code: KeyCode::KeyP,
+            pressed: true,
+            repeat: false,
+        });
+        screen.update(Duration::ZERO, &mut ctx);
+        assert_eq!(key_text(&screen, Action::QuickSave), "P");
+        assert_eq!(key_text(&screen, Action::Pause), "S");
+    }
+}
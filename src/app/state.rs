@@ -0,0 +1,687 @@
+use crate::assets::AssetServer;
+use crate::input::Input;
+use crate::{
+    AppConfig, Audio, Easing, EasingAnimator, ErrorLog, Render, RenderGroup, Settings, Storage,
+    TextureStore, TransitionEffect, TransitionFrame,
+};
+use std::ops::Range;
+use std::time::Duration;
+use winit::dpi::PhysicalSize;
+
+/// 状态在切换和更新时可以使用的共享数据，由 AppData 每次调用前组装
+///
+/// 不包括 GPU 资源，GPU 只在渲染前通过 GameState::prepare 使用，所以状态的切换和更新不需要窗口
+pub struct StateContext<'a> {
+    pub assets: &'a mut AssetServer,
+    pub audio: &'a mut Audio,
+    pub input: &'a mut Input,
+    pub config: &'a mut AppConfig,
+    pub errors: &'a mut ErrorLog,
+    /// 存档和设置的存储，找不到平台的数据目录或者浏览器不支持时为 None
+    pub storage: &'a mut Option<Box<dyn Storage>>,
+    /// 玩家的设置，修改后需要调用 Settings::apply 才会生效
+    pub settings: &'a mut Settings,
+    /// 游戏画面的尺寸，重放时为录制时的尺寸
+    pub window_size: PhysicalSize<u32>,
+    pub scale_factor: f32,
+    /// 重放录制的输入时不应该修改玩家的存档
+    pub if_replaying: bool,
+}
+
+/// 渲染前状态可以使用的 GPU 资源
+pub struct GpuContext<'a> {
+    pub render: &'a Render,
+    pub texture_store: &'a mut TextureStore,
+    pub errors: &'a mut ErrorLog,
+}
+
+/// 状态在 update 之后请求的切换
+#[derive(Default)]
+pub enum Transition {
+    #[default]
+    None,
+    /// 在栈顶压入新的状态，原来的栈顶暂停
+    Push(Box<dyn GameState>),
+    /// 移除栈顶的状态，下面的状态恢复
+    Pop,
+    /// 替换栈顶的状态
+    Replace(Box<dyn GameState>),
+    /// 移除栈底以外的所有状态，比如从暂停菜单回到主菜单
+    PopToRoot,
+    /// 播放转场的切换，旧的状态在转场结束后才会 exit
+    Animated(Box<Transition>, ScreenTransition),
+}
+
+impl Transition {
+    pub fn with_effect(self, screen: ScreenTransition) -> Transition {
+        Transition::Animated(Box::new(self), screen)
+    }
+}
+
+/// 转场过程中哪些状态会继续更新
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TransitionUpdate {
+    /// 两边都不更新，画面保持不动
+    Freeze,
+    /// 只更新新的栈顶，旧的画面保持不动
+    #[default]
+    Incoming,
+    /// 两边都更新，旧的状态请求的切换会被忽略
+    ///
+    /// 旧的状态会收到同样的输入，比如加载画面在完成后会重复创建游戏，不能使用这种方式
+    Both,
+}
+
+/// 切换状态时播放的转场
+#[derive(Copy, Clone, Debug)]
+pub struct ScreenTransition {
+    pub effect: TransitionEffect,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub update: TransitionUpdate,
+}
+
+impl ScreenTransition {
+    pub fn new(effect: TransitionEffect, duration: Duration) -> ScreenTransition {
+        ScreenTransition {
+            effect,
+            duration,
+            easing: Easing::InOutCubic,
+            update: TransitionUpdate::default(),
+        }
+    }
+}
+
+/// 正在播放的转场
+struct ActiveTransition {
+    screen: ScreenTransition,
+    animator: EasingAnimator,
+    /// 被移除或者替换掉的状态，按在栈中的顺序排列，转场结束前依旧会渲染
+    leaving: Vec<Box<dyn GameState>>,
+    /// leaving 中从这里开始的状态在切换前是可见的
+    leaving_visible: usize,
+    /// 切换前可见并且还留在栈中的状态，和 leaving 一起组成旧画面
+    from: Range<usize>,
+}
+
+/// 主菜单、加载画面、游戏等状态，由 StateStack 管理
+///
+/// 只有栈顶的状态会执行 update 和 fixed_update，渲染时从最上面的不透明状态开始按从下到上的顺序渲染。
+/// 转场中由 ScreenTransition 的 update 决定更新哪些状态。
+pub trait GameState {
+    /// 进入栈中时调用，调用前已经执行过 resize
+    fn enter(&mut self, _ctx: &mut StateContext) {}
+    /// 从栈中移除时调用，包括关闭窗口时
+    fn exit(&mut self, _ctx: &mut StateContext) {}
+    /// 上面压入了新的状态
+    fn pause(&mut self, _ctx: &mut StateContext) {}
+    /// 上面的状态移除后重新成为栈顶
+    fn resume(&mut self, _ctx: &mut StateContext) {}
+    fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> Transition;
+    fn fixed_update(&mut self, _step: Duration, _ctx: &mut StateContext) {}
+    /// 每帧渲染前调用，只有栈顶的状态会调用，用于上传纹理或者读取纹理的尺寸
+    fn prepare(&mut self, _gpu: &mut GpuContext) {}
+    /// alpha 用于在前后两次 fixed_update 之间插值
+    fn render(&mut self, alpha: f32) -> RenderGroup<'_>;
+    fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32);
+    /// 还在播放动画，需要继续重绘
+    fn if_animating(&self) -> bool {
+        false
+    }
+    /// 为 true 时下面的状态依旧会渲染，比如覆盖在游戏上的暂停菜单
+    fn if_overlay(&self) -> bool {
+        false
+    }
+    /// 是否是一局游戏，AppData 根据它开始录制和重放输入
+    fn if_gameplay(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+    transition: Option<ActiveTransition>,
+    /// 转场中栈顶请求的切换，转场结束后再执行，在这之前栈顶不再更新
+    queued: Option<Transition>,
+}
+
+impl StateStack {
+    pub fn push(&mut self, mut state: Box<dyn GameState>, ctx: &mut StateContext) {
+        if let Some(top) = self.states.last_mut() {
+            top.pause(ctx);
+        }
+        state.resize(ctx.window_size, ctx.scale_factor);
+        state.enter(ctx);
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self, ctx: &mut StateContext) -> Option<Box<dyn GameState>> {
+        let mut state = self.states.pop()?;
+        state.exit(ctx);
+        if let Some(top) = self.states.last_mut() {
+            top.resume(ctx);
+        }
+        Some(state)
+    }
+
+    /// 从上到下移除栈底以外的所有状态，栈底的状态恢复
+    pub fn pop_to_root(&mut self, ctx: &mut StateContext) {
+        if self.states.len() <= 1 {
+            return;
+        }
+        for mut state in self.states.split_off(1).into_iter().rev() {
+            state.exit(ctx);
+        }
+        if let Some(root) = self.states.last_mut() {
+            root.resume(ctx);
+        }
+    }
+
+    /// 替换栈顶的状态，下面的状态不会收到 pause 和 resume
+    pub fn replace(&mut self, mut state: Box<dyn GameState>, ctx: &mut StateContext) {
+        if let Some(mut old) = self.states.pop() {
+            old.exit(ctx);
+        }
+        state.resize(ctx.window_size, ctx.scale_factor);
+        state.enter(ctx);
+        self.states.push(state);
+    }
+
+    /// 执行切换，返回是否开始了新的一局游戏
+    ///
+    /// 还在播放的转场会立即结束，再执行新的切换
+    pub fn apply(&mut self, transition: Transition, ctx: &mut StateContext) -> bool {
+        if !matches!(transition, Transition::None) {
+            self.finish_transition(ctx);
+        }
+        match transition {
+            Transition::None => false,
+            Transition::Push(state) => {
+                let if_gameplay = state.if_gameplay();
+                self.push(state, ctx);
+                if_gameplay
+            }
+            Transition::Pop => {
+                self.pop(ctx);
+                false
+            }
+            Transition::Replace(state) => {
+                let if_gameplay = state.if_gameplay();
+                self.replace(state, ctx);
+                if_gameplay
+            }
+            Transition::PopToRoot => {
+                self.pop_to_root(ctx);
+                false
+            }
+            Transition::Animated(transition, screen) => {
+                self.apply_animated(*transition, screen, ctx)
+            }
+        }
+    }
+
+    /// 和 apply 相同，只是被移除的状态要等转场结束后才 exit
+    fn apply_animated(
+        &mut self,
+        transition: Transition,
+        screen: ScreenTransition,
+        ctx: &mut StateContext,
+    ) -> bool {
+        let visible_start = self.visible_start();
+        let len = self.states.len();
+        let (if_gameplay, leaving) = match transition {
+            Transition::Push(state) => {
+                let if_gameplay = state.if_gameplay();
+                self.push(state, ctx);
+                (if_gameplay, Vec::new())
+            }
+            Transition::Pop => {
+                let Some(leaving) = self.states.pop() else {
+                    return false;
+                };
+                if let Some(top) = self.states.last_mut() {
+                    top.resume(ctx);
+                }
+                (false, vec![leaving])
+            }
+            Transition::Replace(mut state) => {
+                let leaving = self.states.pop().into_iter().collect();
+                let if_gameplay = state.if_gameplay();
+                state.resize(ctx.window_size, ctx.scale_factor);
+                state.enter(ctx);
+                self.states.push(state);
+                (if_gameplay, leaving)
+            }
+            Transition::PopToRoot => {
+                if len <= 1 {
+                    return false;
+                }
+                let leaving = self.states.split_off(1);
+                if let Some(root) = self.states.last_mut() {
+                    root.resume(ctx);
+                }
+                (false, leaving)
+            }
+            other => return self.apply(other, ctx),
+        };
+        // 旧画面中依旧留在栈中的部分，被移除的状态由 leaving 单独渲染
+        let remaining = len - leaving.len();
+        let from = visible_start.min(remaining)..remaining;
+        self.transition = Some(ActiveTransition {
+            screen,
+            animator: EasingAnimator::with_easing(0.0, 1.0, screen.duration, screen.easing),
+            leaving,
+            leaving_visible: visible_start.saturating_sub(remaining),
+            from,
+        });
+        if_gameplay
+    }
+
+    /// 结束正在播放的转场，被移除的状态在这时从上到下 exit
+    fn finish_transition(&mut self, ctx: &mut StateContext) {
+        let Some(active) = self.transition.take() else {
+            return;
+        };
+        for mut leaving in active.leaving.into_iter().rev() {
+            leaving.exit(ctx);
+        }
+    }
+
+    /// 转场中旧画面的栈顶，被移除的状态或者新状态下面的状态
+    fn outgoing(&mut self) -> Option<&mut Box<dyn GameState>> {
+        let active = self.transition.as_mut()?;
+        if !active.leaving.is_empty() {
+            return active.leaving.last_mut();
+        }
+        let index = active.from.end.checked_sub(1)?;
+        self.states.get_mut(index)
+    }
+
+    /// 转场中的更新方式，没有转场时只更新栈顶
+    fn update_mode(&self) -> TransitionUpdate {
+        self.transition
+            .as_ref()
+            .map_or(TransitionUpdate::Incoming, |active| active.screen.update)
+    }
+
+    /// 更新栈顶的状态并执行它请求的切换，返回是否开始了新的一局游戏
+    pub fn update(&mut self, delta: Duration, ctx: &mut StateContext) -> bool {
+        let mode = self.update_mode();
+        if let Some(active) = &mut self.transition {
+            active.animator.update(delta);
+            if active.animator.if_finished() {
+                self.finish_transition(ctx);
+                if let Some(queued) = self.queued.take() {
+                    return self.apply(queued, ctx);
+                }
+            } else if mode == TransitionUpdate::Both {
+                if let Some(outgoing) = self.outgoing() {
+                    let _ = outgoing.update(delta, ctx);
+                }
+            }
+        }
+        if mode == TransitionUpdate::Freeze || self.queued.is_some() {
+            return false;
+        }
+        let Some(top) = self.states.last_mut() else {
+            return false;
+        };
+        let transition = top.update(delta, ctx);
+        if self.transition.is_some() && !matches!(transition, Transition::None) {
+            self.queued = Some(transition);
+            return false;
+        }
+        self.apply(transition, ctx)
+    }
+
+    pub fn fixed_update(&mut self, step: Duration, ctx: &mut StateContext) {
+        match self.update_mode() {
+            TransitionUpdate::Freeze => return,
+            TransitionUpdate::Incoming => {}
+            TransitionUpdate::Both => {
+                if let Some(outgoing) = self.outgoing() {
+                    outgoing.fixed_update(step, ctx);
+                }
+            }
+        }
+        if let Some(top) = self.states.last_mut() {
+            top.fixed_update(step, ctx);
+        }
+    }
+
+    pub fn prepare(&mut self, gpu: &mut GpuContext) {
+        if let Some(top) = self.states.last_mut() {
+            top.prepare(gpu);
+        }
+    }
+
+    pub fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f32) {
+        for state in &mut self.states {
+            state.resize(window_size, scale_factor);
+        }
+        if let Some(active) = &mut self.transition {
+            for leaving in &mut active.leaving {
+                leaving.resize(window_size, scale_factor);
+            }
+        }
+    }
+
+    /// 需要渲染的状态的起始位置，也就是最上面的不透明状态
+    fn visible_start(&self) -> usize {
+        self.states
+            .iter()
+            .rposition(|state| !state.if_overlay())
+            .unwrap_or(0)
+    }
+
+    /// 按从下到上的顺序返回需要渲染的内容，转场时还会返回旧画面
+    ///
+    /// 同时出现在新旧画面中的状态只渲染一次，比如压入暂停菜单时下面的游戏
+    pub fn render(&mut self, alpha: f32) -> (Vec<RenderGroup<'_>>, Option<TransitionFrame<'_>>) {
+        let to_start = self.visible_start();
+        let Some(active) = &mut self.transition else {
+            let groups = self.states[to_start..]
+                .iter_mut()
+                .map(|state| state.render(alpha))
+                .collect();
+            return (groups, None);
+        };
+        let start = to_start.min(active.from.start);
+        let mut groups: Vec<RenderGroup> = self.states[start..]
+            .iter_mut()
+            .map(|state| state.render(alpha))
+            .collect();
+        let mut from = groups[active.from.start - start..active.from.end - start].to_vec();
+        from.extend(
+            active.leaving[active.leaving_visible..]
+                .iter_mut()
+                .map(|state| state.render(alpha)),
+        );
+        let to = groups.split_off(to_start - start);
+        let transition = TransitionFrame {
+            from,
+            effect: active.screen.effect,
+            progress: active.animator.value(),
+        };
+        (to, Some(transition))
+    }
+
+    pub fn if_animating(&self) -> bool {
+        self.transition.is_some()
+            || self.states[self.visible_start()..]
+                .iter()
+                .any(|state| state.if_animating())
+    }
+
+    /// 栈中是否有正在进行的游戏，暂停时游戏依旧在栈中
+    pub fn if_gameplay(&self) -> bool {
+        self.states.iter().any(|state| state.if_gameplay())
+    }
+
+    /// 游戏还在栈中，但是被暂停菜单等状态盖住了
+    pub fn if_paused(&self) -> bool {
+        self.if_gameplay() && !self.states.last().is_some_and(|state| state.if_gameplay())
+    }
+
+    /// 从上到下退出所有状态，比如关闭窗口时
+    pub fn exit_all(&mut self, ctx: &mut StateContext) {
+        self.queued = None;
+        self.finish_transition(ctx);
+        while let Some(mut state) = self.states.pop() {
+            state.exit(ctx);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+/// 测试中组装 StateContext 用的数据，不需要窗口和 GPU
+#[cfg(test)]
+pub(crate) struct TestContext {
+    pub assets: AssetServer,
+    pub audio: Audio,
+    pub input: Input,
+    pub config: AppConfig,
+    pub errors: ErrorLog,
+    pub storage: Option<Box<dyn Storage>>,
+    pub settings: Settings,
+}
+
+#[cfg(test)]
+impl TestContext {
+    pub fn new() -> TestContext {
+        TestContext {
+            assets: AssetServer::new(crate::AssetSource::Directory(Default::default())),
+            audio: Audio::null(),
+            input: Input::default(),
+            config: AppConfig::default(),
+            errors: ErrorLog::default(),
+            storage: Some(Box::new(crate::MemoryStorage::default())),
+            settings: Settings::default(),
+        }
+    }
+
+    pub fn ctx(&mut self) -> StateContext<'_> {
+        StateContext {
+            assets: &mut self.assets,
+            audio: &mut self.audio,
+            input: &mut self.input,
+            config: &mut self.config,
+            errors: &mut self.errors,
+            storage: &mut self.storage,
+            settings: &mut self.settings,
+            window_size: PhysicalSize::new(1280, 720),
+            scale_factor: 1.0,
+            if_replaying: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const FADE: Duration = Duration::from_millis(100);
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// 记录收到的调用，update 时返回 request 中的切换
+    struct Probe {
+        name: &'static str,
+        overlay: bool,
+        log: Log,
+        request: Rc<RefCell<Transition>>,
+    }
+
+    impl Probe {
+        fn record(&self, event: &str) {
+            self.log.borrow_mut().push(format!("{event} {}", self.name));
+        }
+    }
+
+    impl GameState for Probe {
+        fn enter(&mut self, _ctx: &mut StateContext) {
+            self.record("enter");
+        }
+        fn exit(&mut self, _ctx: &mut StateContext) {
+            self.record("exit");
+        }
+        fn pause(&mut self, _ctx: &mut StateContext) {
+            self.record("pause");
+        }
+        fn resume(&mut self, _ctx: &mut StateContext) {
+            self.record("resume");
+        }
+        fn update(&mut self, _delta: Duration, _ctx: &mut StateContext) -> Transition {
+            self.record("update");
+            std::mem::take(&mut *self.request.borrow_mut())
+        }
+        fn render(&mut self, _alpha: f32) -> RenderGroup<'_> {
+            self.record("render");
+            RenderGroup {
+                passes: Vec::new(),
+                sprites: Vec::new(),
+            }
+        }
+        fn resize(&mut self, _window_size: PhysicalSize<u32>, _scale_factor: f32) {}
+        fn if_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    #[derive(Default)]
+    struct Probes {
+        log: Log,
+        request: Rc<RefCell<Transition>>,
+    }
+
+    impl Probes {
+        fn state(&self, name: &'static str) -> Box<dyn GameState> {
+            self.probe(name, false)
+        }
+
+        fn overlay(&self, name: &'static str) -> Box<dyn GameState> {
+            self.probe(name, true)
+        }
+
+        fn probe(&self, name: &'static str, overlay: bool) -> Box<dyn GameState> {
+            Box::new(Probe {
+                name,
+                overlay,
+                log: self.log.clone(),
+                request: self.request.clone(),
+            })
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.log.borrow_mut())
+        }
+    }
+
+    /// 渲染的状态，以及新画面和旧画面各有几组
+    fn rendered(stack: &mut StateStack, probes: &Probes) -> (Vec<String>, (usize, usize)) {
+        probes.take();
+        let (to, transition) = stack.render(1.0);
+        let groups = (to.len(), transition.map_or(0, |frame| frame.from.len()));
+        let names = probes
+            .take()
+            .iter()
+            .map(|event| event.trim_start_matches("render ").to_owned())
+            .collect();
+        (names, groups)
+    }
+
+    #[test]
+    fn lifecycle_order() {
+        let probes = Probes::default();
+        let mut data = TestContext::new();
+        let mut stack = StateStack::default();
+        let ctx = &mut data.ctx();
+
+        stack.push(probes.state("menu"), ctx);
+        stack.push(probes.state("game"), ctx);
+        stack.push(probes.overlay("pause"), ctx);
+        assert_eq!(
+            probes.take(),
+            [
+                "enter menu",
+                "pause menu",
+                "enter game",
+                "pause game",
+                "enter pause"
+            ]
+        );
+
+        stack.pop(ctx);
+        assert_eq!(probes.take(), ["exit pause", "resume game"]);
+
+        stack.replace(probes.state("other"), ctx);
+        assert_eq!(probes.take(), ["exit game", "enter other"]);
+
+        stack.push(probes.overlay("pause"), ctx);
+        probes.take();
+        stack.pop_to_root(ctx);
+        assert_eq!(probes.take(), ["exit pause", "exit other", "resume menu"]);
+        assert_eq!(stack.len(), 1);
+
+        stack.exit_all(ctx);
+        assert_eq!(probes.take(), ["exit menu"]);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn overlays_render_the_states_below() {
+        let probes = Probes::default();
+        let mut data = TestContext::new();
+        let mut stack = StateStack::default();
+        let ctx = &mut data.ctx();
+
+        stack.push(probes.state("menu"), ctx);
+        stack.push(probes.state("game"), ctx);
+        assert_eq!(rendered(&mut stack, &probes), (vec!["game".into()], (1, 0)));
+        stack.push(probes.overlay("pause"), ctx);
+        stack.push(probes.overlay("settings"), ctx);
+        let names = vec!["game".into(), "pause".into(), "settings".into()];
+        assert_eq!(rendered(&mut stack, &probes), (names, (3, 0)));
+    }
+
+    #[test]
+    fn animated_transitions_exit_after_the_effect() {
+        let probes = Probes::default();
+        let mut data = TestContext::new();
+        let mut stack = StateStack::default();
+        let ctx = &mut data.ctx();
+        let screen = ScreenTransition::new(TransitionEffect::Iris, FADE);
+
+        stack.push(probes.state("menu"), ctx);
+        stack.push(probes.state("game"), ctx);
+        stack.push(probes.overlay("pause"), ctx);
+        probes.take();
+
+        // 回到主菜单，转场中旧画面依旧渲染游戏和暂停菜单
+        stack.apply(Transition::PopToRoot.with_effect(screen), ctx);
+        assert_eq!(probes.take(), ["resume menu"]);
+        let names = vec!["menu".into(), "game".into(), "pause".into()];
+        assert_eq!(rendered(&mut stack, &probes), (names, (1, 2)));
+
+        // 转场中栈顶请求的切换在转场结束后执行
+        *probes.request.borrow_mut() = Transition::Push(probes.state("loading"));
+        stack.update(FADE / 2, ctx);
+        assert_eq!(probes.take(), ["update menu"]);
+        stack.update(FADE / 2, ctx);
+        assert_eq!(
+            probes.take(),
+            ["exit pause", "exit game", "pause menu", "enter loading"]
+        );
+        assert_eq!(
+            rendered(&mut stack, &probes),
+            (vec!["loading".into()], (1, 0))
+        );
+
+        // 压入覆盖在上面的状态时，下面的状态在新旧画面中只渲染一次
+        stack.apply(
+            Transition::Push(probes.overlay("pause")).with_effect(screen),
+            ctx,
+        );
+        probes.take();
+        let names = vec!["loading".into(), "pause".into()];
+        assert_eq!(rendered(&mut stack, &probes), (names, (2, 1)));
+
+        // 下面的状态立即恢复，被移除的状态在新的切换立即结束转场时 exit
+        stack.apply(Transition::Pop.with_effect(screen), ctx);
+        stack.apply(Transition::Replace(probes.state("game")), ctx);
+        assert_eq!(
+            probes.take(),
+            ["resume loading", "exit pause", "exit loading", "enter game"]
+        );
+    }
+}
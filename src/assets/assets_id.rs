@@ -0,0 +1,159 @@
+/// 资源的 UUID
+///
+/// 文本格式为带连字符的 UUID，比如 `bbe14c53-ab76-40b4-8383-1c3da2ca0a2b`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct AssetsId(u128);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseAssetsIdError {
+    /// 长度不是 36
+    Length(usize),
+    /// 这个位置应该是 `-`
+    Hyphen(usize),
+    /// 这个位置不是十六进制数字
+    Digit(usize),
+}
+
+impl std::fmt::Display for ParseAssetsIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseAssetsIdError::Length(len) => {
+                write!(f, "Assets id must be 36 characters long, got {len}")
+            }
+            ParseAssetsIdError::Hyphen(index) => write!(f, "Expected '-' at index {index}"),
+            ParseAssetsIdError::Digit(index) => {
+                write!(f, "Expected a hexadecimal digit at index {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseAssetsIdError {}
+
+/// 连字符在 UUID 文本中的位置
+const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
+impl AssetsId {
+    pub const INVALID: AssetsId = AssetsId(0);
+
+    /// 编译时解析 UUID，格式错误时编译失败
+    pub const fn new(uuid: &str) -> AssetsId {
+        match AssetsId::parse(uuid) {
+            Ok(id) => id,
+            Err(_) => panic!("Invalid assets id"),
+        }
+    }
+
+    pub const fn parse(uuid: &str) -> Result<AssetsId, ParseAssetsIdError> {
+        let bytes = uuid.as_bytes();
+        if bytes.len() != 36 {
+            return Err(ParseAssetsIdError::Length(bytes.len()));
+        }
+        let mut value = 0u128;
+        let mut index = 0;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            if index == HYPHENS[0]
+                || index == HYPHENS[1]
+                || index == HYPHENS[2]
+                || index == HYPHENS[3]
+            {
+                if byte != b'-' {
+                    return Err(ParseAssetsIdError::Hyphen(index));
+                }
+            } else {
+                let digit = match byte {
+                    b'0'..=b'9' => byte - b'0',
+                    b'a'..=b'f' => byte - b'a' + 10,
+                    b'A'..=b'F' => byte - b'A' + 10,
+                    _ => return Err(ParseAssetsIdError::Digit(index)),
+                };
+                value = (value << 4) | digit as u128;
+            }
+            index += 1;
+        }
+        Ok(AssetsId(value))
+    }
+
+    /// 运行时生成的纹理使用的 id，数字保存在最后 4 个字节中
+    pub const fn from_u32(num: u32) -> AssetsId {
+        AssetsId(num as u128)
+    }
+
+    pub const fn as_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+impl std::str::FromStr for AssetsId {
+    type Err = ParseAssetsIdError;
+
+    fn from_str(uuid: &str) -> Result<Self, Self::Err> {
+        AssetsId::parse(uuid)
+    }
+}
+
+impl std::fmt::Display for AssetsId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            value >> 96,
+            (value >> 80) & 0xffff,
+            (value >> 64) & 0xffff,
+            (value >> 48) & 0xffff,
+            value & 0xffff_ffff_ffff
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_display() {
+        let text = "bbe14c53-ab76-40b4-8383-1c3da2ca0a2b";
+        let id: AssetsId = text.parse().unwrap();
+        assert_eq!(id.to_string(), text);
+        assert_eq!(
+            AssetsId::parse("BBE14C53-AB76-40B4-8383-1C3DA2CA0A2B"),
+            Ok(id)
+        );
+
+        // 以前生成的 id 在 Display 时会 panic
+        let generated = AssetsId::from_u32(42);
+        assert_eq!(
+            generated.to_string(),
+            "00000000-0000-0000-0000-00000000002a"
+        );
+        assert_eq!(generated.to_string().parse(), Ok(generated));
+        assert_eq!(AssetsId::INVALID.to_string().parse(), Ok(AssetsId::INVALID));
+
+        for entry in crate::assets::MANIFEST {
+            assert_eq!(entry.id.to_string().parse(), Ok(entry.id));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        assert_eq!(
+            AssetsId::parse("bbe14c53"),
+            Err(ParseAssetsIdError::Length(8))
+        );
+        assert_eq!(
+            AssetsId::parse("bbe14c53_ab76-40b4-8383-1c3da2ca0a2b"),
+            Err(ParseAssetsIdError::Hyphen(8))
+        );
+        assert_eq!(
+            AssetsId::parse("bbe14c53-ab76-40b4-8383-1c3da2ca0a2g"),
+            Err(ParseAssetsIdError::Digit(35))
+        );
+        // 多字节字符不会在字符中间被截断
+        assert_eq!(
+            AssetsId::parse("bbe14c53-ab76-40b4-8383-1c3da2ca0aé"),
+            Err(ParseAssetsIdError::Digit(34))
+        );
+    }
+}
@@ -1,91 +1,142 @@
-use crate::Rect;
+mod assets_id;
+mod server;
 
-pub static AUDIO_PICKUP: &'static [u8] = include_bytes!("audio/pickup_demo.ogg");
-pub static AUDIO_PLACE: &'static [u8] = include_bytes!("audio/place_demo_2.ogg");
-pub static AUDIO_BGM: &'static [u8] =
-    include_bytes!("audio/bgm/Carousel Dreams - The Soundlings.mp3");
-pub static AUDIO_BGM_2: &'static [u8] = include_bytes!("audio/bgm/Unrest - ELPHNT.mp3");
-pub static AUDIO_AMBIENT: &'static [u8] = include_bytes!("audio/ambient_sound_demo.ogg");
-pub static AUDIO_RECORD_PRESS: &'static [u8] = include_bytes!("audio/record_press.ogg");
+pub use assets_id::*;
+pub use server::*;
 
-pub static UI_CURSOR: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"bbe14c53-ab76-40b4-8383-1c3da2ca0a2b"),
-    include_bytes!("ui/ui-cursor.png"),
-);
-pub static BG_CHECKER: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"903c0509-5e63-49ad-aa14-7c9efb603fbd"),
-    include_bytes!("checker.png"),
-);
-pub static UI_ZOOM_IN: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"36bd36f0-6626-43d2-a53b-9318cd33ea66"),
-    include_bytes!("ui/ui-zoom-in.png"),
-);
-pub static UI_ZOOM_IN_SLICE: [Rect; 6] = [
-    Rect::new_u32(0, 0, 35, 35),
-    Rect::new_u32(36, 0, 71, 35),
-    Rect::new_u32(72, 0, 107, 35),
-    Rect::new_u32(108, 0, 143, 35),
-    Rect::new_u32(144, 0, 179, 35),
-    Rect::new_u32(180, 0, 215, 35),
-    // Rect::new_u32(216, 0, 251, 35),
-    // Rect::new_u32(252, 0, 287, 35),
+use crate::{AudioBus, Rect};
+
+/// 开启 embedded_assets 时把资源打包进程序中，不需要再从目录或者网络加载
+#[cfg(feature = "embedded_assets")]
+macro_rules! embedded {
+    ($path:literal) => {
+        Some(include_bytes!($path))
+    };
+}
+#[cfg(not(feature = "embedded_assets"))]
+macro_rules! embedded {
+    ($path:literal) => {
+        None
+    };
+}
+
+// 根据 manifest.toml 生成的资源注册，参考 build.rs
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// 启动时需要加载的纹理
+pub static STARTUP_TEXTURES: [&AssetEntry; 6] = [
+    &UI_CURSOR,
+    &BG_CHECKER,
+    &UI_ZOOM_IN,
+    &UI_ZOOM_OUT,
+    &START_NORMAL,
+    &START_HOVER,
 ];
-pub static UI_ZOOM_OUT: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"c618c22b-27b5-4827-ba2a-bc5602ea21bb"),
-    include_bytes!("ui/ui-zoom-out.png"),
-);
-pub static UI_ZOOM_OUT_SLICE: [Rect; 6] = [
-    Rect::new_u32(0, 0, 35, 35),
-    Rect::new_u32(36, 0, 71, 35),
-    Rect::new_u32(72, 0, 107, 35),
-    Rect::new_u32(108, 0, 143, 35),
-    Rect::new_u32(144, 0, 179, 35),
-    Rect::new_u32(180, 0, 215, 35),
-    // Rect::new_u32(216, 0, 251, 35),
-    // Rect::new_u32(252, 0, 287, 35),
+/// 启动时需要加载的音频，以及播放时使用的名称
+pub static STARTUP_AUDIO: [(&str, &AssetEntry); 6] = [
+    ("pickup", &AUDIO_PICKUP),
+    ("place", &AUDIO_PLACE),
+    ("bgm", &AUDIO_BGM),
+    ("bgm2", &AUDIO_BGM_2),
+    ("ambient", &AUDIO_AMBIENT),
+    ("record_press", &AUDIO_RECORD_PRESS),
 ];
 
-pub static SCENE_SIDEBOARD: &'static [u8] = include_bytes!("scenes/SideBoardScene.json");
-pub static PACKAGE_SIDEBOARD: &'static [u8] = include_bytes!("package/SideBoardSceneTotal.pkg");
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AssetKind {
+    Texture,
+    Audio,
+    Scene,
+    Package,
+}
 
-pub static START_NORMAL: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"8688cb57-0b86-48c7-b9ae-d6cfdba6a562"),
-    include_bytes!("ui/start-normal.png"),
-);
-pub static START_HOVER: (AssetsId, &'static [u8]) = (
-    AssetsId::new(b"03e0e1a3-d6c3-4a68-82a0-a29edac1e645"),
-    include_bytes!("ui/start-hover.png"),
-);
+/// 一个资源的注册信息
+#[derive(Debug, Copy, Clone)]
+pub struct AssetEntry {
+    pub id: AssetsId,
+    pub kind: AssetKind,
+    /// 相对于资源目录的路径，native 上为 `src/assets`，web 上为 `dist/assets`
+    pub path: &'static str,
+    /// 开启 embedded_assets 时打包进程序中的数据
+    pub embedded: Option<&'static [u8]>,
+    pub sampler: SamplerSettings,
+    /// 纹理中每一帧的区域
+    pub slices: &'static [Rect],
+    /// 音频的播放设置，其他资源为默认值
+    pub audio: AudioSettings,
+}
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct AssetsId([u8; 36]);
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureFilter {
+    /// 像素风格的纹理缩放时保持清晰
+    #[default]
+    Nearest,
+    Linear,
+}
 
-impl AssetsId {
-    pub const INVALID: AssetsId = AssetsId([0; 36]);
-    #[inline]
-    const fn new(input: &[u8; 36]) -> AssetsId {
-        AssetsId(*input)
-    }
-    pub fn from_u32(num: u32) -> AssetsId {
-        let mut result = [0u8; 36];
-        let bytes = num.to_be_bytes();
-        result[32..].copy_from_slice(&bytes);
-        AssetsId(result)
-    }
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureAddressMode {
+    #[default]
+    Clamp,
+    Repeat,
+    Mirror,
 }
 
-impl Default for AssetsId {
+/// 纹理的采样设置
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SamplerSettings {
+    pub filter: TextureFilter,
+    pub address_mode: TextureAddressMode,
+}
+
+/// 音频的播放设置
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AudioSettings {
+    /// 播放时使用的混音总线
+    pub bus: AudioBus,
+    /// 默认音量，播放时指定的音量会再乘上它
+    pub volume: f32,
+    /// 播放时边读边解码，用于较长的音乐，否则在加载时解码成 PCM
+    pub stream: bool,
+}
+
+impl AudioSettings {
+    pub const DEFAULT: AudioSettings = AudioSettings {
+        bus: AudioBus::Sfx,
+        volume: 1.0,
+        stream: false,
+    };
+}
+
+impl Default for AudioSettings {
     fn default() -> Self {
-        AssetsId::INVALID
+        AudioSettings::DEFAULT
     }
 }
 
-impl std::fmt::Display for AssetsId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "AssetsId({})",
-            std::str::from_utf8(&self.0).expect("Invalid UTF-8 UUID")
-        )
+impl SamplerSettings {
+    pub const DEFAULT: SamplerSettings = SamplerSettings {
+        filter: TextureFilter::Nearest,
+        address_mode: TextureAddressMode::Clamp,
+    };
+
+    pub fn to_wgpu(self) -> wgpu::SamplerDescriptor<'static> {
+        let filter = match self.filter {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear,
+        };
+        let address_mode = match self.address_mode {
+            TextureAddressMode::Clamp => wgpu::AddressMode::ClampToEdge,
+            TextureAddressMode::Repeat => wgpu::AddressMode::Repeat,
+            TextureAddressMode::Mirror => wgpu::AddressMode::MirrorRepeat,
+        };
+        wgpu::SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        }
     }
 }
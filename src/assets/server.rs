@@ -0,0 +1,285 @@
+use super::{AssetEntry, AssetsId};
+use crate::Error;
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+/// 设置后 native 上从这个目录加载资源
+pub const ASSETS_DIR_ENV: &str = "ASSETS_DIR";
+
+/// 资源的来源
+#[derive(Debug, Clone)]
+pub enum AssetSource {
+    /// 本地的资源目录
+    #[cfg(not(target_arch = "wasm32"))]
+    Directory(std::path::PathBuf),
+    /// 通过 fetch 加载，地址相对于页面，也就是 `dist/`
+    #[cfg(target_arch = "wasm32")]
+    Url(String),
+}
+
+impl Default for AssetSource {
+    /// native 上依次使用 ASSETS_DIR 环境变量和程序旁边的 assets 目录，
+    /// debug 构建再使用源码中的 `src/assets`，release 构建使用当前目录下的 `assets`，
+    /// web 上使用 `dist/assets`
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(dir) = std::env::var_os(ASSETS_DIR_ENV) {
+                return AssetSource::Directory(dir.into());
+            }
+            let beside_exe = std::env::current_exe()
+                .ok()
+                .and_then(|exe| Some(exe.parent()?.join("assets")))
+                .filter(|dir| dir.is_dir());
+            AssetSource::Directory(beside_exe.unwrap_or_else(fallback_dir))
+        }
+        #[cfg(target_arch = "wasm32")]
+        AssetSource::Url("assets/".to_owned())
+    }
+}
+
+/// 开发时直接使用源码中的资源，不需要复制到程序旁边
+#[cfg(all(not(target_arch = "wasm32"), debug_assertions))]
+fn fallback_dir() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/assets")
+}
+
+/// 发布的程序不能依赖编译时的源码路径，找不到资源目录时之后的加载会报出完整的路径
+#[cfg(all(not(target_arch = "wasm32"), not(debug_assertions)))]
+fn fallback_dir() -> std::path::PathBuf {
+    let dir = std::path::PathBuf::from("assets");
+    if !dir.is_dir() {
+        log::error!(
+            "Assets directory not found beside the executable or in the working directory, \
+             set {ASSETS_DIR_ENV} to the assets directory"
+        );
+    }
+    dir
+}
+
+impl AssetSource {
+    /// 资源在本地资源目录中的路径，用于播放时边读边解码的音乐
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn path(&self, entry: &AssetEntry) -> std::path::PathBuf {
+        match self {
+            AssetSource::Directory(dir) => dir.join(entry.path),
+        }
+    }
+
+    /// 读取资源的原始数据，打包进程序的资源直接返回
+    pub async fn read(&self, entry: &AssetEntry) -> Result<Vec<u8>, Error> {
+        if let Some(bytes) = entry.embedded {
+            return Ok(bytes.to_vec());
+        }
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            AssetSource::Directory(dir) => {
+                let path = dir.join(entry.path);
+                std::fs::read(&path).map_err(|source| Error::Io { path, source })
+            }
+            #[cfg(target_arch = "wasm32")]
+            AssetSource::Url(base) => fetch(&format!("{base}{}", entry.path)).await,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn fetch(url: &str) -> Result<Vec<u8>, Error> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let asset_error = |reason: String| Error::AssetLoad {
+        path: url.to_owned(),
+        reason,
+    };
+    let window = web_sys::window().ok_or_else(|| asset_error("No window".to_owned()))?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|err| asset_error(format!("{err:?}")))?
+        .unchecked_into();
+    if !response.ok() {
+        return Err(asset_error(format!("HTTP {}", response.status())));
+    }
+    let buffer = response
+        .array_buffer()
+        .map_err(|err| asset_error(format!("{err:?}")))?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|err| asset_error(format!("{err:?}")))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LoadState {
+    NotLoaded,
+    Loading,
+    Loaded,
+    /// 加载失败的原因，具体的错误在 poll 时返回
+    Failed(String),
+}
+
+/// 异步加载资源的原始数据，解码和上传到 GPU 由使用资源的地方完成
+///
+/// native 上在后台线程中读取文件，web 上通过 fetch 加载，加载完成的结果在 poll 时取回。
+#[derive(Debug)]
+pub struct AssetServer {
+    source: AssetSource,
+    states: HashMap<AssetsId, LoadState>,
+    loaded: HashMap<AssetsId, Arc<[u8]>>,
+    sender: Sender<(AssetsId, Result<Vec<u8>, Error>)>,
+    receiver: Receiver<(AssetsId, Result<Vec<u8>, Error>)>,
+}
+
+impl AssetServer {
+    pub fn new(source: AssetSource) -> AssetServer {
+        let (sender, receiver) = channel();
+        AssetServer {
+            source,
+            states: HashMap::new(),
+            loaded: HashMap::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn source(&self) -> &AssetSource {
+        &self.source
+    }
+
+    /// 在后台开始加载资源，已经在加载或者已经加载完成的资源不会重复加载
+    pub fn load(&mut self, entry: &AssetEntry) {
+        if matches!(
+            self.state(&entry.id),
+            LoadState::Loading | LoadState::Loaded
+        ) {
+            return;
+        }
+        if let Some(bytes) = entry.embedded {
+            let _ = self.finish(entry.id, Ok(bytes.to_vec()));
+            return;
+        }
+        self.states.insert(entry.id, LoadState::Loading);
+        let entry = *entry;
+        let source = self.source.clone();
+        let sender = self.sender.clone();
+        let task = async move {
+            let _ = sender.send((entry.id, source.read(&entry).await));
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || pollster::block_on(task));
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(task);
+    }
+
+    /// 加载资源并等待加载完成
+    pub async fn load_now(&mut self, entry: &AssetEntry) -> Result<Arc<[u8]>, Error> {
+        if let Some(bytes) = self.loaded.get(&entry.id) {
+            return Ok(bytes.clone());
+        }
+        self.states.insert(entry.id, LoadState::Loading);
+        let result = self.source.read(entry).await;
+        self.finish(entry.id, result)?;
+        Ok(self.loaded[&entry.id].clone())
+    }
+
+    /// 阻塞到资源加载完成，用于不需要窗口的测试和工具
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_blocking(&mut self, entry: &AssetEntry) -> Result<Arc<[u8]>, Error> {
+        pollster::block_on(self.load_now(entry))
+    }
+
+    /// 取回后台加载完成的资源，返回加载失败的错误
+    pub fn poll(&mut self) -> Vec<Error> {
+        let mut errors = Vec::new();
+        while let Ok((id, result)) = self.receiver.try_recv() {
+            if let Err(err) = self.finish(id, result) {
+                errors.push(err);
+            }
+        }
+        errors
+    }
+
+    fn finish(&mut self, id: AssetsId, result: Result<Vec<u8>, Error>) -> Result<(), Error> {
+        match result {
+            Ok(bytes) => {
+                self.states.insert(id, LoadState::Loaded);
+                self.loaded.insert(id, bytes.into());
+                Ok(())
+            }
+            Err(err) => {
+                self.states.insert(id, LoadState::Failed(err.to_string()));
+                Err(err)
+            }
+        }
+    }
+
+    pub fn state(&self, id: &AssetsId) -> LoadState {
+        self.states.get(id).cloned().unwrap_or(LoadState::NotLoaded)
+    }
+
+    /// 已经加载完成的资源的原始数据
+    pub fn get(&self, id: &AssetsId) -> Option<Arc<[u8]>> {
+        self.loaded.get(id).cloned()
+    }
+
+    /// 释放资源的原始数据，比如纹理上传到 GPU 之后
+    pub fn unload(&mut self, id: &AssetsId) {
+        self.loaded.remove(id);
+        self.states.remove(id);
+    }
+
+    /// 已经结束加载（包括加载失败）的资源数和开始加载的资源总数
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self
+            .states
+            .values()
+            .filter(|state| !matches!(state, LoadState::Loading))
+            .count();
+        (done, self.states.len())
+    }
+
+    pub fn if_loading(&self) -> bool {
+        self.states
+            .values()
+            .any(|state| matches!(state, LoadState::Loading))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::{AssetKind, AudioSettings, SamplerSettings, UI_CURSOR};
+
+    static MISSING: AssetEntry = AssetEntry {
+        id: AssetsId::new("00000000-0000-4000-8000-000000000000"),
+        kind: AssetKind::Texture,
+        path: "missing.png",
+        embedded: None,
+        sampler: SamplerSettings::DEFAULT,
+        slices: &[],
+        audio: AudioSettings::DEFAULT,
+    };
+
+    #[test]
+    fn loads_in_background_and_reports_failures() {
+        let mut server = AssetServer::new(AssetSource::Directory(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/assets").into(),
+        ));
+        server.load(&UI_CURSOR);
+        server.load(&MISSING);
+        assert_eq!(server.progress().1, 2);
+        let mut errors = Vec::new();
+        while server.if_loading() {
+            errors.extend(server.poll());
+            std::thread::yield_now();
+        }
+        assert_eq!(server.state(&UI_CURSOR.id), LoadState::Loaded);
+        assert!(server
+            .get(&UI_CURSOR.id)
+            .is_some_and(|bytes| !bytes.is_empty()));
+        assert!(matches!(server.state(&MISSING.id), LoadState::Failed(_)));
+        assert!(matches!(errors.as_slice(), [Error::Io { .. }]));
+        assert_eq!(server.progress(), (2, 2));
+    }
+}
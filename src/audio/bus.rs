@@ -0,0 +1,79 @@
+/// 混音总线，每个音频都属于 Master 以外的一条总线，Master 控制所有声音
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum AudioBus {
+    Master,
+    Music,
+    #[default]
+    Sfx,
+    /// 循环播放的环境音
+    Ambient,
+    /// 按钮等界面的音效
+    Ui,
+}
+
+impl AudioBus {
+    pub const ALL: [AudioBus; 5] = [
+        AudioBus::Master,
+        AudioBus::Music,
+        AudioBus::Sfx,
+        AudioBus::Ambient,
+        AudioBus::Ui,
+    ];
+}
+
+/// 一条总线的音量
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BusVolume {
+    /// 0 - 1
+    pub volume: f32,
+    /// 静音时保留原来的音量，取消静音后恢复
+    pub muted: bool,
+}
+
+impl Default for BusVolume {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl BusVolume {
+    fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
+        }
+    }
+}
+
+/// 所有总线的音量
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioBuses {
+    buses: [BusVolume; AudioBus::ALL.len()],
+}
+
+impl AudioBuses {
+    pub fn get(&self, bus: AudioBus) -> BusVolume {
+        self.buses[bus as usize]
+    }
+
+    pub fn set_volume(&mut self, bus: AudioBus, volume: f32) {
+        self.buses[bus as usize].volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_muted(&mut self, bus: AudioBus, muted: bool) {
+        self.buses[bus as usize].muted = muted;
+    }
+
+    /// 属于这条总线的声音实际的增益，包括 Master 的音量
+    pub fn gain(&self, bus: AudioBus) -> f32 {
+        let master = self.get(AudioBus::Master).gain();
+        match bus {
+            AudioBus::Master => master,
+            _ => master * self.get(bus).gain(),
+        }
+    }
+}
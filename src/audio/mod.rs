@@ -1,114 +1,347 @@
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+mod bus;
+mod music;
+mod sound;
+mod source;
+mod spatial;
+
+pub use bus::*;
+pub use music::*;
+pub use sound::*;
+pub use source::*;
+pub use spatial::*;
+
+use crate::Error;
+use glam::Vec2;
+use rodio::cpal::traits::HostTrait;
+use rodio::queue::SourcesQueueOutput;
+use rodio::{DeviceTrait, OutputStream, OutputStreamHandle, Sink, Source};
 use slab::Slab;
 use std::collections::HashMap;
-use std::io::Cursor;
-use std::sync::Arc;
+use std::time::Duration;
+
+/// 不知道输出设备的采样率时使用的采样率
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// 声音输出到哪里
+#[derive(Default)]
+enum AudioOutput {
+    /// 还没有初始化或者找不到音频设备，播放时什么也不做
+    #[default]
+    None,
+    Device {
+        handle: OutputStreamHandle,
+        /// 音效在加载时转换成这个采样率，播放时不需要再转换
+        sample_rate: u32,
+    },
+    /// 不输出到设备，每个播放器保留自己的输出端，update 时按经过的时间读取，用于测试和无窗口运行
+    Null,
+}
 
 #[derive(Default)]
 pub struct Audio {
-    stream_handle: Option<OutputStreamHandle>,
+    output: AudioOutput,
     audio_sources: HashMap<String, AudioSource>,
     sinks: Slab<AudioSink>,
+    buses: AudioBuses,
+    music: MusicPlayer,
+    /// 下一个播放器的代数，用于区分复用了同一个位置的播放器
+    generation: u32,
+    listener: Option<Listener>,
 }
 
 pub struct AudioSink {
     source_key: String,
     sink: Sink,
-}
-
-impl AudioSink {
-    pub fn set_volume(&self, volume: f32) {
-        self.sink.set_volume(volume);
-    }
+    bus: AudioBus,
+    /// 播放时的音量乘上音频的默认音量，总线音量变化后用它重新计算实际音量
+    volume: f32,
+    /// 淡入淡出时的增益
+    gain: f32,
+    /// 循环播放，不会自己结束
+    looped: bool,
+    generation: u32,
+    spatial: Option<Spatial>,
+    /// 没有输出设备时播放器的输出端
+    null_output: Option<SourcesQueueOutput<f32>>,
 }
 
 impl Audio {
+    /// 不输出到任何设备，声音依旧会创建播放器，可以检查音量等状态
+    pub fn null() -> Audio {
+        Audio {
+            output: AudioOutput::Null,
+            ..Default::default()
+        }
+    }
     pub fn resume_audio_context(&mut self) {
-        if let Ok((stream, stream_handle)) = OutputStream::try_default() {
+        // 使用默认设备的默认配置，这样可以知道输出的采样率，失败时再尝试其他设备
+        let default = rodio::cpal::default_host()
+            .default_output_device()
+            .and_then(|device| {
+                let config = device.default_output_config().ok()?;
+                let sample_rate = config.sample_rate().0;
+                let (stream, handle) =
+                    OutputStream::try_from_device_config(&device, config).ok()?;
+                Some((stream, handle, sample_rate))
+            });
+        let stream = default.or_else(|| {
+            let (stream, handle) = OutputStream::try_default().ok()?;
+            Some((stream, handle, DEFAULT_SAMPLE_RATE))
+        });
+        if let Some((stream, handle, sample_rate)) = stream {
             log::info!("Default audio device found.");
             // We leak `OutputStream` to prevent the audio from stopping.
             core::mem::forget(stream);
-            self.stream_handle = Some(stream_handle);
+            self.output = AudioOutput::Device {
+                handle,
+                sample_rate,
+            };
         } else {
             log::warn!("No audio device found.");
         };
     }
-    pub fn load_source(&mut self, key: &str, source_bytes: Vec<u8>) {
+    /// 输出设备的采样率，还没有初始化输出设备时为 DEFAULT_SAMPLE_RATE
+    pub fn output_sample_rate(&self) -> u32 {
+        match &self.output {
+            AudioOutput::Device { sample_rate, .. } => *sample_rate,
+            AudioOutput::None | AudioOutput::Null => DEFAULT_SAMPLE_RATE,
+        }
+    }
+    /// 解码失败时依旧会注册这个音频，播放时为静音
+    pub fn load_source(&mut self, key: &str, source_bytes: Vec<u8>) -> Result<(), Error> {
         let source = AudioSource::new(source_bytes);
+        let result = source.check(key);
+        self.insert_source(key, source);
+        result
+    }
+    /// 注册已经检查过的音频，用于在其他线程中检查音频能否解码
+    pub fn insert_source(&mut self, key: &str, source: AudioSource) {
         self.audio_sources.insert(key.to_string(), source);
     }
-    pub fn play_sound(&mut self, source_key: &str) -> Option<usize> {
+    pub fn play_sound(&mut self, source_key: &str) -> Option<SoundHandle> {
         self.play_sound_with_volume(source_key, 1.0)
     }
-    pub fn play_sound_with_volume(&mut self, source_key: &str, volume: f32) -> Option<usize> {
-        let Some(stream_handle) = self.stream_handle.as_ref() else {
-            log::warn!("Audio output unavailable, cannot play sound");
-            return None;
-        };
+    /// volume 会再乘上音频的默认音量和所在总线的音量
+    pub fn play_sound_with_volume(&mut self, source_key: &str, volume: f32) -> Option<SoundHandle> {
+        let index = self.start_sink(source_key, volume, false, 1.0, None)?;
+        Some(SoundHandle {
+            index,
+            generation: self.sinks[index].generation,
+        })
+    }
+    /// looped 为 true 时无缝地循环播放，gain 为淡入时的初始增益，有 position 时在世界空间中的这个位置播放
+    fn start_sink(
+        &mut self,
+        source_key: &str,
+        volume: f32,
+        looped: bool,
+        gain: f32,
+        position: Option<Vec2>,
+    ) -> Option<usize> {
         let Some(source) = self.audio_sources.get(source_key) else {
             log::warn!("Unavailable audio source({source_key})");
             return None;
         };
-        let sink = Sink::try_new(&stream_handle).expect("Could not create audio sink");
-        sink.append(source.decoder());
-        sink.set_volume(volume);
-        sink.play();
-        Some(self.sinks.insert(AudioSink {
+        let mut null_output = None;
+        let sink = match &self.output {
+            AudioOutput::None => {
+                log::warn!("Audio output unavailable, cannot play sound");
+                return None;
+            }
+            AudioOutput::Device { handle, .. } => match Sink::try_new(handle) {
+                Ok(sink) => sink,
+                Err(err) => {
+                    log::error!("{}", Error::AudioOutput(err.to_string()));
+                    return None;
+                }
+            },
+            AudioOutput::Null => {
+                let (sink, output) = Sink::new_idle();
+                null_output = Some(output);
+                sink
+            }
+        };
+        let spatial = self.spatial(position);
+        let gains = spatial.as_ref().map(|spatial| &spatial.gains);
+        // 无法解码的音频播放静音，保证暂停和恢复等操作依旧可以正常使用
+        let playing = source
+            .open(source_key, looped)
+            .unwrap_or_else(|_| Box::new(rodio::source::Empty::<f32>::new()));
+        match gains {
+            Some(gains) => gains.append(&sink, playing),
+            None => sink.append(playing),
+        }
+        let audio_sink = AudioSink {
             source_key: source_key.to_string(),
             sink,
-        }))
+            bus: source.settings.bus,
+            volume: volume * source.settings.volume,
+            gain,
+            looped,
+            generation: self.generation,
+            spatial,
+            null_output,
+        };
+        self.generation = self.generation.wrapping_add(1);
+        audio_sink.sink.set_volume(self.output_volume(&audio_sink));
+        audio_sink.sink.play();
+        Some(self.sinks.insert(audio_sink))
     }
-    pub fn get_sink(&self, key: usize) -> Option<&AudioSink> {
-        self.sinks.get(key)
+    /// 实际输出的音量，音乐被压低时还要乘上压低的比例
+    fn output_volume(&self, sink: &AudioSink) -> f32 {
+        let duck = match sink.bus {
+            AudioBus::Music => self.music.duck_gain(),
+            _ => 1.0,
+        };
+        sink.volume * sink.gain * duck * self.buses.gain(sink.bus)
     }
-    pub fn clean_finished_sink(&mut self) {
-        // self.sinks
-        //     .retain(|_key, sink| if sink.empty() { false } else { true });
+    fn set_sink_gain(&mut self, key: usize, gain: f32) {
+        if let Some(sink) = self.sinks.get_mut(key) {
+            sink.gain = gain;
+            let sink = &self.sinks[key];
+            sink.sink.set_volume(self.output_volume(sink));
+        }
     }
-}
-
-pub struct AudioSource {
-    pub bytes: Arc<[u8]>,
-}
-
-impl AudioSource {
-    fn new(bytes: Vec<u8>) -> AudioSource {
-        Self {
-            bytes: bytes.into(),
+    /// 场景播放的声音，音乐和环境音交给音乐播放器循环播放，其他音效有位置时在这个位置播放
+    pub fn play_scene_sound(&mut self, sound: &str, position: Option<Vec2>) -> bool {
+        match self
+            .audio_sources
+            .get(sound)
+            .map(|source| source.settings.bus)
+        {
+            Some(AudioBus::Music) => {
+                self.play_music(sound);
+                self.current_track() == Some(sound)
+            }
+            Some(AudioBus::Ambient) => {
+                self.play_ambient(sound);
+                true
+            }
+            _ => self.start_sink(sound, 1.0, false, 1.0, position).is_some(),
         }
     }
-    pub fn decoder(&self) -> Decoder<Cursor<Arc<[u8]>>> {
-        Decoder::new(Cursor::new(self.bytes.clone())).unwrap()
+    pub fn bus(&self, bus: AudioBus) -> BusVolume {
+        self.buses.get(bus)
+    }
+    /// 修改总线的音量，总线上正在播放的声音立即生效，修改 Master 会影响所有声音
+    pub fn set_bus_volume(&mut self, bus: AudioBus, volume: f32) {
+        self.buses.set_volume(bus, volume);
+        self.apply_bus(bus);
+    }
+    pub fn set_bus_muted(&mut self, bus: AudioBus, muted: bool) {
+        self.buses.set_muted(bus, muted);
+        self.apply_bus(bus);
+    }
+    fn apply_bus(&self, bus: AudioBus) {
+        for (_, sink) in self.sinks.iter() {
+            if bus == AudioBus::Master || sink.bus == bus {
+                sink.sink.set_volume(self.output_volume(sink));
+            }
+        }
+    }
+    /// 每帧调用，推进没有输出设备时的播放进度，更新音乐的淡入淡出和压低，以及有位置的声音的声像
+    pub fn update(&mut self, delta: Duration) {
+        self.advance_null_output(delta);
+        self.update_music(delta);
+        self.update_spatial();
+    }
+    /// 模拟输出设备按时间读取声音，播放完的声音之后可以被回收
+    fn advance_null_output(&mut self, delta: Duration) {
+        for (_, sink) in self.sinks.iter_mut() {
+            let Some(output) = &mut sink.null_output else {
+                continue;
+            };
+            let samples = delta.as_secs_f64() * output.sample_rate() as f64;
+            let samples = samples as usize * output.channels() as usize;
+            output.by_ref().take(samples).for_each(drop);
+        }
+    }
+    /// 回收播放完的声音，音乐播放器管理的声音由它自己回收
+    pub fn clean_finished_sink(&mut self) {
+        let music = &self.music;
+        self.sinks
+            .retain(|key, sink| !sink.sink.empty() || music.if_owns(key));
     }
 }
 
 impl isometric_engine::Audio for Audio {
     fn play(&mut self, sound: &str) -> bool {
-        let mut volume = 1.0;
-        if sound == "bgm" || sound == "bgm2" {
-            volume = 0.4;
-        }
-        let result = self.play_sound_with_volume(sound, volume);
-        result.is_some()
+        self.play_scene_sound(sound, None)
     }
 
     fn pause(&mut self, sound: &str) -> bool {
-        for (_, sink) in self.sinks.iter() {
-            if sink.source_key.as_str() == sound {
-                sink.sink.pause();
-                return true;
-            }
+        if self.current_track() == Some(sound) {
+            return self.pause_music();
         }
-        false
+        self.pause_source(sound) > 0
     }
 
     fn resume(&mut self, sound: &str) -> bool {
-        for (_, sink) in self.sinks.iter() {
-            if sink.source_key.as_str() == sound {
-                sink.sink.play();
-                return true;
-            }
+        if self.current_track() == Some(sound) {
+            return self.resume_music();
         }
-        false
+        self.resume_source(sound) > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AudioSettings;
+
+    /// 无法解码的音频同样会创建播放器，播放静音
+    fn audio_with_sources() -> Audio {
+        let mut audio = Audio::null();
+        let music = AudioSettings {
+            bus: AudioBus::Music,
+            volume: 0.4,
+            ..AudioSettings::DEFAULT
+        };
+        audio.insert_source("bgm", AudioSource::new(Vec::new()).with_settings(music));
+        audio.insert_source("pickup", AudioSource::new(Vec::new()));
+        audio
+    }
+
+    fn volume(audio: &Audio, handle: SoundHandle) -> f32 {
+        audio.sinks[handle.index].sink.volume()
+    }
+
+    #[test]
+    fn sources_play_on_their_bus() {
+        let mut audio = audio_with_sources();
+        audio.set_bus_volume(AudioBus::Sfx, 0.5);
+        let bgm = audio.play_sound("bgm").unwrap();
+        let pickup = audio.play_sound_with_volume("pickup", 0.8).unwrap();
+        assert_eq!(audio.sinks[bgm.index].bus, AudioBus::Music);
+        assert_eq!(volume(&audio, bgm), 0.4);
+        assert_eq!(volume(&audio, pickup), 0.4);
+        assert_eq!(audio.play_sound("missing"), None);
+        assert_eq!(Audio::default().play_sound("pickup"), None);
+    }
+
+    #[test]
+    fn bus_changes_apply_to_live_sinks() {
+        let mut audio = audio_with_sources();
+        let bgm = audio.play_sound("bgm").unwrap();
+        let pickup = audio.play_sound("pickup").unwrap();
+
+        audio.set_bus_volume(AudioBus::Music, 0.5);
+        assert_eq!(volume(&audio, bgm), 0.2);
+        assert_eq!(volume(&audio, pickup), 1.0);
+
+        audio.set_bus_volume(AudioBus::Master, 0.5);
+        assert_eq!(volume(&audio, bgm), 0.1);
+        assert_eq!(volume(&audio, pickup), 0.5);
+
+        // 静音后保留原来的音量
+        audio.set_bus_muted(AudioBus::Sfx, true);
+        assert_eq!(volume(&audio, pickup), 0.0);
+        assert_eq!(audio.bus(AudioBus::Sfx).volume, 1.0);
+        audio.set_bus_muted(AudioBus::Sfx, false);
+        assert_eq!(volume(&audio, pickup), 0.5);
+
+        audio.set_bus_muted(AudioBus::Master, true);
+        assert_eq!(volume(&audio, bgm), 0.0);
+        assert_eq!(volume(&audio, pickup), 0.0);
     }
 }
@@ -0,0 +1,494 @@
+use super::{Audio, AudioBus};
+use crate::{Easing, EasingAnimator};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// 切换音乐时默认的交叉淡入淡出时间
+pub const DEFAULT_CROSSFADE: Duration = Duration::from_secs(2);
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PlaylistMode {
+    #[default]
+    Sequential,
+    /// 每一轮重新打乱顺序，新一轮的第一首不会和上一轮的最后一首相同
+    Shuffle,
+}
+
+/// 依次播放的一组音乐
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Playlist {
+    pub tracks: Vec<String>,
+    pub mode: PlaylistMode,
+    /// 播放完一轮后重新开始，只有一首时无缝循环
+    pub repeat: bool,
+}
+
+impl Playlist {
+    pub fn new(
+        tracks: impl IntoIterator<Item = impl Into<String>>,
+        mode: PlaylistMode,
+    ) -> Playlist {
+        Playlist {
+            tracks: tracks.into_iter().map(Into::into).collect(),
+            mode,
+            repeat: true,
+        }
+    }
+
+    /// 无缝循环播放一首音乐
+    pub fn looped(track: &str) -> Playlist {
+        Playlist::new([track], PlaylistMode::Sequential)
+    }
+}
+
+/// 播放特定音效时压低音乐的音量
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ducking {
+    /// 会压低音乐的音效
+    pub sources: Vec<String>,
+    /// 压低后音乐音量的比例
+    pub level: f32,
+    /// 从正常音量降到 level 需要的时间，恢复时同样
+    pub fade: Duration,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            level: 0.3,
+            fade: Duration::from_millis(150),
+        }
+    }
+}
+
+/// 正在淡入或淡出的声音
+struct Fade {
+    sink: usize,
+    animator: EasingAnimator,
+}
+
+impl Fade {
+    fn new(sink: usize, start: f32, end: f32, duration: Duration) -> Fade {
+        Fade {
+            sink,
+            animator: EasingAnimator::with_easing(start, end, duration, Easing::Linear),
+        }
+    }
+}
+
+/// 音乐、环境音和压低音乐的状态，由 Audio 持有
+pub(super) struct MusicPlayer {
+    playlist: Playlist,
+    /// 这一轮的播放顺序，元素为 tracks 的下标
+    order: Vec<usize>,
+    /// 正在播放 order 中的第几首
+    position: usize,
+    current: Option<Fade>,
+    ambient: Option<Fade>,
+    /// 淡出结束后停止的声音
+    fading_out: Vec<Fade>,
+    paused: bool,
+    crossfade: Duration,
+    ducking: Ducking,
+    duck_gain: f32,
+    /// xorshift 的状态，用于随机播放
+    seed: u64,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        Self {
+            playlist: Playlist::default(),
+            order: Vec::new(),
+            position: 0,
+            current: None,
+            ambient: None,
+            fading_out: Vec::new(),
+            paused: false,
+            crossfade: DEFAULT_CROSSFADE,
+            ducking: Ducking::default(),
+            duck_gain: 1.0,
+            seed: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+}
+
+impl MusicPlayer {
+    pub(super) fn duck_gain(&self) -> f32 {
+        self.duck_gain
+    }
+
+    /// 播放器由音乐播放器管理，播放完后不能直接回收
+    pub(super) fn if_owns(&self, key: usize) -> bool {
+        [&self.current, &self.ambient]
+            .into_iter()
+            .flatten()
+            .chain(&self.fading_out)
+            .any(|fade| fade.sink == key)
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    /// 生成新一轮的播放顺序，last 为上一轮最后播放的音乐
+    fn new_order(&mut self, last: Option<usize>) {
+        self.order = (0..self.playlist.tracks.len()).collect();
+        if self.playlist.mode == PlaylistMode::Shuffle {
+            for i in (1..self.order.len()).rev() {
+                let j = (self.next_random() % (i as u64 + 1)) as usize;
+                self.order.swap(i, j);
+            }
+            if self.order.len() > 1 && self.order.first() == last.as_ref() {
+                self.order.swap(0, 1);
+            }
+        }
+        self.position = 0;
+    }
+}
+
+impl Audio {
+    /// 从第一首开始播放，之前的音乐交叉淡出，播放列表相同并且正在播放时什么也不做
+    pub fn play_playlist(&mut self, playlist: Playlist) {
+        if self.music.playlist == playlist && self.music.current.is_some() {
+            return;
+        }
+        self.music.playlist = playlist;
+        self.music.new_order(None);
+        self.start_track();
+    }
+
+    pub fn play_music(&mut self, track: &str) {
+        self.play_playlist(Playlist::looped(track));
+    }
+
+    /// 交叉淡入播放列表中的下一首
+    pub fn skip_track(&mut self) {
+        if self.music.order.is_empty() {
+            return;
+        }
+        self.music.position += 1;
+        if self.music.position >= self.music.order.len() {
+            if !self.music.playlist.repeat {
+                self.stop_music();
+                return;
+            }
+            let last = self.music.order.last().copied();
+            self.music.new_order(last);
+        }
+        self.start_track();
+    }
+
+    /// 淡出正在播放的音乐并清空播放列表
+    pub fn stop_music(&mut self) {
+        self.music.playlist = Playlist::default();
+        self.music.order.clear();
+        self.music.paused = false;
+        if let Some(current) = self.music.current.take() {
+            self.fade_out(current.sink);
+        }
+    }
+
+    pub fn pause_music(&mut self) -> bool {
+        let Some(current) = &self.music.current else {
+            return false;
+        };
+        self.sinks[current.sink].sink.pause();
+        self.music.paused = true;
+        true
+    }
+
+    pub fn resume_music(&mut self) -> bool {
+        let Some(current) = &self.music.current else {
+            return false;
+        };
+        self.sinks[current.sink].sink.play();
+        self.music.paused = false;
+        true
+    }
+
+    /// 正在播放或者暂停中的音乐，不包括正在淡出的
+    pub fn current_track(&self) -> Option<&str> {
+        let current = self.music.current.as_ref()?;
+        Some(self.sinks[current.sink].source_key.as_str())
+    }
+
+    pub fn playlist(&self) -> &Playlist {
+        &self.music.playlist
+    }
+
+    /// 之后切换音乐时生效，为 0 时直接切换
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.music.crossfade = crossfade;
+    }
+
+    pub fn set_ducking(&mut self, ducking: Ducking) {
+        self.music.ducking = ducking;
+    }
+
+    /// 无缝循环播放环境音，之前的环境音淡出
+    pub fn play_ambient(&mut self, source_key: &str) {
+        if let Some(ambient) = &self.music.ambient {
+            if self.sinks[ambient.sink].source_key == source_key {
+                return;
+            }
+        }
+        self.stop_ambient();
+        let fade = self.music.crossfade;
+        let gain = if fade.is_zero() { 1.0 } else { 0.0 };
+        if let Some(sink) = self.start_sink(source_key, 1.0, true, gain, None) {
+            self.music.ambient = Some(Fade::new(sink, gain, 1.0, fade));
+        }
+    }
+
+    pub fn stop_ambient(&mut self) {
+        if let Some(ambient) = self.music.ambient.take() {
+            self.fade_out(ambient.sink);
+        }
+    }
+
+    /// 正在淡入淡出、压低音乐，或者需要检查音乐是否播放完
+    pub fn if_animating(&self) -> bool {
+        let fading = |fade: &Option<Fade>| {
+            fade.as_ref()
+                .is_some_and(|fade| !fade.animator.if_finished())
+        };
+        let polling = !self.music.paused
+            && self
+                .music
+                .current
+                .as_ref()
+                .is_some_and(|current| !self.sinks[current.sink].looped);
+        fading(&self.music.current)
+            || fading(&self.music.ambient)
+            || !self.music.fading_out.is_empty()
+            || self.music.duck_gain < 1.0
+            || polling
+    }
+
+    /// 更新淡入淡出和音乐的压低，音乐快结束时交叉淡入下一首
+    pub(super) fn update_music(&mut self, delta: Duration) {
+        self.update_ducking(delta);
+
+        let gains: Vec<_> = [self.music.current.as_mut(), self.music.ambient.as_mut()]
+            .into_iter()
+            .flatten()
+            .filter(|fade| !fade.animator.if_finished())
+            .map(|fade| (fade.sink, fade.animator.update(delta)))
+            .collect();
+        for (sink, gain) in gains {
+            self.set_sink_gain(sink, gain);
+        }
+
+        let mut fading_out = std::mem::take(&mut self.music.fading_out);
+        fading_out.retain_mut(|fade| {
+            let gain = fade.animator.update(delta);
+            if fade.animator.if_finished() {
+                self.sinks.remove(fade.sink).sink.stop();
+                return false;
+            }
+            self.set_sink_gain(fade.sink, gain);
+            true
+        });
+        self.music.fading_out.append(&mut fading_out);
+
+        if self.music.paused {
+            return;
+        }
+        let Some(current) = &self.music.current else {
+            return;
+        };
+        let sink = &self.sinks[current.sink];
+        let duration = self
+            .audio_sources
+            .get(&sink.source_key)
+            .and_then(|source| source.duration);
+        // 循环播放的音乐只有无法解码时才会结束，这时保持静音，避免每帧重新开始
+        let ending = !sink.looped
+            && (sink.sink.empty()
+                || duration.is_some_and(|duration| {
+                    sink.sink.get_pos() + self.music.crossfade >= duration
+                }));
+        if ending {
+            self.skip_track();
+        }
+    }
+
+    fn update_ducking(&mut self, delta: Duration) {
+        let ducking = &self.music.ducking;
+        let active = !ducking.sources.is_empty()
+            && self.sinks.iter().any(|(_, sink)| {
+                !sink.sink.empty()
+                    && !sink.sink.is_paused()
+                    && ducking.sources.contains(&sink.source_key)
+            });
+        let target = if active { ducking.level } else { 1.0 };
+        let current = self.music.duck_gain;
+        if current == target {
+            return;
+        }
+        let step = if ducking.fade.is_zero() {
+            1.0
+        } else {
+            delta.as_secs_f32() / ducking.fade.as_secs_f32() * (1.0 - ducking.level).abs()
+        };
+        self.music.duck_gain = if current < target {
+            (current + step).min(target)
+        } else {
+            (current - step).max(target)
+        };
+        self.apply_bus(AudioBus::Music);
+    }
+
+    /// 播放 position 指向的音乐，正在播放的音乐交叉淡出
+    fn start_track(&mut self) {
+        if let Some(current) = self.music.current.take() {
+            self.fade_out(current.sink);
+        }
+        self.music.paused = false;
+        let Some(&index) = self.music.order.get(self.music.position) else {
+            return;
+        };
+        let playlist = &self.music.playlist;
+        let track = playlist.tracks[index].clone();
+        let looped = playlist.repeat && playlist.tracks.len() == 1;
+        let crossfade = self.music.crossfade;
+        let gain = if crossfade.is_zero() { 1.0 } else { 0.0 };
+        if let Some(sink) = self.start_sink(&track, 1.0, looped, gain, None) {
+            self.music.current = Some(Fade::new(sink, gain, 1.0, crossfade));
+        }
+    }
+
+    /// 从当前的增益淡出，结束后停止并移除
+    fn fade_out(&mut self, key: usize) {
+        let crossfade = self.music.crossfade;
+        if crossfade.is_zero() {
+            self.sinks.remove(key).sink.stop();
+            return;
+        }
+        let gain = self.sinks[key].gain;
+        self.music
+            .fading_out
+            .push(Fade::new(key, gain, 0.0, crossfade));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AudioSettings, AudioSource};
+
+    const STEP: Duration = Duration::from_millis(500);
+
+    fn audio_with_music() -> Audio {
+        let mut audio = Audio::null();
+        let music = AudioSettings {
+            bus: AudioBus::Music,
+            volume: 1.0,
+            ..AudioSettings::DEFAULT
+        };
+        for key in ["a", "b", "c"] {
+            audio.insert_source(key, AudioSource::new(Vec::new()).with_settings(music));
+        }
+        audio.insert_source("press", AudioSource::new(Vec::new()));
+        audio
+    }
+
+    fn sink_volume(audio: &Audio, track: &str) -> Vec<f32> {
+        audio
+            .sinks
+            .iter()
+            .filter(|(_, sink)| sink.source_key == track)
+            .map(|(_, sink)| sink.sink.volume())
+            .collect()
+    }
+
+    #[test]
+    fn crossfade_between_tracks() {
+        let mut audio = audio_with_music();
+        audio.set_crossfade(Duration::from_secs(1));
+        audio.play_music("a");
+        assert_eq!(audio.current_track(), Some("a"));
+        assert_eq!(sink_volume(&audio, "a"), [0.0]);
+        audio.update(STEP);
+        assert_eq!(sink_volume(&audio, "a"), [0.5]);
+        audio.update(STEP);
+        assert_eq!(sink_volume(&audio, "a"), [1.0]);
+        assert!(!audio.if_animating());
+
+        // 同一首已经在播放时不会重新开始
+        audio.play_music("a");
+        assert_eq!(sink_volume(&audio, "a"), [1.0]);
+
+        audio.play_music("b");
+        assert_eq!(audio.current_track(), Some("b"));
+        audio.update(STEP);
+        assert_eq!(sink_volume(&audio, "a"), [0.5]);
+        assert_eq!(sink_volume(&audio, "b"), [0.5]);
+        audio.update(STEP);
+        assert!(sink_volume(&audio, "a").is_empty());
+        assert_eq!(sink_volume(&audio, "b"), [1.0]);
+
+        audio.stop_music();
+        assert_eq!(audio.current_track(), None);
+        audio.update(Duration::from_secs(1));
+        assert!(audio.sinks.is_empty());
+    }
+
+    #[test]
+    fn playlist_order() {
+        let mut audio = audio_with_music();
+        audio.set_crossfade(Duration::ZERO);
+        audio.play_playlist(Playlist::new(["a", "b", "c"], PlaylistMode::Sequential));
+        let mut played = vec![audio.current_track().unwrap().to_string()];
+        for _ in 0..3 {
+            audio.skip_track();
+            played.push(audio.current_track().unwrap().to_string());
+        }
+        assert_eq!(played, ["a", "b", "c", "a"]);
+        // 没有淡出时直接停止之前的音乐
+        assert_eq!(audio.sinks.len(), 1);
+
+        let mut playlist = Playlist::new(["a", "b", "c"], PlaylistMode::Shuffle);
+        playlist.repeat = false;
+        audio.play_playlist(playlist);
+        let mut played = vec![audio.current_track().unwrap().to_string()];
+        for _ in 0..2 {
+            audio.skip_track();
+            played.push(audio.current_track().unwrap().to_string());
+        }
+        played.sort();
+        assert_eq!(played, ["a", "b", "c"]);
+        audio.skip_track();
+        assert_eq!(audio.current_track(), None);
+    }
+
+    #[test]
+    fn ducking_lowers_music_while_sfx_play() {
+        let mut audio = audio_with_music();
+        audio.set_crossfade(Duration::ZERO);
+        audio.set_ducking(Ducking {
+            sources: vec!["press".to_string()],
+            level: 0.5,
+            fade: Duration::from_secs(1),
+        });
+        audio.play_music("a");
+        // 不推进输出，保持音效一直在播放
+        let press = audio.play_sound("press").unwrap();
+        audio.update_music(STEP);
+        assert_eq!(sink_volume(&audio, "a"), [0.75]);
+        audio.update_music(STEP);
+        assert_eq!(sink_volume(&audio, "a"), [0.5]);
+        assert_eq!(audio.sinks[press.index].sink.volume(), 1.0);
+
+        audio.stop_sound(press);
+        audio.update_music(Duration::from_secs(1));
+        assert_eq!(sink_volume(&audio, "a"), [1.0]);
+        assert!(!audio.if_animating());
+    }
+}
@@ -0,0 +1,197 @@
+use super::{Audio, AudioSink};
+
+/// play_sound 返回的一次播放，声音播放完被回收后句柄失效，之后的操作什么也不做
+///
+/// 播放器的位置会被复用，所以用代数区分同一个位置上先后播放的声音
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SoundHandle {
+    pub(super) index: usize,
+    pub(super) generation: u32,
+}
+
+impl Audio {
+    fn sound(&self, handle: SoundHandle) -> Option<&AudioSink> {
+        self.sinks
+            .get(handle.index)
+            .filter(|sink| sink.generation == handle.generation)
+    }
+
+    pub(super) fn sound_mut(&mut self, handle: SoundHandle) -> Option<&mut AudioSink> {
+        self.sinks
+            .get_mut(handle.index)
+            .filter(|sink| sink.generation == handle.generation)
+    }
+
+    /// 还没有播放完，暂停中同样为 true
+    pub fn if_sound_playing(&self, handle: SoundHandle) -> bool {
+        self.sound(handle).is_some_and(|sink| !sink.sink.empty())
+    }
+
+    pub fn stop_sound(&mut self, handle: SoundHandle) -> bool {
+        if self.sound(handle).is_none() {
+            return false;
+        }
+        self.sinks.remove(handle.index).sink.stop();
+        true
+    }
+
+    pub fn pause_sound(&mut self, handle: SoundHandle) -> bool {
+        self.sound(handle).map(|sink| sink.sink.pause()).is_some()
+    }
+
+    pub fn resume_sound(&mut self, handle: SoundHandle) -> bool {
+        self.sound(handle).map(|sink| sink.sink.play()).is_some()
+    }
+
+    /// 和 play_sound_with_volume 的 volume 相同，会再乘上音频的默认音量和总线的音量
+    pub fn set_sound_volume(&mut self, handle: SoundHandle, volume: f32) -> bool {
+        let Some(sink) = self.sound(handle) else {
+            return false;
+        };
+        let default_volume = self
+            .audio_sources
+            .get(&sink.source_key)
+            .map_or(1.0, |source| source.settings.volume);
+        let sink = &mut self.sinks[handle.index];
+        sink.volume = volume * default_volume;
+        let sink = &self.sinks[handle.index];
+        sink.sink.set_volume(self.output_volume(sink));
+        true
+    }
+
+    /// 同时改变音调，1.0 为原速
+    pub fn set_sound_speed(&mut self, handle: SoundHandle, speed: f32) -> bool {
+        self.sound(handle)
+            .map(|sink| sink.sink.set_speed(speed))
+            .is_some()
+    }
+
+    /// 音效的所有实例，不包括由音乐播放器管理的音乐和环境音
+    fn source_sinks<'a>(&'a self, source_key: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.sinks
+            .iter()
+            .filter(move |(key, sink)| sink.source_key == source_key && !self.music.if_owns(*key))
+            .map(|(key, _)| key)
+    }
+
+    /// 停止音效正在播放的所有实例，返回停止的数量，音乐和环境音使用 stop_music 和 stop_ambient
+    pub fn stop_source(&mut self, source_key: &str) -> usize {
+        let keys: Vec<_> = self.source_sinks(source_key).collect();
+        for key in &keys {
+            self.sinks.remove(*key).sink.stop();
+        }
+        keys.len()
+    }
+
+    /// 返回暂停的数量
+    pub fn pause_source(&mut self, source_key: &str) -> usize {
+        self.source_sinks(source_key)
+            .map(|key| self.sinks[key].sink.pause())
+            .count()
+    }
+
+    /// 返回恢复的数量
+    pub fn resume_source(&mut self, source_key: &str) -> usize {
+        self.source_sinks(source_key)
+            .map(|key| self.sinks[key].sink.play())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Audio, AudioData, AudioSource, PcmBuffer};
+    use std::time::Duration;
+
+    /// 无法解码的音频播放静音，读取一次输出后就播放完了
+    fn audio_with_sfx() -> Audio {
+        let mut audio = Audio::null();
+        audio.insert_source("pickup", AudioSource::new(Vec::new()));
+        audio.insert_source("place", AudioSource::new(Vec::new()));
+        audio
+    }
+
+    #[test]
+    fn finished_sounds_are_reclaimed() {
+        let mut audio = audio_with_sfx();
+        let first = audio.play_sound("pickup").unwrap();
+        audio.update(Duration::from_millis(16));
+        audio.clean_finished_sink();
+        assert!(!audio.if_sound_playing(first));
+
+        // 位置被复用后旧的句柄失效，不会影响新的声音
+        let second = audio.play_sound("pickup").unwrap();
+        assert_eq!(first.index, second.index);
+        assert!(!audio.stop_sound(first));
+        assert!(audio.if_sound_playing(second));
+
+        for frame in 0..1000 {
+            audio.play_sound("pickup").unwrap();
+            audio.play_sound("place").unwrap();
+            if frame % 4 == 0 {
+                audio.update(Duration::from_millis(16));
+                audio.clean_finished_sink();
+            }
+            assert!(audio.sinks.len() <= 8, "{}", audio.sinks.len());
+        }
+        audio.update(Duration::from_millis(16));
+        audio.clean_finished_sink();
+        assert!(audio.sinks.is_empty());
+    }
+
+    #[test]
+    fn sounds_with_duration_stay_until_played() {
+        let mut audio = audio_with_sfx();
+        // 100 毫秒的单声道声音
+        let samples = vec![0.5; crate::DEFAULT_SAMPLE_RATE as usize / 10];
+        let pcm = PcmBuffer::new(1, crate::DEFAULT_SAMPLE_RATE, samples);
+        audio.insert_source("chime", AudioSource::from_data(AudioData::Pcm(pcm)));
+        let chime = audio.play_sound("chime").unwrap();
+
+        audio.update(Duration::from_millis(16));
+        audio.clean_finished_sink();
+        assert!(audio.if_sound_playing(chime));
+        // 其他声音播放完后被回收，正在播放的声音不受影响
+        for _ in 0..4 {
+            audio.play_sound("pickup").unwrap();
+            audio.update(Duration::from_millis(16));
+            audio.clean_finished_sink();
+            assert!(audio.if_sound_playing(chime));
+            assert_eq!(audio.sinks.len(), 1);
+        }
+
+        // 一共经过 120 毫秒后播放完并被回收
+        audio.update(Duration::from_millis(40));
+        audio.clean_finished_sink();
+        assert!(!audio.if_sound_playing(chime));
+        assert!(audio.sinks.is_empty());
+    }
+
+    #[test]
+    fn control_single_sounds_and_sources() {
+        let mut audio = audio_with_sfx();
+        let a = audio.play_sound("pickup").unwrap();
+        let b = audio.play_sound("pickup").unwrap();
+        let place = audio.play_sound("place").unwrap();
+
+        assert!(audio.set_sound_volume(a, 0.5));
+        assert!(audio.set_sound_speed(a, 1.5));
+        assert_eq!(audio.sinks[a.index].sink.volume(), 0.5);
+        assert_eq!(audio.sinks[a.index].sink.speed(), 1.5);
+        assert!(audio.pause_sound(a));
+        assert!(audio.sinks[a.index].sink.is_paused());
+        assert!(audio.resume_sound(a));
+        assert!(!audio.sinks[a.index].sink.is_paused());
+
+        assert_eq!(audio.pause_source("pickup"), 2);
+        assert!(audio.sinks[a.index].sink.is_paused());
+        assert!(audio.sinks[b.index].sink.is_paused());
+        assert!(!audio.sinks[place.index].sink.is_paused());
+        assert_eq!(audio.resume_source("pickup"), 2);
+
+        assert_eq!(audio.stop_source("pickup"), 2);
+        assert!(!audio.if_sound_playing(a));
+        assert!(!audio.set_sound_volume(b, 1.0));
+        assert!(audio.if_sound_playing(place));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{AudioSettings, Error};
+use rodio::source::{SeekError, UniformSourceIterator};
+use rodio::{Decoder, Source};
+use std::io::{Cursor, Read, Seek};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 播放时使用的声音，统一为 f32 采样
+pub(super) type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+/// 音频的数据
+#[derive(Clone, Debug)]
+pub enum AudioData {
+    /// 已经解码并转换成输出设备采样率的 PCM，多次播放共享同一份数据
+    Pcm(PcmBuffer),
+    /// 压缩的数据，播放时边解码边播放
+    Encoded(Arc<[u8]>),
+    /// 打包进程序的压缩数据，不需要复制到堆上
+    Static(&'static [u8]),
+    /// 播放时从文件中边读边解码，整首音乐不会读进内存
+    #[cfg(not(target_arch = "wasm32"))]
+    File(std::path::PathBuf),
+}
+
+/// 解码后的交错 PCM 数据
+#[derive(Clone, Debug)]
+pub struct PcmBuffer {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[f32]>,
+}
+
+impl PcmBuffer {
+    pub fn new(channels: u16, sample_rate: u32, samples: impl Into<Arc<[f32]>>) -> PcmBuffer {
+        PcmBuffer {
+            channels: channels.max(1),
+            sample_rate: sample_rate.max(1),
+            samples: samples.into(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = (self.samples.len() / self.channels as usize) as u64;
+        Duration::from_micros(frames * 1_000_000 / self.sample_rate as u64)
+    }
+}
+
+pub struct AudioSource {
+    pub data: AudioData,
+    /// 所在的总线和默认音量，来自资源清单
+    pub settings: AudioSettings,
+    /// 解码器不提供音频的长度，需要时调用 measure_duration 计算
+    pub duration: Option<Duration>,
+}
+
+impl AudioSource {
+    pub fn new(bytes: impl Into<Arc<[u8]>>) -> AudioSource {
+        AudioSource::from_data(AudioData::Encoded(bytes.into()))
+    }
+    pub fn from_data(data: AudioData) -> AudioSource {
+        let duration = match &data {
+            AudioData::Pcm(pcm) => Some(pcm.duration()),
+            _ => None,
+        };
+        Self {
+            data,
+            settings: AudioSettings::DEFAULT,
+            duration,
+        }
+    }
+    pub fn with_settings(self, settings: AudioSettings) -> AudioSource {
+        AudioSource { settings, ..self }
+    }
+    /// 检查音频能否解码，key 只用于错误信息
+    pub fn check(&self, key: &str) -> Result<(), Error> {
+        self.open(key, false).map(|_| ())
+    }
+    /// 解码成 PCM 缓存起来并转换成输出设备的采样率，之后每次播放不需要再解码和转换
+    ///
+    /// 只用于短音效，解码比较慢，应该在后台线程中调用
+    pub fn decode_to_pcm(&mut self, key: &str, sample_rate: u32) -> Result<(), Error> {
+        if matches!(self.data, AudioData::Pcm(_)) {
+            return Ok(());
+        }
+        let source = self.open(key, false)?;
+        let channels = source.channels();
+        let samples: Vec<f32> =
+            UniformSourceIterator::<_, f32>::new(source, channels, sample_rate).collect();
+        let pcm = PcmBuffer::new(channels, sample_rate, samples);
+        self.duration = Some(pcm.duration());
+        self.data = AudioData::Pcm(pcm);
+        Ok(())
+    }
+    /// 完整解码一遍得到音频的长度，音乐播放列表用它在结束前开始交叉淡入淡出
+    ///
+    /// 解码整首音乐比较慢，应该在后台线程中调用
+    pub fn measure_duration(&mut self) {
+        if let AudioData::Pcm(pcm) = &self.data {
+            self.duration = Some(pcm.duration());
+            return;
+        }
+        self.duration = self.open("", false).ok().map(|source| {
+            let channels = source.channels().max(1) as u64;
+            let sample_rate = source.sample_rate().max(1) as u64;
+            let frames = source.count() as u64 / channels;
+            Duration::from_micros(frames * 1_000_000 / sample_rate)
+        });
+    }
+    /// looped 时播放结束后从头开始，重新打开数据而不是缓存整段声音
+    pub(super) fn open(&self, key: &str, looped: bool) -> Result<BoxedSource, Error> {
+        let source = open_data(&self.data, key)?;
+        if !looped {
+            return Ok(source);
+        }
+        Ok(Box::new(Looping {
+            data: self.data.clone(),
+            key: key.to_string(),
+            source,
+        }))
+    }
+}
+
+fn open_data(data: &AudioData, key: &str) -> Result<BoxedSource, Error> {
+    match data {
+        AudioData::Pcm(pcm) => Ok(Box::new(PcmSource {
+            pcm: pcm.clone(),
+            position: 0,
+        })),
+        AudioData::Encoded(bytes) => decode(key, Cursor::new(bytes.clone())),
+        AudioData::Static(bytes) => decode(key, Cursor::new(*bytes)),
+        #[cfg(not(target_arch = "wasm32"))]
+        AudioData::File(path) => {
+            let file = std::fs::File::open(path).map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+            decode(key, std::io::BufReader::new(file))
+        }
+    }
+}
+
+fn decode<R>(key: &str, reader: R) -> Result<BoxedSource, Error>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    match Decoder::new(reader) {
+        Ok(decoder) => Ok(Box::new(decoder.convert_samples())),
+        Err(source) => Err(Error::AudioDecode {
+            key: key.to_string(),
+            source,
+        }),
+    }
+}
+
+/// 播放缓存的 PCM
+struct PcmSource {
+    pcm: PcmBuffer,
+    position: usize,
+}
+
+impl Iterator for PcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.pcm.samples.get(self.position).copied()?;
+        self.position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.pcm.samples.len() - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+impl Source for PcmSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.pcm.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.pcm.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.pcm.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.pcm.duration())
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let frame = (pos.as_secs_f64() * self.pcm.sample_rate as f64) as usize;
+        self.position = (frame * self.pcm.channels as usize).min(self.pcm.samples.len());
+        Ok(())
+    }
+}
+
+/// 无缝循环播放，结束时重新打开数据
+struct Looping {
+    data: AudioData,
+    key: String,
+    source: BoxedSource,
+}
+
+impl Iterator for Looping {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.source.next() {
+            return Some(sample);
+        }
+        // 重新打开后依旧没有数据时结束，避免空的音频一直循环
+        self.source = open_data(&self.data, &self.key).ok()?;
+        self.source.next()
+    }
+}
+
+impl Source for Looping {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm_source(frames: usize) -> AudioSource {
+        let samples: Vec<f32> = (0..frames * 2).map(|i| i as f32).collect();
+        AudioSource::from_data(AudioData::Pcm(PcmBuffer::new(2, 1000, samples)))
+    }
+
+    #[test]
+    fn pcm_is_shared_and_loops() {
+        let source = pcm_source(3);
+        assert_eq!(source.duration, Some(Duration::from_millis(3)));
+        let once: Vec<f32> = source.open("pcm", false).unwrap().collect();
+        assert_eq!(once, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let looped: Vec<f32> = source.open("pcm", true).unwrap().take(8).collect();
+        assert_eq!(looped, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 0.0, 1.0]);
+        // 空的音频循环时直接结束
+        let empty = pcm_source(0);
+        assert_eq!(empty.open("empty", true).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn decode_to_pcm_reports_undecodable_sources() {
+        let mut source = AudioSource::new(Vec::new());
+        assert!(matches!(
+            source.decode_to_pcm("broken", 48000),
+            Err(Error::AudioDecode { .. })
+        ));
+        assert!(matches!(source.data, AudioData::Encoded(_)));
+
+        // 已经解码过的不会再解码，也不会再转换采样率
+        let mut source = pcm_source(4);
+        source.decode_to_pcm("pcm", 48000).unwrap();
+        let AudioData::Pcm(pcm) = &source.data else {
+            panic!("expected pcm");
+        };
+        assert_eq!(pcm.sample_rate, 1000);
+    }
+
+    #[test]
+    fn cached_pcm_plays_for_its_duration() {
+        let mut audio = crate::Audio::null();
+        let samples = vec![0.0; 4410];
+        let pcm = PcmBuffer::new(1, crate::DEFAULT_SAMPLE_RATE, samples);
+        audio.insert_source("click", AudioSource::from_data(AudioData::Pcm(pcm)));
+        let first = audio.play_sound("click").unwrap();
+        let second = audio.play_sound("click").unwrap();
+
+        audio.update(Duration::from_millis(60));
+        assert!(audio.if_sound_playing(first) && audio.if_sound_playing(second));
+        audio.update(Duration::from_millis(60));
+        audio.clean_finished_sink();
+        assert!(!audio.if_sound_playing(first) && !audio.if_sound_playing(second));
+    }
+}
@@ -0,0 +1,215 @@
+use super::{Audio, SoundHandle};
+use crate::Camera2D;
+use glam::Vec2;
+use rodio::source::ChannelVolume;
+use rodio::{Sink, Source};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 播放线程读取左右声道增益的间隔
+const PAN_UPDATE_PERIOD: Duration = Duration::from_millis(10);
+/// 离开屏幕后再经过这么远（以屏幕的一半为单位）衰减为静音
+const FALLOFF_DISTANCE: f32 = 1.0;
+/// 声音在屏幕边缘时较远一侧声道的增益
+const EDGE_PAN_GAIN: f32 = 0.2;
+
+/// 听者跟随镜头，声音的位置先换算到屏幕上再计算声像和衰减，所以镜头放大后远处的声音更小
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Listener {
+    pub translation: Vec2,
+    /// 镜头的缩放，屏幕上一个像素对应的世界空间距离
+    pub scale: f32,
+    /// 屏幕尺寸的一半
+    pub half_viewport: Vec2,
+}
+
+impl Listener {
+    pub fn from_camera(camera: &Camera2D) -> Listener {
+        Listener {
+            translation: camera.get_translation(),
+            scale: camera.get_scale().x,
+            half_viewport: camera.viewport_size / 2.0,
+        }
+    }
+
+    /// 左右声道的增益，屏幕内不衰减，离开屏幕后逐渐减弱
+    pub fn stereo_gains(&self, position: Vec2) -> [f32; 2] {
+        let offset = (position - self.translation)
+            / self.scale.max(f32::EPSILON)
+            / self.half_viewport.max(Vec2::ONE);
+        let attenuation = (1.0 - (offset.length() - 1.0) / FALLOFF_DISTANCE).clamp(0.0, 1.0);
+        let pan = offset.x.clamp(-1.0, 1.0) * (1.0 - EDGE_PAN_GAIN);
+        [
+            (1.0 - pan).min(1.0) * attenuation,
+            (1.0 + pan).min(1.0) * attenuation,
+        ]
+    }
+}
+
+/// 在播放线程和主线程之间共享的左右声道增益
+#[derive(Clone, Debug, Default)]
+pub(super) struct StereoGains(Arc<[AtomicU32; 2]>);
+
+impl StereoGains {
+    pub(super) fn new(gains: [f32; 2]) -> StereoGains {
+        let stereo = StereoGains::default();
+        stereo.set(gains);
+        stereo
+    }
+
+    pub(super) fn get(&self) -> [f32; 2] {
+        self.0
+            .each_ref()
+            .map(|gain| f32::from_bits(gain.load(Ordering::Relaxed)))
+    }
+
+    fn set(&self, gains: [f32; 2]) {
+        for (gain, value) in self.0.iter().zip(gains) {
+            gain.store(value.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// 混合成单声道后按增益输出到左右声道
+    pub(super) fn append<S>(&self, sink: &Sink, source: S)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let gains = self.clone();
+        let source = ChannelVolume::new(source, gains.get().to_vec()).periodic_access(
+            PAN_UPDATE_PERIOD,
+            move |source| {
+                let [left, right] = gains.get();
+                source.set_volume(0, left);
+                source.set_volume(1, right);
+            },
+        );
+        sink.append(source);
+    }
+}
+
+/// 有位置的声音
+pub(super) struct Spatial {
+    pub(super) position: Vec2,
+    pub(super) gains: StereoGains,
+}
+
+impl Audio {
+    /// 在世界空间中的位置播放，每帧根据听者的位置更新声像和衰减，还没有设置听者时和 play_sound 相同
+    pub fn play_at(&mut self, source_key: &str, position: Vec2) -> Option<SoundHandle> {
+        let index = self.start_sink(source_key, 1.0, false, 1.0, Some(position))?;
+        Some(SoundHandle {
+            index,
+            generation: self.sinks[index].generation,
+        })
+    }
+
+    /// 只对 play_at 播放的声音有效
+    pub fn set_sound_position(&mut self, handle: SoundHandle, position: Vec2) -> bool {
+        let listener = self.listener;
+        let Some(spatial) = self
+            .sound_mut(handle)
+            .and_then(|sink| sink.spatial.as_mut())
+        else {
+            return false;
+        };
+        spatial.position = position;
+        spatial.gains.set(spatial_gains(listener, position));
+        true
+    }
+
+    /// 每帧跟随镜头更新，之后的 update 中更新所有有位置的声音
+    pub fn set_listener(&mut self, listener: Option<Listener>) {
+        self.listener = listener;
+    }
+
+    pub fn listener(&self) -> Option<Listener> {
+        self.listener
+    }
+
+    pub(super) fn spatial(&self, position: Option<Vec2>) -> Option<Spatial> {
+        position.map(|position| Spatial {
+            position,
+            gains: StereoGains::new(spatial_gains(self.listener, position)),
+        })
+    }
+
+    pub(super) fn update_spatial(&mut self) {
+        for (_, sink) in self.sinks.iter_mut() {
+            if let Some(spatial) = &sink.spatial {
+                spatial
+                    .gains
+                    .set(spatial_gains(self.listener, spatial.position));
+            }
+        }
+    }
+}
+
+fn spatial_gains(listener: Option<Listener>, position: Vec2) -> [f32; 2] {
+    listener.map_or([1.0; 2], |listener| listener.stereo_gains(position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AudioSource;
+
+    fn listener() -> Listener {
+        Listener {
+            translation: Vec2::new(100.0, 100.0),
+            scale: 0.5,
+            half_viewport: Vec2::new(400.0, 300.0),
+        }
+    }
+
+    #[test]
+    fn pan_and_attenuate_relative_to_camera() {
+        let listener = listener();
+        assert_eq!(listener.stereo_gains(Vec2::new(100.0, 100.0)), [1.0, 1.0]);
+
+        let [left, right] = listener.stereo_gains(Vec2::new(200.0, 100.0));
+        assert!(left < right && right == 1.0);
+        let [left, right] = listener.stereo_gains(Vec2::new(0.0, 100.0));
+        assert!(left > right && left == 1.0);
+
+        // 屏幕右边缘在世界空间中距离中心 400 * 0.5
+        let [left, right] = listener.stereo_gains(Vec2::new(300.0, 100.0));
+        assert!((left - EDGE_PAN_GAIN).abs() < 1e-6 && right == 1.0);
+        assert_eq!(listener.stereo_gains(Vec2::new(500.0, 100.0)), [0.0, 0.0]);
+
+        // 放大后同样的距离在屏幕上更远
+        let zoomed = Listener {
+            scale: 0.25,
+            ..listener
+        };
+        assert_eq!(zoomed.stereo_gains(Vec2::new(300.0, 100.0)), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn spatial_sounds_follow_the_listener() {
+        let mut audio = Audio::null();
+        audio.insert_source("place", AudioSource::new(Vec::new()));
+        let place = audio.play_at("place", Vec2::new(300.0, 100.0)).unwrap();
+        let gains = |audio: &Audio| {
+            audio.sinks[place.index]
+                .spatial
+                .as_ref()
+                .unwrap()
+                .gains
+                .get()
+        };
+        assert_eq!(gains(&audio), [1.0, 1.0]);
+
+        audio.set_listener(Some(listener()));
+        audio.update_spatial();
+        assert_eq!(
+            gains(&audio),
+            listener().stereo_gains(Vec2::new(300.0, 100.0))
+        );
+
+        assert!(audio.set_sound_position(place, Vec2::new(100.0, 100.0)));
+        assert_eq!(gains(&audio), [1.0, 1.0]);
+        let plain = audio.play_sound("place").unwrap();
+        assert!(!audio.set_sound_position(plain, Vec2::ZERO));
+    }
+}
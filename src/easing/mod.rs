@@ -1,20 +1,63 @@
 use std::time::Duration;
 
+/// 缓动曲线，输入和输出都在 0 - 1 之间
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// https://easings.net/#easeInCubic
+    InCubic,
+    /// https://easings.net/#easeOutCubic
+    #[default]
+    OutCubic,
+    /// https://easings.net/#easeInOutCubic
+    InOutCubic,
+    /// https://easings.net/#easeOutExpo
+    OutExpo,
+}
+
+impl Easing {
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => x,
+            Easing::InCubic => x * x * x,
+            Easing::OutCubic => ease_out_cubic(x),
+            Easing::InOutCubic => ease_in_out_cubic(x),
+            Easing::OutExpo => ease_out_expo(x),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct EasingAnimator {
     start: f32,
     end: f32,
     duration: Duration,
     elapsed: Duration,
+    easing: Easing,
+    /// 接近结束时直接跳到终点，避免镜头缩放最后几帧的变化肉眼几乎看不出来
+    if_snap: bool,
+    value: f32,
 }
 
 impl EasingAnimator {
     pub fn new(start: f32, end: f32, duration: Duration) -> EasingAnimator {
+        EasingAnimator {
+            if_snap: true,
+            ..EasingAnimator::with_easing(start, end, duration, Easing::OutCubic)
+        }
+    }
+
+    /// 使用指定的缓动曲线，完整地播放到终点
+    pub fn with_easing(start: f32, end: f32, duration: Duration, easing: Easing) -> EasingAnimator {
         EasingAnimator {
             start,
             end,
             duration,
             elapsed: Duration::ZERO,
+            easing,
+            if_snap: false,
+            value: start,
         }
     }
 
@@ -22,17 +65,25 @@ impl EasingAnimator {
         self.end
     }
 
+    /// 最近一次 update 得到的值
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
     pub fn update(&mut self, delta: Duration) -> f32 {
         self.elapsed += delta;
         if self.elapsed >= self.duration {
+            self.value = self.end;
             return self.end;
         }
-        let mut progress =
-            ease_out_cubic(self.elapsed.as_micros() as f32 / self.duration.as_micros() as f32);
-        if progress > 0.96 {
+        let mut progress = self
+            .easing
+            .apply(self.elapsed.as_micros() as f32 / self.duration.as_micros() as f32);
+        if self.if_snap && progress > 0.96 {
             progress = 1.0;
         }
-        self.start + (self.end - self.start) * progress
+        self.value = self.start + (self.end - self.start) * progress;
+        self.value
     }
 
     pub fn if_finished(&self) -> bool {
@@ -40,13 +91,18 @@ impl EasingAnimator {
     }
 }
 
-/// https://easings.net/#easeOutCubic
-#[allow(dead_code)]
 fn ease_out_cubic(x: f32) -> f32 {
     1.0 - (1.0 - x).powi(3)
 }
 
-#[allow(dead_code)]
+fn ease_in_out_cubic(x: f32) -> f32 {
+    if x < 0.5 {
+        4.0 * x * x * x
+    } else {
+        1.0 - (-2.0 * x + 2.0).powi(3) / 2.0
+    }
+}
+
 fn ease_out_expo(x: f32) -> f32 {
     if x == 1.0 {
         1.0
@@ -54,3 +110,35 @@ fn ease_out_expo(x: f32) -> f32 {
         1.0 - 2.0_f32.powf(-10.0 * x)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_curves_start_and_end() {
+        for easing in [
+            Easing::Linear,
+            Easing::InCubic,
+            Easing::OutCubic,
+            Easing::InOutCubic,
+            Easing::OutExpo,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+            assert!((0.0..=1.0).contains(&easing.apply(0.5)), "{easing:?}");
+        }
+        assert_eq!(Easing::InOutCubic.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn with_easing_does_not_snap() {
+        let mut animator =
+            EasingAnimator::with_easing(0.0, 1.0, Duration::from_millis(100), Easing::Linear);
+        assert_eq!(animator.value(), 0.0);
+        let value = animator.update(Duration::from_millis(98));
+        assert!(value < 1.0 && !animator.if_finished());
+        assert_eq!(animator.update(Duration::from_millis(2)), 1.0);
+        assert!(animator.if_finished());
+    }
+}
@@ -79,6 +79,16 @@ impl EguiRender {
         self.winit_state.on_window_event(&self.window, event)
     }
 
+    /// 光标悬停在 egui 上或者正在拖动 egui 控件
+    pub fn wants_pointer_input(&self) -> bool {
+        self.context.is_pointer_over_area() || self.context.wants_pointer_input()
+    }
+
+    /// egui 有获得焦点的输入框
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
     pub fn update(&mut self, run_ui: impl FnMut(&egui::Context)) {
         let raw_input = self.winit_state.take_egui_input(&self.window);
         let full_output = self.context.run(raw_input, run_ui);
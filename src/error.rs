@@ -0,0 +1,130 @@
+use crate::assets::AssetsId;
+use crate::{RenderError, SaveError};
+use std::path::PathBuf;
+
+/// 运行中最多保留的错误数量，更早的错误只会出现在日志中
+pub const MAX_REPORTED_ERRORS: usize = 32;
+
+#[derive(Debug)]
+pub enum Error {
+    /// 创建事件循环、窗口或者 GPU 设备失败
+    RenderInit(String),
+    Render(RenderError),
+    /// 图片解码失败，会使用洋红色的棋盘格代替
+    ImageDecode {
+        id: AssetsId,
+        source: image::ImageError,
+    },
+    /// 音频解码失败，播放时会使用静音代替
+    AudioDecode {
+        key: String,
+        source: rodio::decoder::DecoderError,
+    },
+    /// 音频输出设备不可用或者无法创建播放器
+    AudioOutput(String),
+    /// 场景或者资源包无法加载，或者场景更新失败
+    Scene(String),
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// 无法通过网络加载资源
+    AssetLoad {
+        path: String,
+        reason: String,
+    },
+    /// 浏览器的 localStorage 或者 IndexedDB 无法使用
+    Storage(String),
+    /// 存档无法读取或者存档位名称无效
+    Save {
+        slot: String,
+        source: SaveError,
+    },
+    /// 设置文件无法读取或者保存，会使用默认设置
+    Settings(serde_json::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::RenderInit(err) => write!(f, "Failed to initialize render: {err}"),
+            Error::Render(err) => write!(f, "Render error: {err}"),
+            Error::ImageDecode { id, source } => write!(f, "Failed to decode image {id}: {source}"),
+            Error::AudioDecode { key, source } => {
+                write!(f, "Failed to decode audio source({key}): {source}")
+            }
+            Error::AudioOutput(err) => write!(f, "Audio output unavailable: {err}"),
+            Error::Scene(err) => write!(f, "Scene error: {err}"),
+            Error::Io { path, source } => write!(f, "I/O error on {path:?}: {source}"),
+            Error::AssetLoad { path, reason } => write!(f, "Failed to load asset {path}: {reason}"),
+            Error::Storage(err) => write!(f, "Storage error: {err}"),
+            Error::Save { slot, source } => write!(f, "Save error in slot({slot}): {source}"),
+            Error::Settings(err) => write!(f, "Invalid settings: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Render(err) => Some(err),
+            Error::ImageDecode { source, .. } => Some(source),
+            Error::AudioDecode { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            Error::Save { source, .. } => Some(source),
+            Error::Settings(err) => Some(err),
+            Error::RenderInit(_)
+            | Error::AudioOutput(_)
+            | Error::Scene(_)
+            | Error::AssetLoad { .. }
+            | Error::Storage(_) => None,
+        }
+    }
+}
+
+impl From<RenderError> for Error {
+    fn from(err: RenderError) -> Self {
+        Error::Render(err)
+    }
+}
+
+/// 收集运行中出现的错误，在屏幕上显示给玩家或者开发者
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    errors: Vec<Error>,
+    /// 每次记录或者清空错误时加一，显示错误的地方用它判断是否需要更新
+    revision: u64,
+}
+
+impl ErrorLog {
+    /// 记录错误，同时输出到日志
+    pub fn report(&mut self, err: Error) {
+        log::error!("{err}");
+        if self.errors.len() == MAX_REPORTED_ERRORS {
+            self.errors.remove(0);
+        }
+        self.errors.push(err);
+        self.revision += 1;
+    }
+
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    pub fn clear(&mut self) {
+        self.errors.clear();
+        self.revision += 1;
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+impl Extend<Error> for ErrorLog {
+    fn extend<I: IntoIterator<Item = Error>>(&mut self, errors: I) {
+        for err in errors {
+            self.report(err);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use instant::{Duration, Instant};
+
+/// 距离下一帧还剩这么多时间时停止 sleep，改为自旋等待，sleep 的精度通常只有 1ms 左右
+pub const FRAME_LIMITER_SPIN: Duration = Duration::from_micros(1500);
+
+/// 限制帧率
+///
+/// 按固定的间隔安排每一帧的开始时间，而不是在上一帧结束后等待固定的时间，
+/// 这样偶尔某一帧慢了，之后的帧会提前开始把时间追回来，平均帧率依旧准确。
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FrameLimiter {
+    /// 下一帧最早开始的时间，为 None 时不限制
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.next_frame
+    }
+
+    /// 还没到下一帧的时间
+    pub fn if_waiting(&self, now: Instant) -> bool {
+        self.next_frame.is_some_and(|next_frame| now < next_frame)
+    }
+
+    /// 开始新的一帧，按照 fps 安排下一帧的开始时间，fps 为 None 时不再限制
+    pub fn begin_frame(&mut self, now: Instant, fps: Option<u32>) {
+        let Some(fps) = fps.filter(|fps| *fps > 0) else {
+            self.next_frame = None;
+            return;
+        };
+        let frame_time = Duration::from_secs(1) / fps;
+        self.next_frame = Some(match self.next_frame {
+            // 落后超过一帧时不再追赶，避免卡顿后连续不限速地渲染很多帧
+            Some(next_frame) if next_frame + frame_time > now => next_frame + frame_time,
+            _ => now + frame_time,
+        });
+    }
+
+    /// 阻塞到下一帧的开始时间，先 sleep 到快到的时候再自旋等待剩下的时间
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(&self) {
+        let Some(next_frame) = self.next_frame else {
+            return;
+        };
+        let now = Instant::now();
+        if next_frame > now + FRAME_LIMITER_SPIN {
+            std::thread::sleep(next_frame - now - FRAME_LIMITER_SPIN);
+        }
+        while Instant::now() < next_frame {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedules_frames_on_a_fixed_grid() {
+        let start = Instant::now();
+        let frame_time = Duration::from_secs(1) / 50;
+        let mut limiter = FrameLimiter::default();
+        limiter.begin_frame(start, Some(50));
+        assert_eq!(limiter.next_frame(), Some(start + frame_time));
+        assert!(limiter.if_waiting(start + frame_time / 2));
+
+        // 这一帧晚了一点开始，下一帧依旧在原来的时间点上
+        limiter.begin_frame(start + frame_time + Duration::from_millis(3), Some(50));
+        assert_eq!(limiter.next_frame(), Some(start + frame_time * 2));
+
+        // 落后太多时从当前时间重新开始
+        let late = start + frame_time * 10;
+        limiter.begin_frame(late, Some(50));
+        assert_eq!(limiter.next_frame(), Some(late + frame_time));
+
+        limiter.begin_frame(late, None);
+        assert!(!limiter.if_waiting(late));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{Error, FixedTimestep, FrameLimiter, PresentMode, RenderError, FRAME_LIMITER_SPIN};
+use instant::Duration;
 use parking_lot::Mutex;
 use std::future::Future;
 use std::rc::Rc;
@@ -5,21 +7,25 @@ use std::sync::Arc;
 use wgpu::WasmNotSend;
 use winit::application::ApplicationHandler;
 use winit::dpi::{PhysicalSize, Size};
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::event::{StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::window::{Window, WindowId};
 
-pub fn run<T: App + 'static>(title: &'static str) -> Result<(), impl std::error::Error> {
+pub fn run<T: App + 'static>(title: &'static str) -> Result<(), Error> {
     init_logger();
 
-    let events_loop = EventLoop::new().unwrap();
+    let events_loop = EventLoop::new().map_err(|err| Error::RenderInit(err.to_string()))?;
     let mut app = AppHandler::<T>::new(title);
-    events_loop.run_app(&mut app)
+    events_loop
+        .run_app(&mut app)
+        .map_err(|err| Error::RenderInit(err.to_string()))
 }
 
 pub trait App {
     #[allow(opaque_hidden_inferred_bound)]
-    fn new(window: Arc<Window>) -> impl Future<Output = Self> + WasmNotSend;
+    fn new(window: Arc<Window>) -> impl Future<Output = Result<Self, Error>> + WasmNotSend
+    where
+        Self: Sized;
 
     fn get_config(&self) -> &AppConfig;
 
@@ -32,11 +38,21 @@ pub trait App {
 
     fn on_window_input(&mut self, event: &WindowEvent);
 
-    /// 更新渲染数据
-    fn update(&mut self, _dt: instant::Duration) {}
+    /// 每帧更新一次，处理输入、UI 和动画等与帧率相关的数据
+    fn update(&mut self, _dt: Duration) {}
+
+    /// 以固定的时间步长更新游戏逻辑，在 update 之后调用，每帧可能调用零次或者多次
+    fn fixed_update(&mut self, _dt: Duration) {}
+
+    /// 省电模式下，没有输入时只有这里返回 true 才会继续重绘，比如还有动画没有播放完
+    fn if_need_redraw(&self) -> bool {
+        true
+    }
 
     /// 提交渲染
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError>;
+    ///
+    /// alpha 为还不足一个固定步长的时间占步长的比例，用于在前后两次 fixed_update 的状态之间插值
+    fn render(&mut self, alpha: f32) -> Result<(), RenderError>;
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -45,6 +61,19 @@ pub struct AppConfig {
     pub set_as_wallpaper: bool,
     pub fullscreen: bool,
     pub decorations: bool,
+    /// fixed_update 的时间步长
+    pub fixed_timestep: Duration,
+    /// 一帧内最多执行 fixed_update 的次数，避免卡顿后为了追赶时间越来越卡
+    pub max_fixed_steps: u32,
+    pub present_mode: PresentMode,
+    /// 最多有几帧在等待提交到屏幕，越小输入延迟越低，但是更容易掉帧
+    pub max_frame_latency: u32,
+    /// 最大帧率，为 None 时不限制
+    pub max_fps: Option<u32>,
+    /// 省电模式，只有收到输入或者还有动画时才重绘
+    pub power_saving: bool,
+    /// 窗口失去焦点或者被遮挡时的最大帧率，为 None 时和前台一样
+    pub background_fps: Option<u32>,
 }
 
 impl Default for AppConfig {
@@ -54,6 +83,13 @@ impl Default for AppConfig {
             set_as_wallpaper: false,
             fullscreen: false,
             decorations: true,
+            fixed_timestep: Duration::from_secs(1) / 60,
+            max_fixed_steps: 5,
+            present_mode: PresentMode::Vsync,
+            max_frame_latency: 2,
+            max_fps: None,
+            power_saving: false,
+            background_fps: Some(10),
         }
     }
 }
@@ -81,18 +117,36 @@ pub struct AppHandler<T: App> {
     missed_request_redraw: Rc<Mutex<bool>>,
     /// 上次执行渲染的时间
     last_render_time: instant::Instant,
+    timestep: FixedTimestep,
+    limiter: FrameLimiter,
+    if_focused: bool,
+    if_occluded: bool,
+    /// 最后一次收到窗口事件的时间，省电模式下在这之后的一段时间内继续重绘
+    last_input_time: instant::Instant,
+    /// 省电模式下已经停止重绘，等待输入
+    if_idle: bool,
 }
 
+/// 省电模式下，最后一次输入之后继续重绘的时间，让输入触发的动画和场景的变化能够播放完
+pub const POWER_SAVING_LINGER: Duration = Duration::from_secs(1);
+
 impl<T: App> AppHandler<T> {
     pub fn new(title: &'static str) -> AppHandler<T> {
+        let config = AppConfig::default();
         AppHandler {
             title,
             window: None,
             app: Rc::new(Mutex::new(None)),
-            config: AppConfig::default(),
+            config,
             missed_resize: Rc::new(Mutex::new(None)),
             missed_request_redraw: Rc::new(Mutex::new(false)),
             last_render_time: instant::Instant::now(),
+            timestep: FixedTimestep::new(config.fixed_timestep, config.max_fixed_steps),
+            limiter: FrameLimiter::default(),
+            if_focused: true,
+            if_occluded: false,
+            last_input_time: instant::Instant::now(),
+            if_idle: false,
         }
     }
 
@@ -123,8 +177,11 @@ impl<T: App> AppHandler<T> {
             // 设置画布获得焦点时不显示高亮轮廓
             let style = canvas.style();
             style.set_property("outline", "none").unwrap();
-            style.set_property("width", "800px").unwrap();
-            style.set_property("height", "600px").unwrap();
+            // 画布铺满页面，窗口尺寸由 winit 监听画布的尺寸变化得到
+            style.set_property("width", "100%").unwrap();
+            style.set_property("height", "100%").unwrap();
+            // 禁用浏览器默认的触摸滚动和双指缩放，交给游戏处理
+            style.set_property("touch-action", "none").unwrap();
             canvas.focus().expect("画布无法获取焦点");
         }
     }
@@ -143,6 +200,43 @@ impl<T: App> AppHandler<T> {
         }
     }
 
+    /// 当前的最大帧率，窗口在后台时使用 background_fps
+    fn target_fps(&self) -> Option<u32> {
+        if self.if_focused && !self.if_occluded {
+            return self.config.max_fps;
+        }
+        match (self.config.max_fps, self.config.background_fps) {
+            (Some(max_fps), Some(background_fps)) => Some(max_fps.min(background_fps)),
+            (max_fps, background_fps) => background_fps.or(max_fps),
+        }
+    }
+
+    /// 一帧结束后安排下一次重绘
+    fn schedule_redraw(&mut self, event_loop: &ActiveEventLoop, if_need_redraw: bool) {
+        if self.config.power_saving
+            && !if_need_redraw
+            && self.last_input_time.elapsed() > POWER_SAVING_LINGER
+        {
+            // 停止重绘，直到收到下一个窗口事件
+            self.if_idle = true;
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        match self.limiter.next_frame() {
+            // 先睡到快到下一帧的时候，由 new_events 请求重绘，期间依旧可以及时处理输入事件
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(next_frame) => {
+                event_loop
+                    .set_control_flow(ControlFlow::WaitUntil(next_frame - FRAME_LIMITER_SPIN));
+            }
+            _ => {
+                event_loop.set_control_flow(ControlFlow::Wait);
+                // 除非我们手动请求，RedrawRequested 将只会触发一次。
+                self.request_redraw();
+            }
+        }
+    }
+
     #[cfg(feature = "windows_wallpaper")]
     fn set_as_wallpaper(&self) {
         use windows::core::s;
@@ -185,6 +279,13 @@ impl<T: App> AppHandler<T> {
 }
 
 impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+        // 限制帧率时，等待到了下一帧的时间
+        if let StartCause::ResumeTimeReached { .. } = cause {
+            self.request_redraw();
+        }
+    }
+
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         log::info!("winit application resumed!");
         if self.app.as_ref().lock().is_some() {
@@ -192,10 +293,18 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
         }
 
         self.last_render_time = instant::Instant::now();
+        self.timestep.reset();
 
         let mut window_attributes = Window::default_attributes();
         window_attributes.inner_size = Some(Size::Physical(PhysicalSize::new(1920, 1080)));
-        let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(err) => {
+                log::error!("Failed to create window: {err}");
+                event_loop.exit();
+                return;
+            }
+        };
         self.window = Some(window.clone());
         self.config_window();
 
@@ -211,7 +320,13 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
                 // NOTE 这里需要注意，必须先执行异步操作创建出 inner_app
                 //  然后获取 app 的锁进行更新
                 //  如果顺序倒转，会引发死锁，这也是之前打包遇到 Parking not supported on this platform 报错的原因
-                let inner_app = T::new(window).await;
+                let inner_app = match T::new(window).await {
+                    Ok(inner_app) => inner_app,
+                    Err(err) => {
+                        log::error!("{err}");
+                        return;
+                    }
+                };
                 let mut app = app.lock();
                 *app = Some(inner_app);
 
@@ -226,8 +341,15 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let app = pollster::block_on(T::new(window));
-            self.app.lock().replace(app);
+            match pollster::block_on(T::new(window)) {
+                Ok(app) => {
+                    self.app.lock().replace(app);
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    event_loop.exit();
+                }
+            }
         }
     }
 
@@ -237,7 +359,8 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
-        let mut app = self.app.lock();
+        let app = self.app.clone();
+        let mut app = app.lock();
 
         if app.as_ref().is_none() {
             // 如果 app 还没有初始化完成，则记录错失的窗口事件
@@ -267,10 +390,26 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
             }
             self.config.set_as_wallpaper = new_config.set_as_wallpaper;
         }
+        if new_config.fixed_timestep != self.config.fixed_timestep
+            || new_config.max_fixed_steps != self.config.max_fixed_steps
+        {
+            self.timestep
+                .set_step(new_config.fixed_timestep, new_config.max_fixed_steps);
+            self.config.fixed_timestep = new_config.fixed_timestep;
+            self.config.max_fixed_steps = new_config.max_fixed_steps;
+        }
+        // 这些配置每帧都会读取，直接更新就会生效，present_mode 由 App 自己应用到 Render
+        self.config.max_fps = new_config.max_fps;
+        self.config.power_saving = new_config.power_saving;
+        self.config.background_fps = new_config.background_fps;
+        self.config.present_mode = new_config.present_mode;
+        self.config.max_frame_latency = new_config.max_frame_latency;
         let window = self.window.as_mut().unwrap();
         if new_config.fullscreen != self.config.fullscreen {
             if new_config.fullscreen {
                 window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+            } else {
+                window.set_fullscreen(None);
             }
 
             self.config.fullscreen = new_config.fullscreen;
@@ -307,26 +446,57 @@ impl<T: App + 'static> ApplicationHandler for AppHandler<T> {
             WindowEvent::RedrawRequested => {
                 // surface 重绘事件
                 let now = instant::Instant::now();
-                let delta = now - self.last_render_time;
+                if self.limiter.if_waiting(now + FRAME_LIMITER_SPIN) {
+                    // 还没到下一帧的时间，比如省电模式下收到输入时请求的重绘，等到下一帧再渲染
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.schedule_redraw(event_loop, true);
+                    // web 端不能阻塞，跳过这次 requestAnimationFrame
+                    #[cfg(target_arch = "wasm32")]
+                    self.request_redraw();
+                    return;
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                self.limiter.wait();
+                let now = instant::Instant::now();
+                self.limiter.begin_frame(now, self.target_fps());
+
+                let mut delta = now - self.last_render_time;
                 self.last_render_time = now;
+                if std::mem::take(&mut self.if_idle) {
+                    // 省电模式下停止重绘的时间不计入游戏时间
+                    delta = delta.min(self.timestep.step());
+                }
 
                 app.update(delta);
+                for _ in 0..self.timestep.advance(delta) {
+                    app.fixed_update(self.timestep.step());
+                }
 
                 self.pre_present_notify();
 
-                match app.render() {
+                match app.render(self.timestep.alpha()) {
                     Ok(_) => {}
-                    // 当展示平面的上下文丢失，就需重新配置
-                    Err(wgpu::SurfaceError::Lost) => log::error!("Surface is lost"),
-                    // 所有其他错误（过期、超时等）应在下一帧解决
-                    Err(e) => log::error!("{e:?}"),
+                    // 显存不足时无法恢复，直接退出
+                    Err(RenderError::OutOfMemory) => {
+                        log::error!("{}", RenderError::OutOfMemory);
+                        event_loop.exit();
+                    }
+                    // surface 丢失、超时等错误已经跳过了这一帧，设备丢失时 app 会重新创建设备
+                    Err(err) => log::warn!("Skipped frame: {err}"),
                 }
 
-                // 除非我们手动请求，RedrawRequested 将只会触发一次。
-                self.request_redraw();
+                self.schedule_redraw(event_loop, app.if_need_redraw());
             }
+            WindowEvent::Focused(focused) => self.if_focused = focused,
+            WindowEvent::Occluded(occluded) => self.if_occluded = occluded,
             _ => (),
         }
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            self.last_input_time = instant::Instant::now();
+            if self.if_idle {
+                self.request_redraw();
+            }
+        }
 
         // 在背景层运行时，就需要主动检测设备输入
         #[cfg(feature = "windows_wallpaper")]
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use winit::keyboard::KeyCode;
+
+/// 可以在设置中重新绑定按键的操作
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum Action {
+    CameraLeft,
+    CameraRight,
+    CameraUp,
+    CameraDown,
+    QuickSave,
+    QuickLoad,
+    TakeOutItem,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::CameraLeft,
+        Action::CameraRight,
+        Action::CameraUp,
+        Action::CameraDown,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::TakeOutItem,
+        Action::Pause,
+    ];
+
+    /// 设置界面中显示的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::CameraLeft => "Camera left",
+            Action::CameraRight => "Camera right",
+            Action::CameraUp => "Camera up",
+            Action::CameraDown => "Camera down",
+            Action::QuickSave => "Quick save",
+            Action::QuickLoad => "Quick load",
+            Action::TakeOutItem => "Take out item",
+            Action::Pause => "Pause",
+        }
+    }
+
+    pub fn default_key(&self) -> KeyCode {
+        match self {
+            Action::CameraLeft => KeyCode::ArrowLeft,
+            Action::CameraRight => KeyCode::ArrowRight,
+            Action::CameraUp => KeyCode::ArrowUp,
+            Action::CameraDown => KeyCode::ArrowDown,
+            Action::QuickSave => KeyCode::KeyS,
+            Action::QuickLoad => KeyCode::F9,
+            Action::TakeOutItem => KeyCode::KeyT,
+            Action::Pause => KeyCode::Escape,
+        }
+    }
+}
+
+/// 设置界面中显示的按键名字，字母和数字键去掉 `Key` 和 `Digit` 前缀
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    match name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+    {
+        Some(short) => short.to_owned(),
+        None => name,
+    }
+}
+
+/// 操作到按键的映射，没有保存的操作使用默认按键
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, KeyCode>,
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    /// 绑定新的按键，按键已经被其他操作使用时交换两者的按键，返回被交换的操作
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> Option<Action> {
+        let old_key = self.key(action);
+        let swapped = Action::ALL
+            .into_iter()
+            .find(|other| *other != action && self.key(*other) == key);
+        if let Some(other) = swapped {
+            self.keys.insert(other, old_key);
+        }
+        self.keys.insert(action, key);
+        swapped
+    }
+
+    /// 恢复所有操作的默认按键
+    pub fn reset(&mut self) {
+        self.keys.clear();
+    }
+
+    /// 手动修改的设置中多个操作使用同一个按键时，让后面的操作恢复默认按键，
+    /// 后面的操作本来就是默认按键时改为恢复前面的操作，直到每个按键最多只对应一个操作
+    pub fn resolve_conflicts(&mut self) {
+        while let Some(action) = self.conflicting_action() {
+            self.keys.remove(&action);
+        }
+    }
+
+    /// 默认按键互不相同，所以冲突的两个操作中至少有一个绑定过按键
+    fn conflicting_action(&self) -> Option<Action> {
+        for (index, later) in Action::ALL.iter().enumerate() {
+            for earlier in &Action::ALL[..index] {
+                if self.key(*earlier) == self.key(*later) {
+                    return Some(if self.keys.contains_key(later) {
+                        *later
+                    } else {
+                        *earlier
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_swaps_conflicting_keys() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(bindings.key(Action::QuickSave), KeyCode::KeyS);
+        assert_eq!(bindings.bind(Action::QuickSave, KeyCode::KeyQ), None);
+        assert_eq!(bindings.key(Action::QuickSave), KeyCode::KeyQ);
+
+        assert_eq!(
+            bindings.bind(Action::Pause, KeyCode::KeyQ),
+            Some(Action::QuickSave)
+        );
+        assert_eq!(bindings.key(Action::Pause), KeyCode::KeyQ);
+        assert_eq!(bindings.key(Action::QuickSave), KeyCode::Escape);
+
+        // 每个按键最多只对应一个操作
+        for action in Action::ALL {
+            let count = Action::ALL
+                .iter()
+                .filter(|other| bindings.key(**other) == bindings.key(action))
+                .count();
+            assert_eq!(count, 1, "{action:?}");
+        }
+    }
+
+    #[test]
+    fn conflicts_fall_back_to_default_keys() {
+        // 后面的操作恢复默认按键
+        let mut bindings: KeyBindings =
+            serde_json::from_str(r#"{"keys":{"QuickSave":"KeyA","QuickLoad":"KeyA"}}"#).unwrap();
+        bindings.resolve_conflicts();
+        assert_eq!(bindings.key(Action::QuickSave), KeyCode::KeyA);
+        assert_eq!(bindings.key(Action::QuickLoad), KeyCode::F9);
+
+        // 恢复的默认按键又和前面的操作冲突时，前面的操作也恢复默认按键
+        let mut bindings: KeyBindings =
+            serde_json::from_str(r#"{"keys":{"QuickSave":"F9","QuickLoad":"F9"}}"#).unwrap();
+        bindings.resolve_conflicts();
+        assert_eq!(bindings, KeyBindings::default());
+
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Pause, KeyCode::KeyP);
+        let resolved = bindings.clone();
+        bindings.resolve_conflicts();
+        assert_eq!(bindings, resolved);
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(key_name(KeyCode::KeyS), "S");
+        assert_eq!(key_name(KeyCode::Digit1), "1");
+        assert_eq!(key_name(KeyCode::Escape), "Escape");
+        assert_eq!(key_name(KeyCode::ArrowLeft), "ArrowLeft");
+    }
+
+    #[test]
+    fn missing_actions_use_default_keys() {
+        let bindings: KeyBindings = serde_json::from_str(r#"{"keys":{"Pause":"KeyP"}}"#).unwrap();
+        assert_eq!(bindings.key(Action::Pause), KeyCode::KeyP);
+        assert_eq!(bindings.key(Action::CameraUp), KeyCode::ArrowUp);
+    }
+}
@@ -0,0 +1,431 @@
+use super::{Input, InputEvent};
+use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder, Repeat, Replay, Ticks};
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::time::Duration;
+
+/// 手柄按键，布局参考 Xbox 手柄，South 就是 A 键
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// 扳机按下超过一定程度时视为按下，具体的按下程度使用 `Input::gamepad_trigger`
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// 手柄的模拟输入，摇杆在 -1 到 1 之间，Y 轴向上为正；扳机在 0 到 1 之间
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GamepadSide {
+    Left,
+    Right,
+}
+
+/// 震动请求，strength 在 0 到 1 之间
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rumble {
+    pub strength: f32,
+    pub duration: Duration,
+}
+
+/// 基于 gilrs 的手柄后端
+///
+/// 每帧通过 `poll` 将手柄事件转换为 `InputEvent` 交给 Input，
+/// 同时连接多个手柄时，以最后一个有输入的手柄作为当前手柄，震动也只作用在当前手柄上。
+pub struct Gamepads {
+    /// 当前平台不支持或者初始化失败时为 None
+    gilrs: Option<Gilrs>,
+    pads: ConnectedPads<GamepadId>,
+    /// 震动效果在 drop 时会停止，所以需要保存到播放结束
+    rumble_effect: Option<gilrs::ff::Effect>,
+}
+
+impl std::fmt::Debug for Gamepads {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gamepads")
+            .field("available", &self.gilrs.is_some())
+            .field("active", &self.pads.active)
+            .finish()
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Gamepads::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(gilrs::Error::NotImplemented(_)) => {
+                log::warn!("Gamepad is not supported on this platform");
+                None
+            }
+            Err(err) => {
+                log::error!("Failed to init gamepad: {err}");
+                None
+            }
+        };
+        let active = gilrs
+            .as_ref()
+            .and_then(|gilrs| gilrs.gamepads().next().map(|(id, _)| id));
+        Gamepads {
+            gilrs,
+            pads: ConnectedPads::new(active),
+            rumble_effect: None,
+        }
+    }
+
+    /// 将这一帧的手柄事件交给 input，需要在处理游戏逻辑之前调用
+    pub fn poll(&mut self, input: &mut Input) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        if self.pads.active.is_some() != input.gamepad_connected() {
+            input.handle_event(InputEvent::GamepadConnected(self.pads.active.is_some()));
+        }
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    log::info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+                    self.pads.connected(event.id, input);
+                }
+                EventType::Disconnected => {
+                    log::info!("Gamepad disconnected: {}", gilrs.gamepad(event.id).name());
+                    let next = gilrs
+                        .gamepads()
+                        .map(|(id, _)| id)
+                        .find(|id| *id != event.id);
+                    if self.pads.disconnected(event.id, next, input) {
+                        self.rumble_effect = None;
+                    }
+                }
+                event_type => self.pads.input(event.id, convert_event(event_type), input),
+            }
+        }
+    }
+
+    /// 播放 input 中积累的震动请求，只播放最强的一个
+    pub fn apply_rumble(&mut self, input: &mut Input) {
+        let strongest = input
+            .take_rumble_requests()
+            .into_iter()
+            .max_by(|a, b| a.strength.total_cmp(&b.strength));
+        let (Some(rumble), Some(gilrs), Some(id)) = (strongest, &mut self.gilrs, self.pads.active)
+        else {
+            return;
+        };
+        if !gilrs.gamepad(id).is_ff_supported() {
+            return;
+        }
+        let play_for = Ticks::from_ms(rumble.duration.as_millis() as u32);
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: (rumble.strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16,
+                },
+                scheduling: Replay {
+                    play_for,
+                    ..Default::default()
+                },
+                envelope: Default::default(),
+            })
+            .repeat(Repeat::For(play_for))
+            .gamepads(&[id])
+            .finish(gilrs)
+            .and_then(|effect| effect.play().map(|_| effect));
+        match effect {
+            Ok(effect) => self.rumble_effect = Some(effect),
+            Err(err) => log::warn!("Failed to play rumble: {err}"),
+        }
+    }
+}
+
+/// 一个手柄按住的按键和模拟输入
+#[derive(Debug, Default)]
+struct PadState {
+    buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+/// 同时连接的手柄和当前手柄，和 gilrs 分开以便测试
+///
+/// Input 中只有一份合并后的手柄状态，所以这里记录每个手柄各自按住的按键：
+/// 其他手柄还按着同一个按键时不转发松开；手柄断开时只松开它遗留的按键，
+/// 模拟输入换成当前手柄的值。切换当前手柄只改变震动的目标。
+#[derive(Debug)]
+struct ConnectedPads<Id> {
+    active: Option<Id>,
+    pads: HashMap<Id, PadState>,
+}
+
+impl<Id: Copy + Eq + Hash> ConnectedPads<Id> {
+    fn new(active: Option<Id>) -> ConnectedPads<Id> {
+        ConnectedPads {
+            active,
+            pads: active
+                .into_iter()
+                .map(|id| (id, PadState::default()))
+                .collect(),
+        }
+    }
+
+    /// 新连接的手柄成为当前手柄
+    fn connected(&mut self, id: Id, input: &mut Input) {
+        self.active = Some(id);
+        self.pads.entry(id).or_default();
+        if !input.gamepad_connected() {
+            input.handle_event(InputEvent::GamepadConnected(true));
+        }
+    }
+
+    /// 松开断开的手柄遗留的按键，当前手柄断开时换成 next，返回当前手柄是否变化
+    fn disconnected(&mut self, id: Id, next: Option<Id>, input: &mut Input) -> bool {
+        let state = self.pads.remove(&id).unwrap_or_default();
+        let changed = self.active == Some(id);
+        if changed {
+            self.active = next;
+        }
+        if self.active.is_none() {
+            self.pads.clear();
+            input.handle_event(InputEvent::GamepadConnected(false));
+            return changed;
+        }
+        for button in state.buttons {
+            if !self.if_held(button) {
+                input.handle_event(InputEvent::GamepadButton {
+                    button,
+                    pressed: false,
+                });
+            }
+        }
+        for axis in state.axes.into_keys() {
+            let value = self
+                .active
+                .and_then(|active| self.pads.get(&active)?.axes.get(&axis).copied())
+                .unwrap_or(0.0);
+            input.handle_event(InputEvent::GamepadAxis { axis, value });
+        }
+        changed
+    }
+
+    /// 最后一个有输入的手柄成为当前手柄
+    fn input(&mut self, id: Id, event: Option<InputEvent>, input: &mut Input) {
+        self.connected(id, input);
+        let Some(event) = event else {
+            return;
+        };
+        let state = self.pads.entry(id).or_default();
+        match event {
+            InputEvent::GamepadButton {
+                button,
+                pressed: true,
+            } => {
+                state.buttons.insert(button);
+            }
+            InputEvent::GamepadButton {
+                button,
+                pressed: false,
+            } => {
+                state.buttons.remove(&button);
+                if self.if_held(button) {
+                    return;
+                }
+            }
+            InputEvent::GamepadAxis { axis, value } => {
+                state.axes.insert(axis, value);
+            }
+            _ => {}
+        }
+        input.handle_event(event);
+    }
+
+    /// 有手柄还按着这个按键
+    fn if_held(&self, button: GamepadButton) -> bool {
+        self.pads
+            .values()
+            .any(|state| state.buttons.contains(&button))
+    }
+}
+
+fn convert_event(event: EventType) -> Option<InputEvent> {
+    match event {
+        EventType::ButtonPressed(button, _) => Some(InputEvent::GamepadButton {
+            button: convert_button(button)?,
+            pressed: true,
+        }),
+        EventType::ButtonReleased(button, _) => Some(InputEvent::GamepadButton {
+            button: convert_button(button)?,
+            pressed: false,
+        }),
+        EventType::ButtonChanged(Button::LeftTrigger2, value, _) => Some(InputEvent::GamepadAxis {
+            axis: GamepadAxis::LeftTrigger,
+            value,
+        }),
+        EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+            Some(InputEvent::GamepadAxis {
+                axis: GamepadAxis::RightTrigger,
+                value,
+            })
+        }
+        EventType::AxisChanged(axis, value, _) => {
+            let axis = match axis {
+                Axis::LeftStickX => GamepadAxis::LeftStickX,
+                Axis::LeftStickY => GamepadAxis::LeftStickY,
+                Axis::RightStickX => GamepadAxis::RightStickX,
+                Axis::RightStickY => GamepadAxis::RightStickY,
+                _ => return None,
+            };
+            Some(InputEvent::GamepadAxis { axis, value })
+        }
+        _ => None,
+    }
+}
+
+fn convert_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    fn button(button: GamepadButton, pressed: bool) -> Option<InputEvent> {
+        Some(InputEvent::GamepadButton { button, pressed })
+    }
+
+    #[test]
+    fn switching_pads_keeps_held_state() {
+        let mut input = Input::default();
+        let mut pads = ConnectedPads::new(None);
+        pads.connected(1, &mut input);
+        assert!(input.gamepad_connected());
+        pads.input(1, button(GamepadButton::South, true), &mut input);
+        input.fresh();
+
+        // 第二个手柄连接和有输入时成为当前手柄，第一个手柄按住的按键不受影响
+        pads.connected(2, &mut input);
+        pads.input(
+            2,
+            Some(InputEvent::GamepadAxis {
+                axis: GamepadAxis::LeftStickX,
+                value: 0.01,
+            }),
+            &mut input,
+        );
+        assert_eq!(pads.active, Some(2));
+        assert!(input.if_gamepad_pressed(&GamepadButton::South));
+        assert!(!input.if_gamepad_just_pressed(&GamepadButton::South));
+
+        // 两个手柄按着同一个按键时，都松开才算松开
+        pads.input(2, button(GamepadButton::South, true), &mut input);
+        pads.input(1, button(GamepadButton::South, false), &mut input);
+        assert!(input.if_gamepad_pressed(&GamepadButton::South));
+        assert_eq!(pads.active, Some(1));
+        pads.input(2, button(GamepadButton::South, false), &mut input);
+        assert!(!input.if_gamepad_pressed(&GamepadButton::South));
+    }
+
+    #[test]
+    fn disconnecting_releases_only_that_pad() {
+        let mut input = Input::default();
+        let mut pads = ConnectedPads::new(None);
+        pads.input(1, button(GamepadButton::East, true), &mut input);
+        pads.input(
+            1,
+            Some(InputEvent::GamepadAxis {
+                axis: GamepadAxis::LeftStickX,
+                value: 0.5,
+            }),
+            &mut input,
+        );
+        // 不是当前手柄的手柄按着按键和摇杆断开
+        pads.input(2, button(GamepadButton::South, true), &mut input);
+        pads.input(
+            2,
+            Some(InputEvent::GamepadAxis {
+                axis: GamepadAxis::RightStickX,
+                value: 1.0,
+            }),
+            &mut input,
+        );
+        pads.input(1, button(GamepadButton::North, true), &mut input);
+        input.fresh();
+        assert!(!pads.disconnected(2, Some(1), &mut input));
+        assert_eq!(pads.active, Some(1));
+        assert!(!input.if_gamepad_pressed(&GamepadButton::South));
+        assert_eq!(input.gamepad_stick(GamepadSide::Right), Vec2::ZERO);
+        assert!(input.if_gamepad_pressed(&GamepadButton::East));
+        assert!(input.if_gamepad_pressed(&GamepadButton::North));
+
+        // 当前手柄断开时只松开它自己的按键，模拟输入换成新的当前手柄的值
+        pads.input(3, button(GamepadButton::East, true), &mut input);
+        pads.input(
+            3,
+            Some(InputEvent::GamepadAxis {
+                axis: GamepadAxis::LeftStickX,
+                value: 1.0,
+            }),
+            &mut input,
+        );
+        pads.input(3, button(GamepadButton::West, true), &mut input);
+        input.fresh();
+        assert!(pads.disconnected(3, Some(1), &mut input));
+        assert_eq!(pads.active, Some(1));
+        assert!(input.gamepad_connected());
+        assert!(!input.if_gamepad_pressed(&GamepadButton::West));
+        assert!(input.if_gamepad_pressed(&GamepadButton::East));
+        assert!(input.if_gamepad_pressed(&GamepadButton::North));
+        assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickX), 0.5);
+
+        // 最后一个手柄断开时清空所有状态
+        assert!(pads.disconnected(1, None, &mut input));
+        assert!(!input.gamepad_connected());
+        assert!(!input.if_any_pressed());
+    }
+}
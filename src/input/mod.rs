@@ -1,16 +1,137 @@
+mod bindings;
+mod gamepad;
+mod replay;
+mod touch;
+
+pub use bindings::*;
+pub use gamepad::*;
+pub use replay::*;
+pub use touch::*;
+
 use glam::Vec2;
-use std::collections::HashMap;
-use winit::event::{ElementState, MouseButton, WindowEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use winit::event::{ElementState, MouseButton, TouchPhase, WindowEvent};
 use winit::keyboard::{KeyCode, PhysicalKey};
 
+/// 摇杆的径向死区，小于死区的输入会被忽略，之外的部分重新映射到 0 - 1
+pub const STICK_DEAD_ZONE: f32 = 0.2;
+/// 扳机的死区
+pub const TRIGGER_DEAD_ZONE: f32 = 0.05;
+/// 摇杆推到底时虚拟光标每秒移动的像素
+pub const VIRTUAL_CURSOR_SPEED: f32 = 800.0;
+
 #[derive(Debug, Default)]
 pub struct Input {
     keyboard_inputs: HashMap<KeyCode, KeyState>,
     cursor_pos: Vec2,
     mouse_inputs: HashMap<MouseButton, KeyState>,
+    /// 本轮刚刚松开的鼠标按键
+    mouse_just_released: HashSet<MouseButton>,
+    /// 本轮鼠标输入被哪一层占用了
+    pointer_claim: Option<InputLayer>,
+    /// 本轮键盘输入被哪一层占用了
+    keyboard_claim: Option<InputLayer>,
+    gamepad_connected: bool,
+    gamepad_buttons: HashMap<GamepadButton, KeyState>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    /// 等待手柄后端播放的震动
+    rumble_requests: Vec<Rumble>,
+    touch: GestureRecognizer,
+    /// 最后一次移动光标的是触摸而不是鼠标
+    touch_pointer: bool,
+    /// 操作对应的按键，由设置决定
+    bindings: KeyBindings,
+    recorder: Option<InputRecorder>,
+}
+
+/// 输入的分发顺序，排在前面的层先决定是否占用输入
+///
+/// 每帧按照 egui -> 游戏 UI -> 场景 的顺序处理输入，
+/// 一旦某一层占用了鼠标或者键盘，之后的层在这一帧就不会再收到对应的输入。
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum InputLayer {
+    Egui,
+    Ui,
+    Scene,
+}
+
+/// 从 WindowEvent 中提取出的输入事件，也方便在测试中直接构造，以及录制和重放
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    CursorMoved(Vec2),
+    Mouse {
+        button: MouseButton,
+        pressed: bool,
+    },
+    Keyboard {
+        key_code: KeyCode,
+        pressed: bool,
+        repeat: bool,
+    },
+    GamepadButton {
+        button: GamepadButton,
+        pressed: bool,
+    },
+    GamepadAxis {
+        axis: GamepadAxis,
+        value: f32,
+    },
+    /// 手柄连接或者断开，都会清空之前的手柄状态
+    GamepadConnected(bool),
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: Vec2,
+    },
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
+                PhysicalKey::Code(key_code) => Some(InputEvent::Keyboard {
+                    key_code,
+                    pressed: event.state == ElementState::Pressed,
+                    repeat: event.repeat,
+                }),
+                PhysicalKey::Unidentified(_) => None,
+            },
+            WindowEvent::MouseInput { button, state, .. } => Some(InputEvent::Mouse {
+                button: *button,
+                pressed: *state == ElementState::Pressed,
+            }),
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::CursorMoved(Vec2::new(
+                position.x as f32,
+                position.y as f32,
+            ))),
+            WindowEvent::Touch(touch) => Some(InputEvent::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                position: Vec2::new(touch.location.x as f32, touch.location.y as f32),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Input 中跨帧保留的状态，开始录制时保存下来，重放前先恢复
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct InputSnapshot {
+    keyboard_inputs: Vec<(KeyCode, KeyState)>,
+    cursor_pos: Vec2,
+    mouse_inputs: Vec<(MouseButton, KeyState)>,
+    gamepad_connected: bool,
+    gamepad_buttons: Vec<(GamepadButton, KeyState)>,
+    gamepad_axes: Vec<(GamepadAxis, f32)>,
+    touch_pointer: bool,
+    /// 录制时的按键绑定，重放时不受之后修改设置的影响
+    #[serde(default)]
+    bindings: KeyBindings,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum KeyState {
     JustPressed,
     Pressed,
@@ -34,6 +155,28 @@ impl Input {
             .map(|state| state == &KeyState::JustPressed)
             .unwrap_or(false)
     }
+    pub fn if_action_pressed(&self, action: Action) -> bool {
+        self.if_keyboard_pressed(&self.bindings.key(action))
+    }
+    pub fn if_action_just_pressed(&self, action: Action) -> bool {
+        self.if_keyboard_just_pressed(&self.bindings.key(action))
+    }
+    /// 这一帧刚刚按下的任意一个按键，用于重新绑定按键
+    pub fn just_pressed_key(&self) -> Option<KeyCode> {
+        self.keyboard_inputs
+            .iter()
+            .find(|(_, state)| **state == KeyState::JustPressed)
+            .map(|(key_code, _)| *key_code)
+    }
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_bindings(bindings.clone());
+        }
+        self.bindings = bindings;
+    }
     pub fn if_mouse_pressed(&self, mouse: &MouseButton) -> bool {
         self.mouse_inputs
             .get(mouse)
@@ -46,47 +189,325 @@ impl Input {
             .map(|state| state == &KeyState::JustPressed)
             .unwrap_or(false)
     }
+    pub fn if_mouse_just_released(&self, mouse: &MouseButton) -> bool {
+        self.mouse_just_released.contains(mouse)
+    }
     pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.handle_event(event);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_event(event);
+        }
         match event {
-            WindowEvent::KeyboardInput { event, .. } => {
-                if let PhysicalKey::Code(key_code) = event.physical_key {
-                    if !event.repeat {
-                        match event.state {
-                            ElementState::Pressed => {
-                                if event.repeat {
-                                    *self.keyboard_inputs.get_mut(&key_code).unwrap() =
-                                        KeyState::Pressed;
-                                } else {
-                                    self.keyboard_inputs.insert(key_code, KeyState::JustPressed);
-                                }
-                            }
-                            ElementState::Released => {
-                                self.keyboard_inputs.remove(&key_code);
-                            }
-                        }
+            InputEvent::Keyboard {
+                key_code,
+                pressed,
+                repeat,
+            } => {
+                if !repeat {
+                    if pressed {
+                        self.keyboard_inputs.insert(key_code, KeyState::JustPressed);
+                    } else {
+                        self.keyboard_inputs.remove(&key_code);
                     }
                 }
             }
-            WindowEvent::MouseWheel { .. } => {}
-            WindowEvent::MouseInput { button, state, .. } => match state {
-                ElementState::Pressed => {
+            InputEvent::Mouse { button, pressed } => {
+                if pressed {
                     if let Some(mouse_state) = self.mouse_inputs.get_mut(&button) {
                         *mouse_state = KeyState::Pressed;
                     } else {
-                        self.mouse_inputs.insert(*button, KeyState::JustPressed);
+                        self.mouse_inputs.insert(button, KeyState::JustPressed);
                     }
-                }
-                ElementState::Released => {
+                } else {
                     self.mouse_inputs.remove(&button);
+                    self.mouse_just_released.insert(button);
                 }
-            },
-            WindowEvent::CursorMoved { position, .. } => {
-                self.cursor_pos = Vec2::new(position.x as f32, position.y as f32);
             }
-            _ => {}
+            InputEvent::CursorMoved(position) => {
+                self.cursor_pos = position;
+                self.touch_pointer = false;
+            }
+            InputEvent::Touch {
+                id,
+                phase,
+                position,
+            } => {
+                self.touch.handle_touch(id, phase, position);
+                // 光标跟随最先按下的手指，所有手指抬起后停在最后抬起的位置
+                self.cursor_pos = self.touch.primary_position().unwrap_or(position);
+                self.touch_pointer = true;
+            }
+            InputEvent::GamepadButton { button, pressed } => {
+                if pressed {
+                    self.gamepad_buttons
+                        .entry(button)
+                        .or_insert(KeyState::JustPressed);
+                } else {
+                    self.gamepad_buttons.remove(&button);
+                }
+            }
+            InputEvent::GamepadAxis { axis, value } => {
+                self.gamepad_axes.insert(axis, value);
+            }
+            InputEvent::GamepadConnected(connected) => {
+                self.gamepad_connected = connected;
+                self.gamepad_buttons.clear();
+                self.gamepad_axes.clear();
+            }
         }
     }
 
+    /// 有按键、鼠标按键、手柄按键或者手指还没有松开
+    pub fn if_any_pressed(&self) -> bool {
+        !self.keyboard_inputs.is_empty()
+            || !self.mouse_inputs.is_empty()
+            || !self.gamepad_buttons.is_empty()
+            || self.touch.finger_count() > 0
+    }
+
+    pub fn gamepad_connected(&self) -> bool {
+        self.gamepad_connected
+    }
+    pub fn if_gamepad_pressed(&self, button: &GamepadButton) -> bool {
+        self.gamepad_buttons
+            .get(button)
+            .map(|state| state == &KeyState::Pressed)
+            .unwrap_or(false)
+    }
+    pub fn if_gamepad_just_pressed(&self, button: &GamepadButton) -> bool {
+        self.gamepad_buttons
+            .get(button)
+            .map(|state| state == &KeyState::JustPressed)
+            .unwrap_or(false)
+    }
+    /// 经过死区处理的摇杆输入，长度不超过 1，Y 轴向上为正
+    pub fn gamepad_stick(&self, side: GamepadSide) -> Vec2 {
+        let (x, y) = match side {
+            GamepadSide::Left => (GamepadAxis::LeftStickX, GamepadAxis::LeftStickY),
+            GamepadSide::Right => (GamepadAxis::RightStickX, GamepadAxis::RightStickY),
+        };
+        let stick = Vec2::new(self.gamepad_axis(x), self.gamepad_axis(y));
+        let length = stick.length().min(1.0);
+        if length <= STICK_DEAD_ZONE {
+            return Vec2::ZERO;
+        }
+        stick.normalize() * (length - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)
+    }
+    /// 经过死区处理的扳机输入，在 0 到 1 之间
+    pub fn gamepad_trigger(&self, side: GamepadSide) -> f32 {
+        let axis = match side {
+            GamepadSide::Left => GamepadAxis::LeftTrigger,
+            GamepadSide::Right => GamepadAxis::RightTrigger,
+        };
+        let value = self.gamepad_axis(axis).clamp(0.0, 1.0);
+        if value <= TRIGGER_DEAD_ZONE {
+            return 0.0;
+        }
+        (value - TRIGGER_DEAD_ZONE) / (1.0 - TRIGGER_DEAD_ZONE)
+    }
+    /// 没有经过死区处理的原始输入
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    /// 本帧识别出的触摸手势
+    pub fn gestures(&self) -> &[Gesture] {
+        self.touch.gestures()
+    }
+    pub fn tap(&self) -> Option<Vec2> {
+        self.gestures().iter().find_map(|gesture| match gesture {
+            Gesture::Tap(position) => Some(*position),
+            _ => None,
+        })
+    }
+    pub fn long_press(&self) -> Option<Vec2> {
+        self.gestures().iter().find_map(|gesture| match gesture {
+            Gesture::LongPress(position) => Some(*position),
+            _ => None,
+        })
+    }
+    /// 本帧双指平移的总和，屏幕像素
+    pub fn touch_pan(&self) -> Vec2 {
+        self.gestures()
+            .iter()
+            .filter_map(|gesture| match gesture {
+                Gesture::Pan(delta) => Some(*delta),
+                _ => None,
+            })
+            .sum()
+    }
+    /// 本帧双指缩放的比例，没有缩放时为 1
+    pub fn pinch_scale(&self) -> f32 {
+        self.gestures()
+            .iter()
+            .filter_map(|gesture| match gesture {
+                Gesture::Pinch { scale, .. } => Some(*scale),
+                _ => None,
+            })
+            .product()
+    }
+    /// 光标是否来自触摸，触摸时没有悬停，不应该触发屏幕边缘移动镜头之类的操作
+    pub fn if_touch_pointer(&self) -> bool {
+        self.touch_pointer
+    }
+    pub fn touch_count(&self) -> usize {
+        self.touch.finger_count()
+    }
+    /// 推进手势识别的时间，用于识别长按
+    pub fn update_touch(&mut self, delta: Duration) {
+        self.touch.update(delta);
+    }
+
+    /// 每帧处理游戏逻辑之前调用，推进手势识别和虚拟光标
+    pub fn begin_frame(&mut self, delta: Duration, viewport_size: Vec2) {
+        self.update_touch(delta);
+        self.update_virtual_cursor(delta, viewport_size);
+    }
+
+    pub fn snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            keyboard_inputs: self.keyboard_inputs.iter().map(|(k, v)| (*k, *v)).collect(),
+            cursor_pos: self.cursor_pos,
+            mouse_inputs: self.mouse_inputs.iter().map(|(k, v)| (*k, *v)).collect(),
+            gamepad_connected: self.gamepad_connected,
+            gamepad_buttons: self.gamepad_buttons.iter().map(|(k, v)| (*k, *v)).collect(),
+            gamepad_axes: self.gamepad_axes.iter().map(|(k, v)| (*k, *v)).collect(),
+            touch_pointer: self.touch_pointer,
+            bindings: self.bindings.clone(),
+        }
+    }
+    pub fn from_snapshot(snapshot: &InputSnapshot) -> Input {
+        Input {
+            keyboard_inputs: snapshot.keyboard_inputs.iter().copied().collect(),
+            cursor_pos: snapshot.cursor_pos,
+            mouse_inputs: snapshot.mouse_inputs.iter().copied().collect(),
+            gamepad_connected: snapshot.gamepad_connected,
+            gamepad_buttons: snapshot.gamepad_buttons.iter().copied().collect(),
+            gamepad_axes: snapshot.gamepad_axes.iter().copied().collect(),
+            touch_pointer: snapshot.touch_pointer,
+            bindings: snapshot.bindings.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// 开始录制之后收到的输入事件，已经在录制时会丢弃之前的录制
+    pub fn start_recording(
+        &mut self,
+        viewport: ViewportInfo,
+        fixed_timestep: Duration,
+        camera_zoom: u8,
+    ) {
+        self.recorder = Some(InputRecorder::new(
+            viewport,
+            fixed_timestep,
+            camera_zoom,
+            self.snapshot(),
+        ));
+    }
+    pub fn if_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recorder.take().map(InputRecorder::finish)
+    }
+    pub fn record_resize(&mut self, viewport: ViewportInfo) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_resize(viewport);
+        }
+    }
+    /// 在 fresh 之前调用，结束录制中的一帧
+    pub fn record_frame(&mut self, delta: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_frame(delta, self.pointer_claim, self.keyboard_claim);
+        }
+    }
+    /// 在 record_frame 之前调用，这一帧结束时游戏已经暂停
+    pub fn record_paused(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_paused();
+        }
+    }
+    /// 在 fixed_update 之后调用，记录到最后录制的一帧上
+    pub fn record_fixed_step(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_fixed_step();
+        }
+    }
+    /// 重放录制的一帧，代替这一帧的实时输入
+    pub fn replay_frame(&mut self, frame: &InputFrame) {
+        for event in &frame.events {
+            self.handle_event(*event);
+        }
+        if frame.egui_pointer {
+            self.claim_pointer(InputLayer::Egui);
+        }
+        if frame.egui_keyboard {
+            self.claim_keyboard(InputLayer::Egui);
+        }
+        if let Some(bindings) = &frame.bindings {
+            self.bindings = bindings.clone();
+        }
+    }
+
+    /// 用左摇杆移动光标，光标限制在 viewport 内，所以 `cursor_pos` 既可能来自鼠标也可能来自手柄
+    pub fn update_virtual_cursor(&mut self, delta: Duration, viewport_size: Vec2) {
+        let stick = self.gamepad_stick(GamepadSide::Left);
+        if stick == Vec2::ZERO {
+            return;
+        }
+        let offset = Vec2::new(stick.x, -stick.y) * VIRTUAL_CURSOR_SPEED * delta.as_secs_f32();
+        self.cursor_pos = (self.cursor_pos + offset).clamp(Vec2::ZERO, viewport_size);
+    }
+
+    /// 请求手柄震动，不支持震动的手柄会忽略
+    pub fn rumble(&mut self, strength: f32, duration: Duration) {
+        self.rumble_requests.push(Rumble { strength, duration });
+    }
+    pub fn take_rumble_requests(&mut self) -> Vec<Rumble> {
+        std::mem::take(&mut self.rumble_requests)
+    }
+
+    /// 当前帧鼠标输入是否可以被 layer 使用
+    pub fn pointer_available(&self, layer: InputLayer) -> bool {
+        self.pointer_claim.is_none_or(|claim| claim == layer)
+    }
+
+    /// 当前帧键盘输入是否可以被 layer 使用
+    pub fn keyboard_available(&self, layer: InputLayer) -> bool {
+        self.keyboard_claim.is_none_or(|claim| claim == layer)
+    }
+
+    pub fn pointer_claim(&self) -> Option<InputLayer> {
+        self.pointer_claim
+    }
+
+    pub fn keyboard_claim(&self) -> Option<InputLayer> {
+        self.keyboard_claim
+    }
+
+    /// 占用这一帧的鼠标输入，已经被其他层占用时返回 false
+    pub fn claim_pointer(&mut self, layer: InputLayer) -> bool {
+        if !self.pointer_available(layer) {
+            return false;
+        }
+        self.pointer_claim = Some(layer);
+        true
+    }
+
+    /// 占用这一帧的键盘输入，已经被其他层占用时返回 false
+    pub fn claim_keyboard(&mut self, layer: InputLayer) -> bool {
+        if !self.keyboard_available(layer) {
+            return false;
+        }
+        self.keyboard_claim = Some(layer);
+        true
+    }
+
     /// 经过一轮判断后，就需要将所有 just 的状态都修改掉
     pub fn fresh(&mut self) {
         for (_, state) in self.keyboard_inputs.iter_mut() {
@@ -99,5 +520,118 @@ impl Input {
                 *state = KeyState::Pressed;
             }
         }
+        for (_, state) in self.gamepad_buttons.iter_mut() {
+            if state == &KeyState::JustPressed {
+                *state = KeyState::Pressed;
+            }
+        }
+        self.mouse_just_released.clear();
+        self.touch.clear_gestures();
+        self.pointer_claim = None;
+        self.keyboard_claim = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn click(input: &mut Input, position: Vec2) {
+        input.handle_event(InputEvent::CursorMoved(position));
+        input.handle_event(InputEvent::Mouse {
+            button: MouseButton::Left,
+            pressed: true,
+        });
+    }
+
+    #[test]
+    fn synthetic_events_update_state() {
+        let mut input = Input::default();
+        click(&mut input, Vec2::new(10.0, 20.0));
+        input.handle_event(InputEvent::Keyboard {
+            key_code: KeyCode::KeyS,
+            pressed: true,
+            repeat: false,
+        });
+        assert_eq!(input.cursor_pos(), Vec2::new(10.0, 20.0));
+        assert!(input.if_mouse_just_pressed(&MouseButton::Left));
+        assert!(input.if_keyboard_just_pressed(&KeyCode::KeyS));
+
+        input.fresh();
+        assert!(input.if_mouse_pressed(&MouseButton::Left));
+        assert!(input.if_keyboard_pressed(&KeyCode::KeyS));
+
+        input.handle_event(InputEvent::Mouse {
+            button: MouseButton::Left,
+            pressed: false,
+        });
+        assert!(input.if_mouse_just_released(&MouseButton::Left));
+        assert!(!input.if_mouse_pressed(&MouseButton::Left));
+        input.fresh();
+        assert!(!input.if_mouse_just_released(&MouseButton::Left));
+    }
+
+    #[test]
+    fn earlier_layer_claim_blocks_later_layers() {
+        let mut input = Input::default();
+        click(&mut input, Vec2::ZERO);
+        assert!(input.pointer_available(InputLayer::Scene));
+
+        assert!(input.claim_pointer(InputLayer::Egui));
+        assert!(input.pointer_available(InputLayer::Egui));
+        assert!(!input.pointer_available(InputLayer::Ui));
+        assert!(!input.claim_pointer(InputLayer::Ui));
+        assert!(!input.pointer_available(InputLayer::Scene));
+        // 键盘和鼠标分别占用
+        assert!(input.keyboard_available(InputLayer::Scene));
+        assert!(input.claim_keyboard(InputLayer::Ui));
+        assert!(!input.keyboard_available(InputLayer::Scene));
+        assert_eq!(input.pointer_claim(), Some(InputLayer::Egui));
+        assert_eq!(input.keyboard_claim(), Some(InputLayer::Ui));
+
+        // 占用只在当前帧有效
+        input.fresh();
+        assert!(input.pointer_available(InputLayer::Scene));
+        assert!(input.keyboard_available(InputLayer::Scene));
+    }
+
+    #[test]
+    fn gamepad_dead_zone_and_virtual_cursor() {
+        let mut input = Input::default();
+        input.handle_event(InputEvent::GamepadConnected(true));
+        input.handle_event(InputEvent::GamepadButton {
+            button: GamepadButton::South,
+            pressed: true,
+        });
+        input.handle_event(InputEvent::GamepadAxis {
+            axis: GamepadAxis::LeftStickX,
+            value: STICK_DEAD_ZONE * 0.5,
+        });
+        assert!(input.if_gamepad_just_pressed(&GamepadButton::South));
+        assert_eq!(input.gamepad_stick(GamepadSide::Left), Vec2::ZERO);
+        input.update_virtual_cursor(Duration::from_secs(1), Vec2::new(100.0, 100.0));
+        assert_eq!(input.cursor_pos(), Vec2::ZERO);
+
+        input.fresh();
+        assert!(input.if_gamepad_pressed(&GamepadButton::South));
+        input.handle_event(InputEvent::GamepadAxis {
+            axis: GamepadAxis::LeftStickX,
+            value: 1.0,
+        });
+        input.handle_event(InputEvent::GamepadAxis {
+            axis: GamepadAxis::LeftStickY,
+            value: -1.0,
+        });
+        let stick = input.gamepad_stick(GamepadSide::Left);
+        assert!((stick.length() - 1.0).abs() < 1e-5);
+        // Y 轴向上为正，所以向下推摇杆时光标在屏幕上向下移动，并且不会超出 viewport
+        input.update_virtual_cursor(Duration::from_secs(1), Vec2::new(100.0, 100.0));
+        assert_eq!(input.cursor_pos(), Vec2::new(100.0, 100.0));
+
+        // 断开连接会清空状态
+        input.handle_event(InputEvent::GamepadConnected(false));
+        assert!(!input.gamepad_connected());
+        assert!(!input.if_gamepad_pressed(&GamepadButton::South));
+        assert_eq!(input.gamepad_stick(GamepadSide::Left), Vec2::ZERO);
     }
 }
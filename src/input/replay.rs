@@ -0,0 +1,200 @@
+use super::{InputEvent, InputLayer, InputSnapshot, KeyBindings};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 录制文件的版本，格式变化时需要增加
+pub const RECORDING_VERSION: u32 = 2;
+
+/// 游戏画面的尺寸，重放时需要使用录制时的尺寸，才能得到相同的光标世界坐标
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct ViewportInfo {
+    pub size: [u32; 2],
+    pub scale_factor: f32,
+}
+
+/// 一帧内收到的所有输入
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    pub delta: Duration,
+    pub events: Vec<InputEvent>,
+    /// 这一帧 egui 是否占用了鼠标和键盘，egui 不参与重放，所以直接记录结果
+    #[serde(default)]
+    pub egui_pointer: bool,
+    #[serde(default)]
+    pub egui_keyboard: bool,
+    /// 这一帧开始前画面尺寸发生了变化
+    #[serde(default)]
+    pub resize: Option<ViewportInfo>,
+    /// 这一帧执行 fixed_update 的次数，重放时不再根据 delta 重新计算
+    #[serde(default)]
+    pub fixed_steps: u32,
+    /// 这一帧结束时游戏被暂停菜单等状态盖住，之后的 fixed_update 和下一帧的输入都不会交给游戏
+    #[serde(default)]
+    pub paused: bool,
+    /// 这一帧在设置中修改了按键绑定
+    #[serde(default)]
+    pub bindings: Option<KeyBindings>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InputRecording {
+    pub version: u32,
+    pub viewport: ViewportInfo,
+    /// fixed_update 的时间步长
+    pub fixed_timestep: Duration,
+    /// 开始录制时镜头的缩放，由设置中的默认缩放决定
+    #[serde(default = "default_camera_zoom")]
+    pub camera_zoom: u8,
+    /// 开始录制时 Input 的状态，比如光标位置和已经按下的按键
+    #[serde(default)]
+    pub initial: InputSnapshot,
+    pub frames: Vec<InputFrame>,
+}
+
+fn default_camera_zoom() -> u8 {
+    2
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Json(serde_json::Error),
+    Version(u32),
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Json(err) => write!(f, "Invalid input recording: {err}"),
+            RecordingError::Version(version) => write!(
+                f,
+                "Unsupported input recording version {version}, expected {RECORDING_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl InputRecording {
+    pub fn new(
+        viewport: ViewportInfo,
+        fixed_timestep: Duration,
+        camera_zoom: u8,
+        initial: InputSnapshot,
+    ) -> InputRecording {
+        InputRecording {
+            version: RECORDING_VERSION,
+            viewport,
+            fixed_timestep,
+            camera_zoom,
+            initial,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Failed to serialize input recording")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<InputRecording, RecordingError> {
+        let recording: InputRecording =
+            serde_json::from_slice(bytes).map_err(RecordingError::Json)?;
+        if recording.version != RECORDING_VERSION {
+            return Err(RecordingError::Version(recording.version));
+        }
+        Ok(recording)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.delta).sum()
+    }
+}
+
+/// 按帧收集输入事件
+#[derive(Debug)]
+pub struct InputRecorder {
+    recording: InputRecording,
+    current: InputFrame,
+}
+
+impl InputRecorder {
+    pub fn new(
+        viewport: ViewportInfo,
+        fixed_timestep: Duration,
+        camera_zoom: u8,
+        initial: InputSnapshot,
+    ) -> InputRecorder {
+        InputRecorder {
+            recording: InputRecording::new(viewport, fixed_timestep, camera_zoom, initial),
+            current: InputFrame::default(),
+        }
+    }
+
+    pub fn record_event(&mut self, event: InputEvent) {
+        self.current.events.push(event);
+    }
+
+    pub fn record_resize(&mut self, viewport: ViewportInfo) {
+        self.current.resize = Some(viewport);
+    }
+
+    pub fn record_bindings(&mut self, bindings: KeyBindings) {
+        self.current.bindings = Some(bindings);
+    }
+
+    pub fn record_paused(&mut self) {
+        self.current.paused = true;
+    }
+
+    /// 结束当前帧，pointer_claim 和 keyboard_claim 为这一帧结束时 Input 上的占用
+    pub fn end_frame(
+        &mut self,
+        delta: Duration,
+        pointer_claim: Option<InputLayer>,
+        keyboard_claim: Option<InputLayer>,
+    ) {
+        let mut frame = std::mem::take(&mut self.current);
+        frame.delta = delta;
+        frame.egui_pointer = pointer_claim == Some(InputLayer::Egui);
+        frame.egui_keyboard = keyboard_claim == Some(InputLayer::Egui);
+        self.recording.frames.push(frame);
+    }
+
+    /// 记录上一帧结束后执行了一次 fixed_update
+    pub fn record_fixed_step(&mut self) {
+        if let Some(frame) = self.recording.frames.last_mut() {
+            frame.fixed_steps += 1;
+        }
+    }
+
+    pub fn finish(self) -> InputRecording {
+        self.recording
+    }
+}
+
+/// 按顺序取出录制的帧
+#[derive(Debug)]
+pub struct InputReplay {
+    recording: InputRecording,
+    next: usize,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> InputReplay {
+        InputReplay { recording, next: 0 }
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    pub fn next_frame(&mut self) -> Option<&InputFrame> {
+        let frame = self.recording.frames.get(self.next)?;
+        self.next += 1;
+        Some(frame)
+    }
+
+    pub fn if_finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+}
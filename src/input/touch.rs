@@ -0,0 +1,244 @@
+use glam::Vec2;
+use std::collections::HashMap;
+use std::time::Duration;
+use winit::event::TouchPhase;
+
+/// 手指移动超过这个距离（像素）就不再算作点击或者长按
+pub const TAP_SLOP: f32 = 12.0;
+/// 按住超过这个时间就触发长按
+pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gesture {
+    /// 单指轻点，位置为抬起时的位置
+    Tap(Vec2),
+    /// 单指按住不动，触发后直到手指抬起都不会再触发点击
+    LongPress(Vec2),
+    /// 双指平移，屏幕像素的偏移
+    Pan(Vec2),
+    /// 双指缩放，scale 为双指距离与上一次的比值，大于 1 表示放大
+    Pinch { center: Vec2, scale: f32 },
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Finger {
+    start: Vec2,
+    position: Vec2,
+    start_time: Duration,
+}
+
+/// 多点触控手势识别
+///
+/// 从第一根手指按下到所有手指抬起算作一次手势，期间只要出现过第二根手指，
+/// 这次手势就只会识别双指平移和缩放，不会再触发点击和长按。
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    fingers: HashMap<u64, Finger>,
+    /// 按下顺序，用于决定主手指和双指手势使用的两根手指
+    order: Vec<u64>,
+    time: Duration,
+    /// 本次手势中出现过多根手指或者移动超出了范围
+    cancelled_tap: bool,
+    long_pressed: bool,
+    /// 上一次双指的中心和距离
+    last_pair: Option<(Vec2, f32)>,
+    gestures: Vec<Gesture>,
+}
+
+impl GestureRecognizer {
+    pub fn handle_touch(&mut self, id: u64, phase: TouchPhase, position: Vec2) {
+        match phase {
+            TouchPhase::Started => {
+                if self.fingers.is_empty() {
+                    self.cancelled_tap = false;
+                    self.long_pressed = false;
+                } else {
+                    self.cancelled_tap = true;
+                }
+                self.fingers.insert(
+                    id,
+                    Finger {
+                        start: position,
+                        position,
+                        start_time: self.time,
+                    },
+                );
+                self.order.push(id);
+                self.last_pair = self.pair();
+            }
+            TouchPhase::Moved => {
+                let Some(finger) = self.fingers.get_mut(&id) else {
+                    return;
+                };
+                finger.position = position;
+                if finger.start.distance(position) > TAP_SLOP {
+                    self.cancelled_tap = true;
+                }
+                if let (Some((last_center, last_distance)), Some((center, distance))) =
+                    (self.last_pair, self.pair())
+                {
+                    if center != last_center {
+                        self.gestures.push(Gesture::Pan(center - last_center));
+                    }
+                    if distance != last_distance && last_distance > 0.0 {
+                        self.gestures.push(Gesture::Pinch {
+                            center,
+                            scale: distance / last_distance,
+                        });
+                    }
+                    self.last_pair = Some((center, distance));
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.fingers.remove(&id).is_none() {
+                    return;
+                }
+                self.order.retain(|finger_id| *finger_id != id);
+                if phase == TouchPhase::Ended
+                    && self.fingers.is_empty()
+                    && !self.cancelled_tap
+                    && !self.long_pressed
+                {
+                    self.gestures.push(Gesture::Tap(position));
+                }
+                // 手指抬起后剩下的手指重新开始计算双指手势，避免中心跳变
+                self.last_pair = self.pair();
+            }
+        }
+    }
+
+    /// 推进时间，用于判断长按
+    pub fn update(&mut self, delta: Duration) {
+        self.time += delta;
+        if self.cancelled_tap || self.long_pressed || self.fingers.len() != 1 {
+            return;
+        }
+        let finger = self.fingers[&self.order[0]];
+        if self.time - finger.start_time >= LONG_PRESS_DURATION {
+            self.long_pressed = true;
+            self.gestures.push(Gesture::LongPress(finger.position));
+        }
+    }
+
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+
+    pub fn clear_gestures(&mut self) {
+        self.gestures.clear();
+    }
+
+    /// 最先按下并且还没有抬起的手指位置
+    pub fn primary_position(&self) -> Option<Vec2> {
+        self.order.first().map(|id| self.fingers[id].position)
+    }
+
+    pub fn finger_count(&self) -> usize {
+        self.fingers.len()
+    }
+
+    fn pair(&self) -> Option<(Vec2, f32)> {
+        let [a, b, ..] = self.order.as_slice() else {
+            return None;
+        };
+        let a = self.fingers[a].position;
+        let b = self.fingers[b].position;
+        Some(((a + b) * 0.5, a.distance(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn pinch_scale(recognizer: &GestureRecognizer) -> f32 {
+        recognizer
+            .gestures()
+            .iter()
+            .filter_map(|gesture| match gesture {
+                Gesture::Pinch { scale, .. } => Some(*scale),
+                _ => None,
+            })
+            .product()
+    }
+
+    #[test]
+    fn tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle_touch(0, TouchPhase::Started, Vec2::new(10.0, 10.0));
+        recognizer.update(FRAME);
+        recognizer.handle_touch(0, TouchPhase::Moved, Vec2::new(14.0, 12.0));
+        recognizer.update(FRAME);
+        recognizer.handle_touch(0, TouchPhase::Ended, Vec2::new(14.0, 12.0));
+        assert_eq!(
+            recognizer.gestures(),
+            &[Gesture::Tap(Vec2::new(14.0, 12.0))]
+        );
+    }
+
+    #[test]
+    fn moving_too_far_or_cancel_is_not_a_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle_touch(0, TouchPhase::Started, Vec2::ZERO);
+        recognizer.handle_touch(0, TouchPhase::Moved, Vec2::new(TAP_SLOP + 1.0, 0.0));
+        recognizer.handle_touch(0, TouchPhase::Ended, Vec2::new(TAP_SLOP + 1.0, 0.0));
+        assert!(recognizer.gestures().is_empty());
+
+        recognizer.handle_touch(1, TouchPhase::Started, Vec2::ZERO);
+        recognizer.handle_touch(1, TouchPhase::Cancelled, Vec2::ZERO);
+        assert!(recognizer.gestures().is_empty());
+    }
+
+    #[test]
+    fn long_press_fires_once_and_suppresses_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle_touch(0, TouchPhase::Started, Vec2::new(5.0, 5.0));
+        let mut elapsed = Duration::ZERO;
+        while elapsed < LONG_PRESS_DURATION * 2 {
+            recognizer.update(FRAME);
+            elapsed += FRAME;
+        }
+        recognizer.handle_touch(0, TouchPhase::Ended, Vec2::new(5.0, 5.0));
+        assert_eq!(
+            recognizer.gestures(),
+            &[Gesture::LongPress(Vec2::new(5.0, 5.0))]
+        );
+    }
+
+    #[test]
+    fn two_finger_pan_and_pinch() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle_touch(0, TouchPhase::Started, Vec2::new(0.0, 0.0));
+        recognizer.handle_touch(1, TouchPhase::Started, Vec2::new(100.0, 0.0));
+        recognizer.clear_gestures();
+
+        // 两根手指一起向下移动，平移的总和等于手指的移动距离，缩放的乘积为 1
+        recognizer.handle_touch(0, TouchPhase::Moved, Vec2::new(0.0, 20.0));
+        recognizer.handle_touch(1, TouchPhase::Moved, Vec2::new(100.0, 20.0));
+        let pan: Vec2 = recognizer
+            .gestures()
+            .iter()
+            .filter_map(|gesture| match gesture {
+                Gesture::Pan(delta) => Some(*delta),
+                _ => None,
+            })
+            .sum();
+        assert_eq!(pan, Vec2::new(0.0, 20.0));
+        assert!((pinch_scale(&recognizer) - 1.0).abs() < 1e-5);
+        recognizer.clear_gestures();
+
+        // 两根手指对称张开，中心不变，只有缩放
+        recognizer.handle_touch(0, TouchPhase::Moved, Vec2::new(-50.0, 20.0));
+        recognizer.handle_touch(1, TouchPhase::Moved, Vec2::new(150.0, 20.0));
+        assert!((pinch_scale(&recognizer) - 2.0).abs() < 1e-5);
+
+        // 双指手势结束后不会触发点击
+        recognizer.clear_gestures();
+        recognizer.handle_touch(0, TouchPhase::Ended, Vec2::new(-50.0, 20.0));
+        recognizer.handle_touch(1, TouchPhase::Ended, Vec2::new(150.0, 20.0));
+        assert!(recognizer.gestures().is_empty());
+        assert_eq!(recognizer.finger_count(), 0);
+    }
+}
@@ -4,18 +4,34 @@ mod audio;
 mod easing;
 #[cfg(feature = "editor_mode")]
 mod egui_render;
+mod error;
 mod fps;
+mod frame_limiter;
 mod framework;
 mod input;
 mod render;
+mod save;
+mod timestep;
+mod ui;
 mod utils;
 
 pub use app::*;
+pub use assets::{
+    AssetEntry, AssetKind, AssetServer, AssetSource, AssetsId, AudioSettings, LoadState,
+    ParseAssetsIdError, SamplerSettings, TextureAddressMode, TextureFilter, MANIFEST,
+    PACKAGE_SIDEBOARD, SCENE_SIDEBOARD,
+};
 pub use audio::*;
 pub use easing::*;
+pub use error::*;
 pub use fps::*;
+pub use frame_limiter::*;
 pub use framework::*;
+pub use input::*;
 pub use render::*;
+pub use save::*;
+pub use timestep::*;
+pub use ui::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -25,5 +41,7 @@ pub const WEB_CANVAS_CONTAINER: &'static str = "wgpu-app-container";
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn start() {
-    run::<AppData>(PKG_NAME).expect("TODO: panic message");
+    if let Err(err) = run::<AppData>(PKG_NAME) {
+        log::error!("{err}");
+    }
 }
@@ -1,5 +1,5 @@
-use crate::{EasingAnimator, Rect, Transform};
-use glam::{Mat4, Vec2, Vec3, Vec4};
+use crate::{CameraEffects, EasingAnimator, Rect, Transform};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 use std::time::Duration;
 
 #[derive(Debug, Default)]
@@ -31,6 +31,8 @@ pub struct Camera2D {
     /// 限制放大倍率只有 x1 x2 x4 x8
     pub pixel_zoom: u8,
     pub scale_animator: EasingAnimator,
+    /// 抖动、冲击缩放、闪烁等特效，只影响渲染，不影响逻辑上的镜头位置
+    pub effects: CameraEffects,
 }
 
 impl Camera2D {
@@ -44,6 +46,7 @@ impl Camera2D {
             far: 1.0,
             pixel_zoom: 1,
             scale_animator: EasingAnimator::default(),
+            effects: CameraEffects::default(),
         }
     }
 
@@ -120,6 +123,10 @@ impl Camera2D {
         }
     }
 
+    pub fn update_effects(&mut self, delta: Duration) {
+        self.effects.update(delta);
+    }
+
     /// 限制镜头展示的世界空间范围，需要配合 transform.scale 来判断镜头实际上可以显示的范围，
     /// 并根据 viewport_size 来判断镜头的 transform.translation 范围
     pub fn update_word_boundary(&mut self, word_boundary: Option<Rect>) {
@@ -171,9 +178,24 @@ impl Camera2D {
         )
     }
 
+    /// 叠加特效后实际用于渲染的 transform
+    #[inline(always)]
+    fn get_effect_transform(&self) -> Transform {
+        let mut transform = self.transform;
+        // 偏移量是屏幕像素，需要乘上镜头缩放转换成世界空间的距离
+        let offset = self.effects.offset() * transform.scale.truncate();
+        transform.translation.x += offset.x;
+        transform.translation.y += offset.y;
+        transform.rotation *= Quat::from_rotation_z(self.effects.angle());
+        let zoom = 1.0 / (1.0 + self.effects.zoom());
+        transform.scale.x *= zoom;
+        transform.scale.y *= zoom;
+        transform
+    }
+
     #[inline(always)]
     fn get_world_from_view(&self) -> Mat4 {
-        self.transform.compute_matrix()
+        self.get_effect_transform().compute_matrix()
     }
 
     #[inline(always)]
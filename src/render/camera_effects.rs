@@ -0,0 +1,222 @@
+use crate::{Color, EasingAnimator};
+use glam::{Vec2, Vec4};
+use std::time::Duration;
+
+/// 镜头特效层
+///
+/// 所有效果都只是叠加在 Camera2D 逻辑 transform 之上的偏移量，只在提交渲染时生效，
+/// 所以 `viewport_to_world` 和 `update_word_boundary` 依旧使用没有抖动的镜头进行计算。
+///
+/// 抖动使用 trauma 模型：trauma 在 0 - 1 之间，随时间线性衰减，实际抖动强度为 trauma 的平方，
+/// 抖动方向由噪声采样得到，只要 seed 相同，相同的 update 序列就一定会得到相同的结果。
+#[derive(Debug)]
+pub struct CameraEffects {
+    seed: u32,
+    /// 累计的运行时间（秒），用于采样噪声
+    time: f32,
+    trauma: f32,
+    /// 每秒衰减的 trauma
+    pub trauma_decay: f32,
+    /// trauma 为 1 时的最大平移偏移，单位是屏幕像素，不受镜头缩放影响
+    pub max_offset: Vec2,
+    /// trauma 为 1 时的最大旋转角度（弧度）
+    pub max_angle: f32,
+    /// 噪声的采样频率，越大抖动越快
+    pub frequency: f32,
+    punch_zoom: EasingAnimator,
+    flash_color: Color,
+    flash_opacity: EasingAnimator,
+    offset: Vec2,
+    angle: f32,
+    zoom: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        CameraEffects::new(0)
+    }
+}
+
+impl CameraEffects {
+    pub fn new(seed: u32) -> CameraEffects {
+        CameraEffects {
+            seed,
+            time: 0.0,
+            trauma: 0.0,
+            trauma_decay: 1.2,
+            max_offset: Vec2::new(12.0, 8.0),
+            max_angle: 0.03,
+            frequency: 24.0,
+            punch_zoom: EasingAnimator::default(),
+            flash_color: Color::default(),
+            flash_opacity: EasingAnimator::default(),
+            offset: Vec2::ZERO,
+            angle: 0.0,
+            zoom: 0.0,
+        }
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// 增加 trauma，多次叠加时最大为 1
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    /// 瞬间放大后回弹，strength 为放大比例，比如 0.05 表示放大 5%
+    pub fn punch_zoom(&mut self, strength: f32, duration: Duration) {
+        self.punch_zoom = EasingAnimator::new(strength, 0.0, duration);
+        self.zoom = strength;
+    }
+
+    /// 全屏闪烁，color 的透明度作为闪烁开始时的透明度，之后在 duration 内淡出
+    pub fn flash(&mut self, color: Color, duration: Duration) {
+        let opacity = color.as_vec4().w;
+        self.flash_color = color;
+        self.flash_opacity = EasingAnimator::new(opacity, 0.0, duration);
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        let delta_secs = delta.as_secs_f32();
+        self.time += delta_secs;
+        self.trauma = (self.trauma - self.trauma_decay * delta_secs).max(0.0);
+
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        self.offset = Vec2::new(
+            self.max_offset.x * shake * value_noise(self.seed, 0, t),
+            self.max_offset.y * shake * value_noise(self.seed, 1, t),
+        );
+        self.angle = self.max_angle * shake * value_noise(self.seed, 2, t);
+
+        self.zoom = if self.punch_zoom.if_finished() {
+            0.0
+        } else {
+            self.punch_zoom.update(delta)
+        };
+
+        if !self.flash_opacity.if_finished() {
+            let opacity = self.flash_opacity.update(delta);
+            self.flash_color.set_opacity(opacity);
+        }
+    }
+
+    /// 还有没有结束的特效
+    pub fn if_active(&self) -> bool {
+        self.trauma > 0.0 || !self.punch_zoom.if_finished() || !self.flash_opacity.if_finished()
+    }
+
+    /// 当前的平移偏移，单位是屏幕像素
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+
+    /// 当前的旋转偏移（弧度）
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// 当前的放大比例，0 表示没有放大
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// 当前需要叠加在画面上的颜色，没有闪烁时返回 None
+    pub fn flash_color(&self) -> Option<Vec4> {
+        if self.flash_opacity.if_finished() {
+            return None;
+        }
+        Some(self.flash_color.as_vec4())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Default)]
+pub struct FlashUniform {
+    pub color: Vec4,
+}
+
+/// 整数哈希，返回 -1 到 1 之间的值
+#[inline]
+fn hash(seed: u32, channel: u32, index: i32) -> f32 {
+    let mut x = seed ^ channel.wrapping_mul(0x9E37_79B9) ^ (index as u32).wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// 一维 value noise，相邻整数点之间做 smoothstep 插值，保证抖动是连续的
+fn value_noise(seed: u32, channel: u32, t: f32) -> f32 {
+    let index = t.floor();
+    let fraction = t - index;
+    let a = hash(seed, channel, index as i32);
+    let b = hash(seed, channel, index as i32 + 1);
+    let smooth = fraction * fraction * (3.0 - 2.0 * fraction);
+    a + (b - a) * smooth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(effects: &mut CameraEffects, frames: usize) -> Vec<(Vec2, f32)> {
+        (0..frames)
+            .map(|_| {
+                effects.update(Duration::from_millis(16));
+                (effects.offset(), effects.angle())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shake_is_deterministic_with_seed() {
+        let mut a = CameraEffects::new(42);
+        let mut b = CameraEffects::new(42);
+        a.add_trauma(1.0);
+        b.add_trauma(1.0);
+        assert_eq!(run(&mut a, 30), run(&mut b, 30));
+
+        let mut c = CameraEffects::new(7);
+        c.add_trauma(1.0);
+        assert_ne!(run(&mut a, 30), run(&mut c, 30));
+    }
+
+    #[test]
+    fn shake_stays_in_range_and_decays() {
+        let mut effects = CameraEffects::new(1);
+        effects.add_trauma(2.0);
+        assert_eq!(effects.trauma(), 1.0);
+        for (offset, angle) in run(&mut effects, 120) {
+            assert!(offset.x.abs() <= effects.max_offset.x);
+            assert!(offset.y.abs() <= effects.max_offset.y);
+            assert!(angle.abs() <= effects.max_angle);
+        }
+        assert_eq!(effects.trauma(), 0.0);
+        assert_eq!(effects.offset(), Vec2::ZERO);
+        assert_eq!(effects.angle(), 0.0);
+    }
+
+    #[test]
+    fn punch_zoom_and_flash_finish() {
+        let mut effects = CameraEffects::new(0);
+        effects.punch_zoom(0.1, Duration::from_millis(200));
+        effects.flash(Color::new([255, 255, 255, 200]), Duration::from_millis(200));
+        assert_eq!(effects.zoom(), 0.1);
+        assert!(effects.flash_color().is_some());
+
+        effects.update(Duration::from_millis(100));
+        assert!(effects.zoom() > 0.0 && effects.zoom() < 0.1);
+        let opacity = effects.flash_color().unwrap().w;
+        assert!(opacity > 0.0 && opacity < 200.0 / 255.0);
+
+        effects.update(Duration::from_millis(100));
+        effects.update(Duration::from_millis(16));
+        assert_eq!(effects.zoom(), 0.0);
+        assert!(effects.flash_color().is_none());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{Camera2D, Color, Rect, RenderLayers, ScreenRepeat, Sprite};
+
+/// 一帧内的一次镜头渲染
+///
+/// 多个 CameraPass 会按顺序渲染到同一个 surface 上，比如先渲染场景再用固定的屏幕空间镜头渲染 UI，
+/// 或者分屏、小地图等需要在 surface 的局部区域渲染的情况。
+#[derive(Copy, Clone, Debug)]
+pub struct CameraPass<'a> {
+    /// NOTE camera 的 viewport_size 需要与 viewport 的尺寸保持一致
+    pub camera: &'a Camera2D,
+    /// 渲染到 surface 上的区域，单位是像素，以左上角为原点，None 表示整个 surface
+    pub viewport: Option<Rect>,
+    pub clear: ClearPolicy,
+    /// 只渲染与 layers 有交集的 sprite
+    pub layers: RenderLayers,
+    pub screen_repeat: Option<&'a ScreenRepeat>,
+}
+
+impl<'a> CameraPass<'a> {
+    pub fn new(camera: &'a Camera2D) -> CameraPass<'a> {
+        CameraPass {
+            camera,
+            viewport: None,
+            clear: ClearPolicy::Load,
+            layers: RenderLayers::default(),
+            screen_repeat: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub enum ClearPolicy {
+    /// 保留之前渲染的内容
+    #[default]
+    Load,
+    /// 渲染前使用指定颜色清空 viewport 区域
+    Color(Color),
+}
+
+/// 一组镜头和它们渲染的 sprite
+///
+/// 多组按顺序渲染到同一帧上，组之间的 sprite 互不影响，比如暂停菜单覆盖在游戏画面上。
+#[derive(Clone, Debug, Default)]
+pub struct RenderGroup<'a> {
+    pub passes: Vec<CameraPass<'a>>,
+    pub sprites: Vec<&'a Sprite>,
+}
@@ -0,0 +1,38 @@
+struct Flash {
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> flash: Flash;
+
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    // 全屏四边形顶点数据
+    let pos = array(
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(-1.0, 1.0),
+        vec2(-1.0, 1.0),
+        vec2(1.0, -1.0),
+        vec2(1.0, 1.0)
+    );
+
+    var out: VertexOutput;
+    out.clip_position = vec4(pos[in.index], 0.0, 1.0);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return flash.color;
+}
@@ -1,29 +1,45 @@
 mod blend_mode;
 mod camera;
+mod camera_effects;
+mod camera_pass;
 mod color;
 mod pipeline;
+mod present_mode;
 mod rect;
 mod render_item;
+mod render_layers;
 mod screen_repeat;
 mod sprite;
 mod sprite_instance;
+mod surface;
 mod texture_store;
 mod transform;
+mod transition;
 mod ui_sprite;
 
 pub use blend_mode::*;
 pub use camera::*;
+pub use camera_effects::*;
+pub use camera_pass::*;
 pub use color::*;
 pub use pipeline::*;
+pub use present_mode::*;
 pub use rect::*;
 pub use render_item::*;
+pub use render_layers::*;
 pub use screen_repeat::*;
 pub use sprite::*;
 pub use sprite_instance::*;
-pub use texture_store::TextureStore;
+pub use surface::{acquire_frame, RenderError, RenderSurface};
+pub use texture_store::{
+    decode_image, missing_texture_image, TextureStore, CIRCLE_TEXTURE, WHITE_TEXTURE,
+};
 pub use transform::*;
+pub use transition::*;
 pub use ui_sprite::*;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use wgpu::util::DeviceExt;
 
 pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
@@ -31,27 +47,40 @@ pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
 pub const MASK_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
 
 pub struct Render {
-    pub surface: wgpu::Surface<'static>,
+    window: Arc<winit::window::Window>,
+    /// 重新创建设备时继续使用原来的 instance 和 surface
+    instance: wgpu::Instance,
+    /// 同一个窗口上不能同时配置两个 surface，所以设备丢失后新的 Render 共用这个 surface
+    pub surface: Arc<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
+    /// surface 支持的提交模式
+    present_modes: Vec<wgpu::PresentMode>,
     uniform_bind_group_layout: wgpu::BindGroupLayout,
     texture_bind_group_layout: wgpu::BindGroupLayout,
     mask_texture: wgpu::Texture,
     grab_texture: wgpu::Texture,
     grab_texture_bind_group: wgpu::BindGroup,
+    /// 转场时保存旧画面
+    transition_texture: wgpu::Texture,
+    transition_texture_bind_group: wgpu::BindGroup,
     render_pipeline: wgpu::RenderPipeline,
     blend_mode_pipeline: wgpu::RenderPipeline,
     blur_pipeline: wgpu::RenderPipeline,
     mask_start_pipeline: wgpu::RenderPipeline,
     mask_end_pipeline: wgpu::RenderPipeline,
     screen_repeat_pipeline: wgpu::RenderPipeline,
+    flash_pipeline: wgpu::RenderPipeline,
+    /// 清空 viewport 区域，直接写入颜色并把模板清零
+    clear_pipeline: wgpu::RenderPipeline,
+    transition_pipeline: wgpu::RenderPipeline,
+    /// 驱动重置、显卡被移除等原因导致设备丢失时，由 wgpu 的回调设置
+    device_lost: Arc<AtomicBool>,
 }
 
 impl Render {
-    pub async fn new(
-        window: std::sync::Arc<winit::window::Window>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(window: Arc<winit::window::Window>) -> Result<Self, crate::Error> {
         log::info!("initializing the surface...");
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
@@ -59,7 +88,18 @@ impl Render {
             flags: wgpu::InstanceFlags::DEBUG,
             ..Default::default()
         });
-        let surface = instance.create_surface(window.clone())?;
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(|err| crate::Error::RenderInit(err.to_string()))?;
+        Render::with_surface(window, instance, Arc::new(surface)).await
+    }
+
+    /// 在已有的 surface 上请求适配器和设备，创建所有管线
+    async fn with_surface(
+        window: Arc<winit::window::Window>,
+        instance: wgpu::Instance,
+        surface: Arc<wgpu::Surface<'static>>,
+    ) -> Result<Self, crate::Error> {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::HighPerformance,
@@ -67,7 +107,9 @@ impl Render {
                 force_fallback_adapter: false,
             })
             .await
-            .expect("no suitable GPU adapters found on the system!");
+            .ok_or_else(|| {
+                crate::Error::RenderInit("no suitable GPU adapters found on the system!".into())
+            })?;
         let adapter_info = adapter.get_info();
         log::info!("adapter info: {adapter_info:?}");
         let caps = surface.get_capabilities(&adapter);
@@ -81,14 +123,24 @@ impl Render {
                 },
                 None,
             )
-            .await?;
+            .await
+            .map_err(|err| crate::Error::RenderInit(err.to_string()))?;
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let device_lost_flag = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // 重新创建设备时旧的设备被销毁也会触发回调，这种情况不需要处理
+            if reason != wgpu::DeviceLostReason::Destroyed {
+                log::error!("GPU device lost({reason:?}): {message}");
+                device_lost_flag.store(true, Ordering::Release);
+            }
+        });
         let size = window.inner_size();
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
             format: TEXTURE_FORMAT,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
+            present_mode: PresentMode::default().to_wgpu(&caps.present_modes),
             desired_maximum_frame_latency: 2,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
@@ -148,38 +200,23 @@ impl Render {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         });
-        let grab_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Grab Texture"),
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        let grab_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(
-                        &grab_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(
-                        &device.create_sampler(&wgpu::SamplerDescriptor::default()),
-                    ),
-                },
-            ],
-            label: None,
-        });
+        let screen_size = wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        };
+        let (grab_texture, grab_texture_bind_group) = create_screen_texture(
+            &device,
+            &texture_bind_group_layout,
+            screen_size,
+            "Grab Texture",
+        );
+        let (transition_texture, transition_texture_bind_group) = create_screen_texture(
+            &device,
+            &texture_bind_group_layout,
+            screen_size,
+            "Transition Texture",
+        );
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
         let render_pipeline = create_pipeline(
@@ -344,9 +381,84 @@ impl Render {
             },
             None,
         );
+        let flash_shader = device.create_shader_module(wgpu::include_wgsl!("flash_shader.wgsl"));
+        let flash_pipeline = create_pipeline(
+            "Flash Pipeline",
+            &device,
+            &[&uniform_bind_group_layout],
+            &flash_shader,
+            &[],
+            wgpu::ColorTargetState {
+                format: TEXTURE_FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            None,
+        );
+        let clear_pipeline = create_pipeline(
+            "Clear Pipeline",
+            &device,
+            &[&uniform_bind_group_layout],
+            &flash_shader,
+            &[],
+            wgpu::ColorTargetState {
+                format: TEXTURE_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            Some(wgpu::DepthStencilState {
+                format: MASK_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Always,
+                        pass_op: wgpu::StencilOperation::Replace,
+                        ..Default::default()
+                    },
+                    back: wgpu::StencilFaceState::IGNORE,
+                    read_mask: !0,
+                    write_mask: !0,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
+        let transition_shader =
+            device.create_shader_module(wgpu::include_wgsl!("transition_shader.wgsl"));
+        let transition_pipeline = create_pipeline(
+            "Transition Pipeline",
+            &device,
+            &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            &transition_shader,
+            &[],
+            wgpu::ColorTargetState {
+                format: TEXTURE_FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            // 圆形开口转场时只在模板为 0 的开口外绘制旧画面
+            Some(wgpu::DepthStencilState {
+                format: MASK_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Equal,
+                        ..Default::default()
+                    },
+                    back: wgpu::StencilFaceState::IGNORE,
+                    read_mask: !0,
+                    write_mask: !0,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
 
         Ok(Render {
+            window,
+            instance,
             surface,
+            present_modes: caps.present_modes,
             device,
             queue,
             config,
@@ -355,14 +467,46 @@ impl Render {
             mask_texture,
             grab_texture,
             grab_texture_bind_group,
+            transition_texture,
+            transition_texture_bind_group,
             render_pipeline,
             blend_mode_pipeline,
             blur_pipeline,
             mask_start_pipeline,
             mask_end_pipeline,
             screen_repeat_pipeline,
+            flash_pipeline,
+            clear_pipeline,
+            transition_pipeline,
+            device_lost,
         })
     }
+
+    pub fn window(&self) -> &Arc<winit::window::Window> {
+        &self.window
+    }
+
+    pub fn if_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    /// 设备丢失后在原来的 surface 上重新创建设备和所有管线，保留原来的尺寸和提交模式
+    ///
+    /// 之前用旧设备创建的资源都不能再使用了，比如 `TextureStore` 需要调用 `reload` 重新上传纹理
+    pub async fn recreate_device(&mut self) -> Result<(), crate::Error> {
+        log::info!("Recreating GPU device...");
+        let mut render = Render::with_surface(
+            self.window.clone(),
+            self.instance.clone(),
+            self.surface.clone(),
+        )
+        .await?;
+        render.config.present_mode = self.config.present_mode;
+        render.config.desired_maximum_frame_latency = self.config.desired_maximum_frame_latency;
+        render.resize(self.config.width, self.config.height);
+        *self = render;
+        Ok(())
+    }
     pub fn resize(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
@@ -385,57 +529,58 @@ impl Render {
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
                 view_formats: &[],
             });
-            let grab_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("Grab Texture"),
+            let (grab_texture, grab_texture_bind_group) = create_screen_texture(
+                &self.device,
+                &self.texture_bind_group_layout,
                 size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: TEXTURE_FORMAT,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
-            let grab_texture_bind_group =
-                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &self.texture_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(
-                                &grab_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Sampler(
-                                &self
-                                    .device
-                                    .create_sampler(&wgpu::SamplerDescriptor::default()),
-                            ),
-                        },
-                    ],
-                    label: None,
-                });
+                "Grab Texture",
+            );
+            let (transition_texture, transition_texture_bind_group) = create_screen_texture(
+                &self.device,
+                &self.texture_bind_group_layout,
+                size,
+                "Transition Texture",
+            );
 
             self.mask_texture = mask_texture;
             self.grab_texture = grab_texture;
             self.grab_texture_bind_group = grab_texture_bind_group;
+            self.transition_texture = transition_texture;
+            self.transition_texture_bind_group = transition_texture_bind_group;
         }
     }
+    /// 修改提交模式和最大帧延迟，没有变化时不会重新配置 surface
+    pub fn set_present_mode(&mut self, present_mode: PresentMode, max_frame_latency: u32) {
+        let present_mode = present_mode.to_wgpu(&self.present_modes);
+        if self.config.present_mode == present_mode
+            && self.config.desired_maximum_frame_latency == max_frame_latency
+        {
+            return;
+        }
+        log::info!("Present mode: {present_mode:?}, max frame latency: {max_frame_latency}");
+        self.config.present_mode = present_mode;
+        self.config.desired_maximum_frame_latency = max_frame_latency;
+        if self.config.width > 0 && self.config.height > 0 {
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// 渲染一帧，转场时先渲染旧画面并保存下来，渲染完新画面后再合成
     pub fn render(
         &self,
         texture_store: &TextureStore,
-        camera: &Camera2D,
-        sprites: &Vec<&Sprite>,
-        screen_repeat: Option<&ScreenRepeat>,
+        groups: &[RenderGroup],
+        transition: Option<&TransitionFrame>,
         #[cfg(feature = "editor_mode")] egui_render: &mut crate::egui_render::EguiRender,
-    ) {
+    ) -> Result<(), RenderError> {
         #[cfg(feature = "profiling")]
         profiling::scope!("Create Frame View");
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next surface texture!");
+        let frame = acquire_frame(&mut surface::WgpuSurface {
+            surface: &self.surface,
+            device: &self.device,
+            config: &self.config,
+            device_lost: &self.device_lost,
+        })?;
         let frame_view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -444,12 +589,165 @@ impl Render {
             .create_view(&wgpu::TextureViewDescriptor::default());
 
         #[cfg(feature = "profiling")]
-        profiling::scope!("Create ViewUniform Bind Group");
-        let view_uniform = camera.get_view_uniform();
+        profiling::scope!("Create Command Encoder");
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder (Tracy)"),
+            });
+        let index_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(&[2_u32, 0, 1, 1, 3, 2]),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+        if let Some(transition) = transition {
+            self.render_groups(
+                &mut encoder,
+                &frame.texture,
+                &frame_view,
+                &mask_view,
+                &index_buffer,
+                texture_store,
+                &transition.from,
+            );
+            copy_screen(&mut encoder, &frame.texture, &self.transition_texture);
+        }
+        self.render_groups(
+            &mut encoder,
+            &frame.texture,
+            &frame_view,
+            &mask_view,
+            &index_buffer,
+            texture_store,
+            groups,
+        );
+        if let Some(transition) = transition {
+            self.composite_transition(
+                &mut encoder,
+                &frame.texture,
+                &frame_view,
+                &mask_view,
+                &index_buffer,
+                texture_store,
+                transition.effect.layers(transition.progress),
+            );
+        }
+
+        #[cfg(feature = "editor_mode")]
+        egui_render.render(&self.device, &self.queue, &mut encoder, &frame_view);
+
+        #[cfg(feature = "profiling")]
+        profiling::scope!("Submit Commands");
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        frame.present();
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_groups(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_texture: &wgpu::Texture,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        index_buffer: &wgpu::Buffer,
+        texture_store: &TextureStore,
+        groups: &[RenderGroup],
+    ) {
+        for group in groups {
+            for pass in &group.passes {
+                self.render_camera_pass(
+                    encoder,
+                    frame_texture,
+                    frame_view,
+                    mask_view,
+                    index_buffer,
+                    texture_store,
+                    pass,
+                    &group.sprites,
+                );
+            }
+        }
+    }
+
+    /// 把保存下来的旧画面按照转场参数叠加到已经渲染好的新画面上
+    #[allow(clippy::too_many_arguments)]
+    fn composite_transition(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_texture: &wgpu::Texture,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        index_buffer: &wgpu::Buffer,
+        texture_store: &TextureStore,
+        layers: TransitionLayers,
+    ) {
+        if layers.block_size > 1.0 {
+            // 新画面同样需要像素化，先复制出来再整个覆盖回去
+            copy_screen(encoder, frame_texture, &self.grab_texture);
+            self.draw_screen_texture(
+                encoder,
+                frame_view,
+                mask_view,
+                &self.grab_texture_bind_group,
+                1.0,
+                layers.block_size,
+                wgpu::LoadOp::Clear(0),
+            );
+        }
+        if layers.from_opacity > 0.0 {
+            let mut stencil_load = wgpu::LoadOp::Clear(0);
+            if let Some(iris) = layers.iris {
+                let half_diagonal =
+                    glam::Vec2::new(self.config.width as f32, self.config.height as f32).length()
+                        / 2.0;
+                self.draw_iris_mask(
+                    encoder,
+                    frame_view,
+                    mask_view,
+                    index_buffer,
+                    texture_store,
+                    iris * half_diagonal,
+                );
+                stencil_load = wgpu::LoadOp::Load;
+            }
+            self.draw_screen_texture(
+                encoder,
+                frame_view,
+                mask_view,
+                &self.transition_texture_bind_group,
+                layers.from_opacity,
+                layers.block_size,
+                stencil_load,
+            );
+        }
+        if let Some(color) = layers.overlay {
+            self.fill_viewport(encoder, frame_view, None, color);
+        }
+    }
+
+    /// 使用遮罩的管线把实心圆写入模板，之后绘制的旧画面只会出现在圆外
+    fn draw_iris_mask(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        index_buffer: &wgpu::Buffer,
+        texture_store: &TextureStore,
+        radius: f32,
+    ) {
+        let camera = Camera2D::new(glam::Vec2::new(
+            self.config.width as f32,
+            self.config.height as f32,
+        ));
         let view_uniform_buffer =
             self.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    contents: bytemuck::cast_slice(&[view_uniform]),
+                    contents: bytemuck::cast_slice(&[camera.get_view_uniform()]),
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                     label: None,
                 });
@@ -462,74 +760,220 @@ impl Render {
             label: None,
         });
 
-        #[cfg(feature = "profiling")]
-        profiling::scope!("Create Command Encoder");
-        let mut encoder = self
+        let (image_size, texture) = texture_store.get_or_missing(&CIRCLE_TEXTURE);
+        let circle = Sprite {
+            texture_id: CIRCLE_TEXTURE,
+            custom_size: Some(glam::Vec2::splat(radius * 2.0)),
+            ..Default::default()
+        };
+        let instance = SpriteInstance::from(
+            &circle.calculate_transform(*image_size),
+            &circle.calculate_uv_offset_scale(*image_size),
+            circle.color,
+            circle.color_blend_mode,
+            circle.blend_mode,
+        );
+        let vertex_buffer = self
             .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder (Tracy)"),
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Vertex Buffer"),
+                contents: bytemuck::cast_slice(&[instance]),
+                usage: wgpu::BufferUsages::VERTEX,
             });
-        let index_buffer = self
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Iris Mask Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: mask_view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.mask_start_pipeline);
+        render_pass.set_bind_group(0, &view_uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, texture, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..6_u32, 0, 0..1);
+    }
+
+    /// 把复制下来的画面绘制到模板为 0 的区域
+    #[allow(clippy::too_many_arguments)]
+    fn draw_screen_texture(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        texture: &wgpu::BindGroup,
+        opacity: f32,
+        block_size: f32,
+        stencil_load: wgpu::LoadOp<u32>,
+    ) {
+        let uniform = TransitionUniform {
+            params: glam::Vec4::new(
+                opacity,
+                block_size,
+                self.config.width as f32,
+                self.config.height as f32,
+            ),
+        };
+        let uniform_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&[2_u32, 0, 1, 1, 3, 2]),
-                usage: wgpu::BufferUsages::INDEX,
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                label: None,
             });
+        let uniform_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Transition Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: mask_view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: stencil_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_stencil_reference(0);
+        render_pass.set_pipeline(&self.transition_pipeline);
+        render_pass.set_bind_group(0, &uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, texture, &[]);
+        render_pass.draw(0..6_u32, 0..1);
+    }
+
+    /// 将 viewport 应用到 render pass，没有指定 viewport 时使用整个 surface
+    fn set_pass_viewport(&self, render_pass: &mut wgpu::RenderPass, viewport: Option<Rect>) {
+        let Some(viewport) = viewport else {
+            return;
+        };
+        // viewport 和 scissor 都不能超出 surface 范围
+        let surface_size = glam::Vec2::new(self.config.width as f32, self.config.height as f32);
+        let min = viewport.min.clamp(glam::Vec2::ZERO, surface_size);
+        let max = viewport.max.clamp(glam::Vec2::ZERO, surface_size);
+        let size = max - min;
+        render_pass.set_viewport(min.x, min.y, size.x, size.y, 0.0, 1.0);
+        render_pass.set_scissor_rect(min.x as u32, min.y as u32, size.x as u32, size.y as u32);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_camera_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_texture: &wgpu::Texture,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        index_buffer: &wgpu::Buffer,
+        texture_store: &TextureStore,
+        pass: &CameraPass,
+        sprites: &[&Sprite],
+    ) {
+        let camera = pass.camera;
+
+        #[cfg(feature = "profiling")]
+        profiling::scope!("Create ViewUniform Bind Group");
+        let view_uniform = camera.get_view_uniform();
+        let view_uniform_buffer =
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    contents: bytemuck::cast_slice(&[view_uniform]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    label: None,
+                });
+        let view_uniform_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_uniform_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
 
         #[cfg(feature = "profiling")]
         profiling::scope!("Convert Sprites");
         let mut render_items: Vec<RenderItem> = Vec::with_capacity(sprites.len());
         let mut sprite_instances: Vec<SpriteInstance> = Vec::with_capacity(sprites.len());
-        for (index, sprite) in sprites.into_iter().enumerate() {
-            let index = index as u32;
-            if let Some((image_size, _)) = texture_store.get(&sprite.texture_id) {
-                let sprite_instance = SpriteInstance::from(
-                    &sprite.calculate_transform(*image_size),
-                    &sprite.calculate_uv_offset_scale(*image_size),
-                    sprite.color,
-                    sprite.color_blend_mode,
-                    sprite.blend_mode,
-                );
-                sprite_instances.push(sprite_instance);
-                match sprite.blend_mode {
-                    BlendMode::Normal => {
-                        if let Some([mask_start, mask_end]) = sprite.mask {
-                            render_items.push(RenderItem::SpriteMaskStart {
-                                range: index..index + 1,
-                                texture_id: sprite.texture_id,
-                                sort_key: mask_start,
-                            });
-                            render_items.push(RenderItem::SpriteMaskEnd {
-                                range: index..index + 1,
-                                texture_id: sprite.texture_id,
-                                sort_key: mask_end,
-                            });
-                        } else {
-                            render_items.push(RenderItem::Sprite {
-                                range: index..index + 1,
-                                texture_id: sprite.texture_id,
-                                sort_key: sprite.transform.translation.z,
-                            });
-                        }
-                    }
-                    BlendMode::Blur => {
-                        render_items.push(RenderItem::BlurSprite {
+        for sprite in sprites
+            .iter()
+            .filter(|sprite| sprite.layers.intersects(&pass.layers))
+        {
+            let index = sprite_instances.len() as u32;
+            let (image_size, _) = texture_store.get_or_missing(&sprite.texture_id);
+            let sprite_instance = SpriteInstance::from(
+                &sprite.calculate_transform(*image_size),
+                &sprite.calculate_uv_offset_scale(*image_size),
+                sprite.color,
+                sprite.color_blend_mode,
+                sprite.blend_mode,
+            );
+            sprite_instances.push(sprite_instance);
+            match sprite.blend_mode {
+                BlendMode::Normal => {
+                    if let Some([mask_start, mask_end]) = sprite.mask {
+                        render_items.push(RenderItem::SpriteMaskStart {
                             range: index..index + 1,
                             texture_id: sprite.texture_id,
-                            sort_key: sprite.transform.translation.z,
+                            sort_key: mask_start,
                         });
-                    }
-                    _ => {
-                        render_items.push(RenderItem::BlendModeSprite {
+                        render_items.push(RenderItem::SpriteMaskEnd {
+                            range: index..index + 1,
+                            texture_id: sprite.texture_id,
+                            sort_key: mask_end,
+                        });
+                    } else {
+                        render_items.push(RenderItem::Sprite {
                             range: index..index + 1,
                             texture_id: sprite.texture_id,
                             sort_key: sprite.transform.translation.z,
                         });
                     }
                 }
-            } else {
-                log::warn!("Unable to find texture({})", sprite.texture_id);
+                BlendMode::Blur => {
+                    render_items.push(RenderItem::BlurSprite {
+                        range: index..index + 1,
+                        texture_id: sprite.texture_id,
+                        sort_key: sprite.transform.translation.z,
+                    });
+                }
+                _ => {
+                    render_items.push(RenderItem::BlendModeSprite {
+                        range: index..index + 1,
+                        texture_id: sprite.texture_id,
+                        sort_key: sprite.transform.translation.z,
+                    });
+                }
             }
         }
         let buffer_size = size_of::<SpriteInstance>() * sprite_instances.len();
@@ -547,46 +991,65 @@ impl Render {
         profiling::scope!("Sort Render Items");
         radsort::sort_by_key(&mut render_items, |item| (item.sort_key(), item.type_key()));
 
+        // 没有指定 viewport 时可以直接在 LoadOp 中清空整个 surface，
+        // 否则只能通过绘制纯色的全屏四边形来清空 viewport 区域
+        let mut color_load = wgpu::LoadOp::Load;
+        if let ClearPolicy::Color(color) = pass.clear {
+            if pass.viewport.is_none() {
+                let color = color.as_vec4().as_dvec4();
+                color_load = wgpu::LoadOp::Clear(wgpu::Color {
+                    r: color.x,
+                    g: color.y,
+                    b: color.z,
+                    a: color.w,
+                });
+            } else {
+                self.clear_viewport(encoder, frame_view, mask_view, pass.viewport, color);
+            }
+        }
+
         {
-            if let Some(screen_repeat) = screen_repeat {
-                if let Some((_, texture)) = texture_store.get(&screen_repeat.texture_id) {
-                    let screen_repeat_uniform = screen_repeat.get_uniform(camera);
-                    let screen_repeat_uniform_buffer =
-                        self.device
-                            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                                contents: bytemuck::cast_slice(&[screen_repeat_uniform]),
-                                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                                label: None,
-                            });
-                    let screen_repeat_uniform_bind_group =
-                        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                            layout: &self.uniform_bind_group_layout,
-                            entries: &[wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: screen_repeat_uniform_buffer.as_entire_binding(),
-                            }],
+            if let Some(screen_repeat) = pass.screen_repeat {
+                let (_, texture) = texture_store.get_or_missing(&screen_repeat.texture_id);
+                let screen_repeat_uniform = screen_repeat.get_uniform(camera);
+                let screen_repeat_uniform_buffer =
+                    self.device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            contents: bytemuck::cast_slice(&[screen_repeat_uniform]),
+                            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                             label: None,
                         });
-
-                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &frame_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
+                let screen_repeat_uniform_bind_group =
+                    self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &self.uniform_bind_group_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: screen_repeat_uniform_buffer.as_entire_binding(),
+                        }],
+                        label: None,
                     });
-                    render_pass.set_pipeline(&self.screen_repeat_pipeline);
-                    render_pass.set_bind_group(0, &screen_repeat_uniform_bind_group, &[]);
-                    render_pass.set_bind_group(1, texture, &[]);
-                    render_pass.draw(0..6_u32, 0..1);
-                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: frame_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: color_load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                self.set_pass_viewport(&mut render_pass, pass.viewport);
+                render_pass.set_pipeline(&self.screen_repeat_pipeline);
+                render_pass.set_bind_group(0, &screen_repeat_uniform_bind_group, &[]);
+                render_pass.set_bind_group(1, texture, &[]);
+                render_pass.draw(0..6_u32, 0..1);
+                // 已经清空过了，之后的 render pass 不需要再清空
+                color_load = wgpu::LoadOp::Load;
             }
         }
 
@@ -596,15 +1059,15 @@ impl Render {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &frame_view,
+                    view: frame_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
+                        load: color_load,
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &mask_view,
+                    view: mask_view,
                     depth_ops: None,
                     stencil_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(0),
@@ -614,86 +1077,223 @@ impl Render {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
+            self.set_pass_viewport(&mut render_pass, pass.viewport);
             render_pass.set_stencil_reference(0);
 
             #[cfg(feature = "profiling")]
             profiling::scope!("Draw Items");
             for render_item in render_items {
-                if let Some((_, texture)) = texture_store.get(&render_item.texture_id()) {
-                    match render_item {
-                        RenderItem::Sprite { .. } => {
-                            render_pass.set_pipeline(&self.render_pipeline);
-                        }
-                        RenderItem::BlendModeSprite { .. } | RenderItem::BlurSprite { .. } => {
-                            drop(render_pass);
-                            encoder.copy_texture_to_texture(
-                                frame.texture.as_image_copy(),
-                                self.grab_texture.as_image_copy(),
-                                wgpu::Extent3d {
-                                    width: self.grab_texture.width(),
-                                    height: self.grab_texture.height(),
-                                    depth_or_array_layers: 1,
+                let (_, texture) = texture_store.get_or_missing(&render_item.texture_id());
+                match render_item {
+                    RenderItem::Sprite { .. } => {
+                        render_pass.set_pipeline(&self.render_pipeline);
+                    }
+                    RenderItem::BlendModeSprite { .. } | RenderItem::BlurSprite { .. } => {
+                        drop(render_pass);
+                        encoder.copy_texture_to_texture(
+                            frame_texture.as_image_copy(),
+                            self.grab_texture.as_image_copy(),
+                            wgpu::Extent3d {
+                                width: self.grab_texture.width(),
+                                height: self.grab_texture.height(),
+                                depth_or_array_layers: 1,
+                            },
+                        );
+                        render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: frame_view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
                                 },
-                            );
-                            render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                                label: None,
-                                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                    view: &frame_view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
+                            })],
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachment {
+                                    view: mask_view,
+                                    depth_ops: None,
+                                    stencil_ops: Some(wgpu::Operations {
                                         load: wgpu::LoadOp::Load,
                                         store: wgpu::StoreOp::Store,
-                                    },
-                                })],
-                                depth_stencil_attachment: Some(
-                                    wgpu::RenderPassDepthStencilAttachment {
-                                        view: &mask_view,
-                                        depth_ops: None,
-                                        stencil_ops: Some(wgpu::Operations {
-                                            load: wgpu::LoadOp::Load,
-                                            store: wgpu::StoreOp::Store,
-                                        }),
-                                    },
-                                ),
-                                timestamp_writes: None,
-                                occlusion_query_set: None,
-                            });
-                            render_pass.set_stencil_reference(0);
-
-                            match render_item {
-                                RenderItem::BlendModeSprite { .. } => {
-                                    render_pass.set_pipeline(&self.blend_mode_pipeline);
-                                }
-                                RenderItem::BlurSprite { .. } => {
-                                    render_pass.set_pipeline(&self.blur_pipeline);
-                                }
-                                _ => {}
+                                    }),
+                                },
+                            ),
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+                        self.set_pass_viewport(&mut render_pass, pass.viewport);
+                        render_pass.set_stencil_reference(0);
+
+                        match render_item {
+                            RenderItem::BlendModeSprite { .. } => {
+                                render_pass.set_pipeline(&self.blend_mode_pipeline);
                             }
-                            render_pass.set_bind_group(2, &self.grab_texture_bind_group, &[]);
-                        }
-                        RenderItem::SpriteMaskStart { .. } => {
-                            render_pass.set_pipeline(&self.mask_start_pipeline);
-                        }
-                        RenderItem::SpriteMaskEnd { .. } => {
-                            render_pass.set_pipeline(&self.mask_end_pipeline);
+                            RenderItem::BlurSprite { .. } => {
+                                render_pass.set_pipeline(&self.blur_pipeline);
+                            }
+                            _ => {}
                         }
+                        render_pass.set_bind_group(2, &self.grab_texture_bind_group, &[]);
+                    }
+                    RenderItem::SpriteMaskStart { .. } => {
+                        render_pass.set_pipeline(&self.mask_start_pipeline);
+                    }
+                    RenderItem::SpriteMaskEnd { .. } => {
+                        render_pass.set_pipeline(&self.mask_end_pipeline);
                     }
-                    render_pass.set_bind_group(0, &view_uniform_bind_group, &[]);
-                    render_pass.set_bind_group(1, texture, &[]);
-                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    render_pass.draw_indexed(0..6_u32, 0, render_item.range().clone());
                 }
+                render_pass.set_bind_group(0, &view_uniform_bind_group, &[]);
+                render_pass.set_bind_group(1, texture, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..6_u32, 0, render_item.range().clone());
             }
         }
 
-        #[cfg(feature = "editor_mode")]
-        egui_render.render(&self.device, &self.queue, &mut encoder, &frame_view);
+        if let Some(flash_color) = camera.effects.flash_color() {
+            self.fill_viewport(encoder, frame_view, pass.viewport, flash_color);
+        }
+    }
 
-        #[cfg(feature = "profiling")]
-        profiling::scope!("Submit Commands");
-        self.queue.submit(std::iter::once(encoder.finish()));
+    /// 使用纯色填充 viewport 区域，用于清空局部区域以及镜头的闪烁效果
+    fn fill_viewport(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        viewport: Option<Rect>,
+        color: glam::Vec4,
+    ) {
+        let flash_uniform_bind_group = self.color_bind_group(color);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Flash Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.set_pass_viewport(&mut render_pass, viewport);
+        render_pass.set_pipeline(&self.flash_pipeline);
+        render_pass.set_bind_group(0, &flash_uniform_bind_group, &[]);
+        render_pass.draw(0..6_u32, 0..1);
+    }
 
-        frame.present();
+    /// 用指定颜色替换 viewport 区域的内容，不和之前的画面混合，同时把这个区域的模板清零
+    fn clear_viewport(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        mask_view: &wgpu::TextureView,
+        viewport: Option<Rect>,
+        color: Color,
+    ) {
+        let clear_uniform_bind_group = self.color_bind_group(color.as_vec4());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Viewport Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: mask_view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.set_pass_viewport(&mut render_pass, viewport);
+        render_pass.set_pipeline(&self.clear_pipeline);
+        render_pass.set_stencil_reference(0);
+        render_pass.set_bind_group(0, &clear_uniform_bind_group, &[]);
+        render_pass.draw(0..6_u32, 0..1);
     }
+
+    /// flash_shader 使用的纯色 uniform
+    fn color_bind_group(&self, color: glam::Vec4) -> wgpu::BindGroup {
+        let uniform_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                contents: bytemuck::cast_slice(&[FlashUniform { color }]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                label: None,
+            });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: None,
+        })
+    }
+}
+
+/// 复制整个画面，目标纹理和 surface 的尺寸相同
+fn copy_screen(
+    encoder: &mut wgpu::CommandEncoder,
+    frame_texture: &wgpu::Texture,
+    target: &wgpu::Texture,
+) {
+    encoder.copy_texture_to_texture(
+        frame_texture.as_image_copy(),
+        target.as_image_copy(),
+        wgpu::Extent3d {
+            width: target.width(),
+            height: target.height(),
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// 和 surface 尺寸相同的纹理，用于复制当前画面后在 shader 中采样
+fn create_screen_texture(
+    device: &wgpu::Device,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    size: wgpu::Extent3d,
+    label: &str,
+) -> (wgpu::Texture, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(
+                    &device.create_sampler(&wgpu::SamplerDescriptor::default()),
+                ),
+            },
+        ],
+        label: None,
+    });
+    (texture, bind_group)
 }
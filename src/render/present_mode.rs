@@ -0,0 +1,69 @@
+/// 画面提交到屏幕的方式
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PresentMode {
+    /// 垂直同步，帧率不超过屏幕刷新率，所有平台都支持
+    #[default]
+    Vsync,
+    /// 自适应垂直同步，帧率跟得上时和垂直同步相同，晚了的帧立即展示，可能出现画面撕裂，
+    /// 不支持时使用垂直同步
+    AdaptiveVsync,
+    /// 不限制渲染帧率，但只在屏幕刷新时展示最新的一帧，没有画面撕裂，不支持时使用垂直同步
+    Mailbox,
+    /// 渲染完成后立即展示，延迟最低但是可能出现画面撕裂，不支持时依次尝试 Mailbox 和垂直同步
+    Immediate,
+}
+
+impl PresentMode {
+    /// 根据 surface 支持的模式选出实际使用的模式
+    pub fn to_wgpu(self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let fallbacks: &[wgpu::PresentMode] = match self {
+            PresentMode::Vsync => &[],
+            PresentMode::AdaptiveVsync => &[wgpu::PresentMode::FifoRelaxed],
+            PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox],
+            PresentMode::Immediate => &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+        };
+        fallbacks
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            // 所有平台都支持 Fifo
+            .unwrap_or(wgpu::PresentMode::Fifo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_supported_mode() {
+        let web = [wgpu::PresentMode::Fifo];
+        assert_eq!(PresentMode::Mailbox.to_wgpu(&web), wgpu::PresentMode::Fifo);
+        assert_eq!(
+            PresentMode::Immediate.to_wgpu(&web),
+            wgpu::PresentMode::Fifo
+        );
+
+        let desktop = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::FifoRelaxed,
+            wgpu::PresentMode::Mailbox,
+        ];
+        assert_eq!(
+            PresentMode::Vsync.to_wgpu(&desktop),
+            wgpu::PresentMode::Fifo
+        );
+        assert_eq!(
+            PresentMode::AdaptiveVsync.to_wgpu(&desktop),
+            wgpu::PresentMode::FifoRelaxed
+        );
+        assert_eq!(
+            PresentMode::AdaptiveVsync.to_wgpu(&web),
+            wgpu::PresentMode::Fifo
+        );
+        assert_eq!(
+            PresentMode::Immediate.to_wgpu(&desktop),
+            wgpu::PresentMode::Mailbox
+        );
+    }
+}
@@ -0,0 +1,33 @@
+/// 渲染层掩码，sprite 只会在与之有交集的镜头中渲染
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RenderLayers(u32);
+
+impl RenderLayers {
+    pub const NONE: RenderLayers = RenderLayers(0);
+    pub const ALL: RenderLayers = RenderLayers(u32::MAX);
+    /// 场景内的 sprite
+    pub const WORLD: RenderLayers = RenderLayers::layer(0);
+    /// 屏幕空间的 UI
+    pub const UI: RenderLayers = RenderLayers::layer(1);
+
+    #[inline]
+    pub const fn layer(index: u8) -> RenderLayers {
+        RenderLayers(1 << index)
+    }
+
+    #[inline]
+    pub const fn with(self, other: RenderLayers) -> RenderLayers {
+        RenderLayers(self.0 | other.0)
+    }
+
+    #[inline]
+    pub const fn intersects(&self, other: &RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers::WORLD
+    }
+}
@@ -1,5 +1,5 @@
 use crate::assets::AssetsId;
-use crate::{BlendMode, Color, Rect, Transform};
+use crate::{BlendMode, Color, Rect, RenderLayers, Transform};
 use glam::{Affine3A, Quat, Vec2, Vec4};
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -20,6 +20,8 @@ pub struct Sprite {
     pub color: Color,
     pub color_blend_mode: BlendMode,
     pub blend_mode: BlendMode,
+    /// 只会在 layers 有交集的镜头中渲染
+    pub layers: RenderLayers,
 }
 
 impl Sprite {
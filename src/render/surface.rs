@@ -0,0 +1,178 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RenderError {
+    /// 获取画面超时，跳过这一帧
+    Timeout,
+    /// 重新配置 surface 后依旧无法获取画面，跳过这一帧，下一帧再试
+    SurfaceLost,
+    /// GPU 设备丢失，需要调用 `Render::recreate_device` 重新创建所有 GPU 资源
+    DeviceLost,
+    /// 显存不足，无法继续渲染
+    OutOfMemory,
+    /// 其他获取画面的错误，跳过这一帧
+    Other,
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Timeout => write!(f, "Timed out acquiring the next surface texture"),
+            RenderError::SurfaceLost => write!(f, "Surface is lost after reconfiguring"),
+            RenderError::DeviceLost => write!(f, "GPU device is lost"),
+            RenderError::OutOfMemory => write!(f, "Out of memory acquiring the surface texture"),
+            RenderError::Other => write!(f, "Failed to acquire the next surface texture"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Render 获取画面时用到的 surface 操作，测试时可以替换成模拟的实现来注入各种错误
+pub trait RenderSurface {
+    type Frame;
+
+    fn get_current_texture(&mut self) -> Result<Self::Frame, wgpu::SurfaceError>;
+
+    /// 使用当前的配置重新配置 surface
+    fn reconfigure(&mut self);
+
+    fn if_device_lost(&self) -> bool;
+}
+
+/// 获取这一帧的画面
+///
+/// surface 丢失或者过期时重新配置后再试一次，超时等其他错误直接跳过这一帧，
+/// 设备丢失时不会再获取画面，需要重新创建设备。
+pub fn acquire_frame<S: RenderSurface>(surface: &mut S) -> Result<S::Frame, RenderError> {
+    if surface.if_device_lost() {
+        return Err(RenderError::DeviceLost);
+    }
+    match surface.get_current_texture() {
+        Ok(frame) => Ok(frame),
+        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+            log::warn!("Surface is lost or outdated, reconfiguring");
+            surface.reconfigure();
+            surface.get_current_texture().map_err(|err| match err {
+                // 重新配置后 surface 马上又失效了，大多是因为窗口还在缩放，下一帧再试
+                wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => RenderError::SurfaceLost,
+                err => err.into(),
+            })
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+impl From<wgpu::SurfaceError> for RenderError {
+    fn from(err: wgpu::SurfaceError) -> Self {
+        match err {
+            wgpu::SurfaceError::Timeout => RenderError::Timeout,
+            wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost => RenderError::SurfaceLost,
+            wgpu::SurfaceError::OutOfMemory => RenderError::OutOfMemory,
+            wgpu::SurfaceError::Other => RenderError::Other,
+        }
+    }
+}
+
+/// 真实的 wgpu surface
+pub(crate) struct WgpuSurface<'a> {
+    pub surface: &'a wgpu::Surface<'static>,
+    pub device: &'a wgpu::Device,
+    pub config: &'a wgpu::SurfaceConfiguration,
+    pub device_lost: &'a AtomicBool,
+}
+
+impl RenderSurface for WgpuSurface<'_> {
+    type Frame = wgpu::SurfaceTexture;
+
+    fn get_current_texture(&mut self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+        self.surface.get_current_texture()
+    }
+
+    fn reconfigure(&mut self) {
+        if self.config.width > 0 && self.config.height > 0 {
+            self.surface.configure(self.device, self.config);
+        }
+    }
+
+    fn if_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// 按顺序返回预设的结果，并记录重新配置的次数
+    #[derive(Default)]
+    struct MockSurface {
+        results: VecDeque<Result<u32, wgpu::SurfaceError>>,
+        reconfigured: u32,
+        device_lost: bool,
+    }
+
+    impl MockSurface {
+        fn new(results: impl IntoIterator<Item = Result<u32, wgpu::SurfaceError>>) -> Self {
+            MockSurface {
+                results: results.into_iter().collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl RenderSurface for MockSurface {
+        type Frame = u32;
+
+        fn get_current_texture(&mut self) -> Result<u32, wgpu::SurfaceError> {
+            self.results.pop_front().expect("unexpected acquire")
+        }
+
+        fn reconfigure(&mut self) {
+            self.reconfigured += 1;
+        }
+
+        fn if_device_lost(&self) -> bool {
+            self.device_lost
+        }
+    }
+
+    #[test]
+    fn lost_and_outdated_reconfigure_and_retry() {
+        for err in [wgpu::SurfaceError::Lost, wgpu::SurfaceError::Outdated] {
+            let mut surface = MockSurface::new([Err(err), Ok(7)]);
+            assert_eq!(acquire_frame(&mut surface), Ok(7));
+            assert_eq!(surface.reconfigured, 1);
+        }
+
+        // 重新配置后依旧失败时只重试一次
+        let mut surface = MockSurface::new([
+            Err(wgpu::SurfaceError::Outdated),
+            Err(wgpu::SurfaceError::Lost),
+        ]);
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::SurfaceLost));
+        assert_eq!(surface.reconfigured, 1);
+        assert!(surface.results.is_empty());
+    }
+
+    #[test]
+    fn timeout_skips_frame_without_reconfiguring() {
+        let mut surface = MockSurface::new([Err(wgpu::SurfaceError::Timeout), Ok(1)]);
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::Timeout));
+        assert_eq!(surface.reconfigured, 0);
+        // 下一帧正常获取
+        assert_eq!(acquire_frame(&mut surface), Ok(1));
+
+        let mut surface = MockSurface::new([Err(wgpu::SurfaceError::OutOfMemory)]);
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::OutOfMemory));
+    }
+
+    #[test]
+    fn device_lost_stops_acquiring() {
+        let mut surface = MockSurface::new([Ok(1)]);
+        surface.device_lost = true;
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::DeviceLost));
+        assert_eq!(surface.results.len(), 1);
+    }
+}
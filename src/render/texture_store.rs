@@ -1,102 +1,229 @@
-use crate::assets::AssetsId;
-use crate::{Render, TEXTURE_FORMAT};
+use crate::assets::{AssetEntry, AssetsId, SamplerSettings};
+use crate::{rasterize_text, Error, Render, TEXTURE_FORMAT};
 use glam::Vec2;
 use std::collections::HashMap;
 
-#[derive(Debug, Default)]
+/// 缺失纹理棋盘格每一格的像素大小
+const MISSING_TEXTURE_CELL: u32 = 8;
+/// 1x1 的白色纹理，配合 Sprite 的 color 和 custom_size 绘制纯色的矩形
+pub const WHITE_TEXTURE: AssetsId = AssetsId::new("ffffffff-ffff-4fff-bfff-ffffffffffff");
+/// 白色的实心圆，用于圆形开口转场的模板
+pub const CIRCLE_TEXTURE: AssetsId = AssetsId::new("ffffffff-ffff-4fff-bfff-fffffffffffe");
+/// 实心圆纹理的边长
+const CIRCLE_TEXTURE_SIZE: u32 = 256;
+
+#[derive(Debug)]
 pub struct TextureStore {
     textures: HashMap<AssetsId, (Vec2, wgpu::BindGroup)>,
+    /// 保留上传到 GPU 的图片和采样设置，设备丢失后用于重新创建纹理
+    images: HashMap<AssetsId, (image::RgbaImage, SamplerSettings)>,
+    /// 找不到纹理或者图片解码失败时显示的洋红色棋盘格
+    missing: (Vec2, wgpu::BindGroup),
+    /// 已经生成的文字纹理，键为文字和点阵像素的边长
+    texts: HashMap<(String, u32), AssetsId>,
     auto_increment_key: u32,
 }
 
 impl TextureStore {
+    pub fn new(render: &Render) -> TextureStore {
+        let mut texture_store = TextureStore {
+            textures: HashMap::new(),
+            images: HashMap::new(),
+            missing: create_texture(render, &missing_texture_image(), SamplerSettings::DEFAULT),
+            texts: HashMap::new(),
+            auto_increment_key: 0,
+        };
+        texture_store.load_texture_with_key(
+            render,
+            image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])),
+            SamplerSettings::DEFAULT,
+            Some(WHITE_TEXTURE),
+        );
+        texture_store.load_texture_with_key(
+            render,
+            circle_image(CIRCLE_TEXTURE_SIZE),
+            SamplerSettings::DEFAULT,
+            Some(CIRCLE_TEXTURE),
+        );
+        texture_store
+    }
     pub fn get(&self, id: &AssetsId) -> Option<&(Vec2, wgpu::BindGroup)> {
         self.textures.get(id)
     }
-    pub fn load_texture_raw(
+    /// 找不到纹理时返回洋红色的棋盘格，让缺失的资源在画面上一眼就能看出来
+    pub fn get_or_missing(&self, id: &AssetsId) -> &(Vec2, wgpu::BindGroup) {
+        self.textures.get(id).unwrap_or(&self.missing)
+    }
+    /// 解码失败时依旧会用洋红色的棋盘格占用这个 id，之后可以正常使用，只是显示为棋盘格
+    pub fn load_texture_bytes(
         &mut self,
         render: &Render,
-        (assets_id, assets_bytes): (AssetsId, &'static [u8]),
-    ) -> AssetsId {
-        if self.textures.contains_key(&assets_id) {
-            return assets_id;
+        entry: &AssetEntry,
+        assets_bytes: &[u8],
+    ) -> Result<AssetsId, Error> {
+        if self.textures.contains_key(&entry.id) {
+            return Ok(entry.id);
+        }
+        self.load_decoded(render, entry, decode_image(assets_bytes))
+    }
+    /// 上传已经解码的图片，图片可以在其他线程中通过 decode_image 解码
+    pub fn load_decoded(
+        &mut self,
+        render: &Render,
+        entry: &AssetEntry,
+        decoded: Result<image::RgbaImage, image::ImageError>,
+    ) -> Result<AssetsId, Error> {
+        match decoded {
+            Ok(image) => {
+                Ok(self.load_texture_with_key(render, image, entry.sampler, Some(entry.id)))
+            }
+            Err(source) => {
+                self.load_texture_with_key(
+                    render,
+                    missing_texture_image(),
+                    SamplerSettings::DEFAULT,
+                    Some(entry.id),
+                );
+                Err(Error::ImageDecode {
+                    id: entry.id,
+                    source,
+                })
+            }
         }
-        let image = image::load_from_memory(assets_bytes).unwrap();
-        let image = image.to_rgba8();
-        self.load_texture_with_key(render, &image, Some(assets_id))
     }
     pub fn load_texture(&mut self, render: &Render, image: &image::RgbaImage) -> AssetsId {
-        self.load_texture_with_key(render, image, None)
+        self.load_texture_with_key(render, image.clone(), SamplerSettings::DEFAULT, None)
+    }
+    /// 用新的图片替换已有的纹理，id 还没有使用时和 load_texture 相同，比如每次内容变化时重新生成的文字
+    pub fn replace_texture(&mut self, render: &Render, id: AssetsId, image: image::RgbaImage) {
+        self.load_texture_with_key(render, image, SamplerSettings::DEFAULT, Some(id));
+    }
+    /// 用内置的点阵字体生成透明背景的文字纹理，多行文字用换行分隔
+    ///
+    /// 相同的文字只生成一次，界面每次打开时不会重复上传
+    pub fn text_texture(&mut self, render: &Render, text: &str, scale: u32) -> AssetsId {
+        if let Some(id) = self.texts.get(&(text.to_owned(), scale)) {
+            return *id;
+        }
+        let image = rasterize_text(text.lines(), scale, [0; 4]);
+        let id = self.load_texture_with_key(render, image, SamplerSettings::DEFAULT, None);
+        self.texts.insert((text.to_owned(), scale), id);
+        id
+    }
+    /// 使用保留的图片重新创建所有纹理，用于设备丢失后重新创建了 Render
+    pub fn reload(&mut self, render: &Render) {
+        log::info!("Reloading {} textures", self.images.len());
+        self.missing = create_texture(render, &missing_texture_image(), SamplerSettings::DEFAULT);
+        for (key, (image, sampler)) in self.images.iter() {
+            self.textures
+                .insert(*key, create_texture(render, image, *sampler));
+        }
     }
     fn load_texture_with_key(
         &mut self,
         render: &Render,
-        image: &image::RgbaImage,
+        image: image::RgbaImage,
+        sampler: SamplerSettings,
         key: Option<AssetsId>,
     ) -> AssetsId {
-        let image_size = wgpu::Extent3d {
-            width: image.width(),
-            height: image.height(),
-            depth_or_array_layers: 1,
-        };
-        let texture = render.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Example Texture"),
-            size: image_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        render.queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &image,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * image.width()),
-                rows_per_image: Some(image.height()),
-            },
-            image_size,
-        );
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = render
-            .device
-            .create_sampler(&wgpu::SamplerDescriptor::default());
-        let texture_bind_group = render.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &render.texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: None,
-        });
-
         let key = if let Some(key) = key {
             key
         } else {
             self.auto_increment_key += 1;
             AssetsId::from_u32(self.auto_increment_key)
         };
-        self.textures.insert(
-            key,
-            (
-                Vec2::new(image.width() as f32, image.height() as f32),
-                texture_bind_group,
-            ),
-        );
+        self.textures
+            .insert(key, create_texture(render, &image, sampler));
+        self.images.insert(key, (image, sampler));
         key
     }
 }
+
+/// 解码纹理使用的图片，不需要 GPU，可以在后台线程中执行
+pub fn decode_image(bytes: &[u8]) -> Result<image::RgbaImage, image::ImageError> {
+    image::load_from_memory(bytes).map(|image| image.to_rgba8())
+}
+
+/// 洋红色和黑色相间的棋盘格
+pub fn missing_texture_image() -> image::RgbaImage {
+    let size = MISSING_TEXTURE_CELL * 4;
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        if (x / MISSING_TEXTURE_CELL + y / MISSING_TEXTURE_CELL).is_multiple_of(2) {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+/// 圆外完全透明的白色实心圆
+fn circle_image(size: u32) -> image::RgbaImage {
+    let radius = size as f32 / 2.0;
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let offset = glam::Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - radius;
+        if offset.length() <= radius {
+            image::Rgba([255, 255, 255, 255])
+        } else {
+            image::Rgba([255, 255, 255, 0])
+        }
+    })
+}
+
+fn create_texture(
+    render: &Render,
+    image: &image::RgbaImage,
+    sampler: SamplerSettings,
+) -> (Vec2, wgpu::BindGroup) {
+    let image_size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = render.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Example Texture"),
+        size: image_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    render.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            aspect: wgpu::TextureAspect::All,
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        &image,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        image_size,
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = render.device.create_sampler(&sampler.to_wgpu());
+    let texture_bind_group = render.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &render.texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+        label: None,
+    });
+
+    (
+        Vec2::new(image.width() as f32, image.height() as f32),
+        texture_bind_group,
+    )
+}
@@ -0,0 +1,119 @@
+use crate::{Color, RenderGroup};
+use glam::Vec4;
+
+/// 像素化转场在中间时刻的像素块边长
+const PIXELATE_MAX_BLOCK: f32 = 32.0;
+
+/// 两个画面之间的转场效果
+#[derive(Copy, Clone, Debug)]
+pub enum TransitionEffect {
+    /// 旧画面先淡出为纯色，新画面再从纯色中淡入
+    Fade(Color),
+    /// 旧画面逐渐变透明，露出下面的新画面
+    Crossfade,
+    /// 从画面中心扩大的圆形开口，开口内显示新画面
+    Iris,
+    /// 像素块先变大再变小，中间完成两个画面的交叉淡化
+    Pixelate,
+}
+
+/// 某一时刻合成转场需要的参数
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TransitionLayers {
+    /// 覆盖在新画面上的旧画面的透明度
+    pub from_opacity: f32,
+    /// 两个画面的像素块边长，1 表示不做像素化
+    pub block_size: f32,
+    /// 圆形开口的半径占画面对角线一半的比例，开口内不绘制旧画面
+    pub iris: Option<f32>,
+    /// 最后叠加在整个画面上的纯色
+    pub overlay: Option<Vec4>,
+}
+
+impl TransitionEffect {
+    /// progress 为 0 时完全是旧画面，为 1 时完全是新画面
+    pub fn layers(&self, progress: f32) -> TransitionLayers {
+        let progress = progress.clamp(0.0, 1.0);
+        let mut layers = TransitionLayers {
+            from_opacity: 1.0 - progress,
+            block_size: 1.0,
+            iris: None,
+            overlay: None,
+        };
+        match self {
+            TransitionEffect::Fade(color) => {
+                let mut color = color.as_vec4();
+                // 前半段盖住旧画面，后半段从新画面上褪去
+                if progress < 0.5 {
+                    layers.from_opacity = 1.0;
+                    color.w *= progress * 2.0;
+                } else {
+                    layers.from_opacity = 0.0;
+                    color.w *= (1.0 - progress) * 2.0;
+                }
+                layers.overlay = Some(color);
+            }
+            TransitionEffect::Crossfade => {}
+            TransitionEffect::Iris => {
+                layers.from_opacity = 1.0;
+                layers.iris = Some(progress);
+            }
+            TransitionEffect::Pixelate => {
+                let peak = 1.0 - (progress * 2.0 - 1.0).abs();
+                layers.block_size = 1.0 + (PIXELATE_MAX_BLOCK - 1.0) * peak;
+            }
+        }
+        layers
+    }
+}
+
+/// 转场过程中需要额外渲染的旧画面
+#[derive(Clone, Debug)]
+pub struct TransitionFrame<'a> {
+    pub from: Vec<RenderGroup<'a>>,
+    pub effect: TransitionEffect,
+    /// 0 到 1 之间，已经应用过缓动曲线
+    pub progress: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Default)]
+pub struct TransitionUniform {
+    /// x 为透明度，y 为像素块边长，zw 为画面尺寸
+    pub params: Vec4,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_show_from_at_start_and_to_at_end() {
+        let black = Color::new([0, 0, 0, 255]);
+        for effect in [
+            TransitionEffect::Fade(black),
+            TransitionEffect::Crossfade,
+            TransitionEffect::Pixelate,
+        ] {
+            let start = effect.layers(0.0);
+            assert_eq!(start.from_opacity, 1.0, "{effect:?}");
+            assert_eq!(start.block_size, 1.0, "{effect:?}");
+            let end = effect.layers(1.0);
+            assert_eq!(end.from_opacity, 0.0, "{effect:?}");
+            assert_eq!(end.block_size, 1.0, "{effect:?}");
+            assert!(end.overlay.is_none_or(|color| color.w == 0.0), "{effect:?}");
+        }
+        assert_eq!(TransitionEffect::Iris.layers(1.0).iris, Some(1.0));
+    }
+
+    #[test]
+    fn fade_is_opaque_in_the_middle() {
+        let layers = TransitionEffect::Fade(Color::new([0, 0, 0, 255])).layers(0.5);
+        assert_eq!(layers.from_opacity, 0.0);
+        assert_eq!(layers.overlay.unwrap().w, 1.0);
+        assert_eq!(
+            TransitionEffect::Pixelate.layers(0.5).block_size,
+            PIXELATE_MAX_BLOCK
+        );
+    }
+}
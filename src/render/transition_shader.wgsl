@@ -0,0 +1,47 @@
+struct Transition {
+    // x 为透明度，y 为像素块边长，zw 为画面尺寸
+    params: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> transition: Transition;
+
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    // 全屏四边形顶点数据
+    let pos = array(
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(-1.0, 1.0),
+        vec2(-1.0, 1.0),
+        vec2(1.0, -1.0),
+        vec2(1.0, 1.0)
+    );
+
+    var out: VertexOutput;
+    out.clip_position = vec4(pos[in.index], 0.0, 1.0);
+
+    return out;
+}
+
+@group(1) @binding(0) var screen_texture: texture_2d<f32>;
+@group(1) @binding(1) var screen_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 同一个像素块内的片元都采样像素块中心的颜色，块边长为 1 时就是片元自身
+    let block = transition.params.y;
+    let position = (floor(in.clip_position.xy / block) + vec2(0.5)) * block;
+    let texture = textureSample(screen_texture, screen_sampler, position / transition.params.zw);
+
+    return vec4(texture.rgb, texture.a * transition.params.x);
+}
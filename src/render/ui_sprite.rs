@@ -3,31 +3,22 @@ use glam::Vec2;
 use std::time::Duration;
 
 /// 直接在 Sprite 的基础上添加 UI Sprite 的支持
+///
+/// 位置和尺寸由 UiTree 的布局结果决定
 #[derive(Copy, Clone, Debug, Default)]
 pub struct UiSprite {
     pub sprite: Sprite,
-    pub size: Val<Vec2>,
-    pub left: Option<Val<f32>>,
-    pub top: Option<Val<f32>>,
     pub loop_anima: Option<(&'static [Rect], usize, Duration)>,
 }
 
 impl UiSprite {
-    pub fn update(&mut self, camera: &Camera2D, delta: Duration) {
-        let size = self.size.cal(camera);
+    /// layout 是 UiTree 计算出的屏幕坐标，camera 是渲染 UI 使用的镜头
+    pub fn update(&mut self, layout: Rect, camera: &Camera2D, delta: Duration) {
+        self.sprite.custom_size = Some(layout.size() * camera.get_scale());
 
-        self.sprite.custom_size = Some(size * camera.get_scale());
-
-        if let Some(left) = self.left {
-            let left = left.cal(camera) + size.x / 2.0;
-            self.sprite.transform.translation.x = camera
-                .viewport_to_world(Vec2::new(camera.viewport_size.x - left, 0.0))
-                .x;
-        }
-        if let Some(top) = self.top {
-            let top = top.cal(camera) + size.y / 2.0;
-            self.sprite.transform.translation.y = camera.viewport_to_world(Vec2::new(0.0, top)).y;
-        }
+        let center = camera.viewport_to_world(layout.center());
+        self.sprite.transform.translation.x = center.x;
+        self.sprite.transform.translation.y = center.y;
         if let Some((frames, index, elapsed)) = &mut self.loop_anima {
             *elapsed += delta;
             if *elapsed >= Duration::from_millis(100) {
@@ -60,46 +51,3 @@ impl UiSprite {
         self.loop_anima = None;
     }
 }
-
-#[derive(Copy, Clone, Debug, Default)]
-pub struct Val<T> {
-    base: T,
-}
-
-impl From<f32> for Val<f32> {
-    fn from(value: f32) -> Self {
-        Self { base: value }
-    }
-}
-
-impl Val<f32> {
-    pub fn cal(&self, camera: &Camera2D) -> f32 {
-        if camera.viewport_size.x >= 3840.0 && camera.viewport_size.y >= 2160.0 {
-            self.base * 4.0
-        } else if camera.viewport_size.x >= 2880.0 && camera.viewport_size.y >= 1620.0 {
-            self.base * 2.0
-        } else if camera.viewport_size.x >= 1920.0 && camera.viewport_size.y >= 1080.0 {
-            self.base * 2.0
-        } else {
-            self.base
-        }
-    }
-}
-
-impl From<Vec2> for Val<Vec2> {
-    fn from(value: Vec2) -> Self {
-        Self { base: value }
-    }
-}
-
-impl Val<Vec2> {
-    pub fn cal(&self, camera: &Camera2D) -> Vec2 {
-        if camera.viewport_size.x >= 3840.0 && camera.viewport_size.y >= 2160.0 {
-            self.base * 4.0
-        } else if camera.viewport_size.x >= 1920.0 && camera.viewport_size.y >= 1080.0 {
-            self.base * 2.0
-        } else {
-            self.base
-        }
-    }
-}
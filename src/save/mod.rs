@@ -0,0 +1,307 @@
+mod settings;
+mod storage;
+
+pub use settings::*;
+pub use storage::*;
+
+use crate::Error;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 当前的存档版本，修改存档格式时加一，并在 MIGRATIONS 中加上旧版本的迁移
+pub const SAVE_VERSION: u32 = 1;
+/// 自动存档使用的存档位
+pub const AUTOSAVE_SLOT: &str = "autosave";
+/// 快速存档使用的存档位
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+/// 自动存档的间隔
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// 存档位名称的最大长度
+pub const MAX_SLOT_NAME_LEN: usize = 64;
+
+/// 存档在存储中的目录
+pub const SAVES_DIR: &str = "saves";
+
+const SAVE_EXTENSION: &str = "sav";
+
+/// 把旧版本的存档头迁移到下一个版本，下标为 i 的函数把版本 i 迁移到版本 i + 1
+///
+/// 版本 0 是以前直接写入的场景文件，没有存档头
+const MIGRATIONS: [fn(&mut serde_json::Value, &mut Vec<u8>); SAVE_VERSION as usize] =
+    [migrate_legacy_scene];
+
+fn migrate_legacy_scene(header: &mut serde_json::Value, _scene: &mut Vec<u8>) {
+    // 旧的场景文件没有保存镜头，加载时镜头移动到场景的中心
+    *header = serde_json::json!({ "save_version": 1, "saved_at": 0 });
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Json(serde_json::Error),
+    /// 存档的版本比当前的版本更新，无法加载
+    Version(u32),
+    /// 存档位名称在清理后为空
+    InvalidSlot(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Json(err) => write!(f, "Invalid save header: {err}"),
+            SaveError::Version(version) => write!(
+                f,
+                "Save version {version} is newer than the supported version {SAVE_VERSION}"
+            ),
+            SaveError::InvalidSlot(slot) => write!(f, "Invalid save slot name {slot:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Json(err) => Some(err),
+            SaveError::Version(_) | SaveError::InvalidSlot(_) => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct SaveCamera {
+    pub translation: Vec2,
+    pub pixel_zoom: u8,
+}
+
+/// 存档头，单独占存档文件的第一行，之后是场景序列化后的内容
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveHeader {
+    pub save_version: u32,
+    /// 保存时的 Unix 时间戳，单位为秒
+    pub saved_at: u64,
+    /// 为 None 时加载后镜头移动到场景的中心
+    #[serde(default)]
+    pub camera: Option<SaveCamera>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveData {
+    pub header: SaveHeader,
+    pub scene: Vec<u8>,
+}
+
+impl SaveData {
+    pub fn new(camera: Option<SaveCamera>, scene: Vec<u8>) -> SaveData {
+        let saved_at = instant::SystemTime::now()
+            .duration_since(instant::SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        SaveData {
+            header: SaveHeader {
+                save_version: SAVE_VERSION,
+                saved_at,
+                camera,
+            },
+            scene,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = serde_json::to_vec(&self.header).expect("Failed to serialize save header");
+        bytes.push(b'\n');
+        bytes.extend_from_slice(&self.scene);
+        bytes
+    }
+
+    /// 读取存档，旧版本的存档会依次迁移到当前的版本
+    pub fn from_bytes(bytes: &[u8]) -> Result<SaveData, SaveError> {
+        let (mut header, mut scene) = match bytes.iter().position(|byte| *byte == b'\n') {
+            Some(end) => match serde_json::from_slice::<serde_json::Value>(&bytes[..end]) {
+                Ok(header) if header.get("save_version").is_some() => {
+                    (header, bytes[end + 1..].to_vec())
+                }
+                _ => (serde_json::Value::Null, bytes.to_vec()),
+            },
+            None => (serde_json::Value::Null, bytes.to_vec()),
+        };
+        let mut version = header
+            .get("save_version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0) as u32;
+        if version > SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+        while version < SAVE_VERSION {
+            log::info!("Migrating save from version {version}");
+            MIGRATIONS[version as usize](&mut header, &mut scene);
+            version += 1;
+        }
+        let header: SaveHeader = serde_json::from_value(header).map_err(SaveError::Json)?;
+        Ok(SaveData { header, scene })
+    }
+
+    /// 保存前清理镜头中无效的数据
+    ///
+    /// 场景只能原样保存，物品的临时状态（比如拿在手上的物品）会一起保存下来，
+    /// 引擎的 Scene 还没有提供重置这些状态的接口
+    pub fn sanitize(&mut self) {
+        if let Some(camera) = &mut self.header.camera {
+            if !camera.translation.is_finite() {
+                self.header.camera = None;
+            } else {
+                // 缩放只有 x1 x2 x4 x8 这几档
+                camera.pixel_zoom = camera.pixel_zoom.clamp(1, 8).next_power_of_two().min(8);
+            }
+        }
+    }
+}
+
+/// 清理玩家输入的存档位名称，只保留字母、数字、`-` 和 `_`，其他字符替换为 `_`
+pub fn sanitize_slot_name(slot: &str) -> Result<String, SaveError> {
+    let name: String = slot
+        .trim()
+        .chars()
+        .take(MAX_SLOT_NAME_LEN)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '_') {
+        return Err(SaveError::InvalidSlot(slot.to_owned()));
+    }
+    Ok(name)
+}
+
+/// 存档位对应的存储 key
+pub fn slot_key(slot: &str) -> Result<String, Error> {
+    let name = sanitize_slot_name(slot).map_err(|source| Error::Save {
+        slot: slot.to_owned(),
+        source,
+    })?;
+    Ok(format!("{SAVES_DIR}/{name}.{SAVE_EXTENSION}"))
+}
+
+pub fn save_slot(storage: &mut dyn Storage, slot: &str, data: &SaveData) -> Result<(), Error> {
+    storage.write(&slot_key(slot)?, &data.to_bytes())
+}
+
+/// 读取存档，存档位还没有存档时为 None
+pub fn load_slot(storage: &dyn Storage, slot: &str) -> Result<Option<SaveData>, Error> {
+    let Some(bytes) = storage.read(&slot_key(slot)?)? else {
+        return Ok(None);
+    };
+    SaveData::from_bytes(&bytes)
+        .map(Some)
+        .map_err(|source| Error::Save {
+            slot: slot.to_owned(),
+            source,
+        })
+}
+
+pub fn delete_slot(storage: &mut dyn Storage, slot: &str) -> Result<(), Error> {
+    storage.remove(&slot_key(slot)?)
+}
+
+/// 所有存档位的名称
+pub fn list_slots(storage: &dyn Storage) -> Result<Vec<String>, Error> {
+    Ok(storage
+        .list(SAVES_DIR)?
+        .into_iter()
+        .filter_map(|name| Some(name.strip_suffix(&format!(".{SAVE_EXTENSION}"))?.to_owned()))
+        .collect())
+}
+
+/// 自动存档的计时器
+#[derive(Debug, Copy, Clone)]
+pub struct Autosave {
+    interval: Duration,
+    elapsed: Duration,
+}
+
+impl Autosave {
+    pub fn new(interval: Duration) -> Autosave {
+        Autosave {
+            interval,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// 累加经过的时间，到了存档的时间时返回 true 并重新开始计时
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        self.elapsed += delta;
+        if self.elapsed >= self.interval {
+            self.elapsed = Duration::ZERO;
+            return true;
+        }
+        false
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Autosave::new(AUTOSAVE_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_round_trip_and_legacy_migration() {
+        let mut storage = MemoryStorage::default();
+        assert!(list_slots(&storage).unwrap().is_empty());
+        assert_eq!(load_slot(&storage, "slot_1").unwrap(), None);
+
+        let camera = SaveCamera {
+            translation: Vec2::new(10.0, -4.0),
+            pixel_zoom: 4,
+        };
+        let data = SaveData::new(Some(camera), b"{\"scene\":\n1}".to_vec());
+        save_slot(&mut storage, "Slot 1", &data).unwrap();
+        assert_eq!(load_slot(&storage, "slot_1").unwrap(), Some(data));
+        assert_eq!(list_slots(&storage).unwrap(), ["slot_1"]);
+        delete_slot(&mut storage, "slot_1").unwrap();
+        assert!(list_slots(&storage).unwrap().is_empty());
+
+        // 以前直接写入的场景文件没有存档头
+        let legacy = SaveData::from_bytes(b"{\"scene\":1}").unwrap();
+        assert_eq!(legacy.header.save_version, SAVE_VERSION);
+        assert_eq!(legacy.header.camera, None);
+        assert_eq!(legacy.scene, b"{\"scene\":1}");
+
+        let future = format!("{{\"save_version\":{}}}\n", SAVE_VERSION + 1);
+        assert!(matches!(
+            SaveData::from_bytes(future.as_bytes()),
+            Err(SaveError::Version(_))
+        ));
+    }
+
+    #[test]
+    fn sanitizes_slot_names_and_camera() {
+        assert_eq!(sanitize_slot_name(" My/../Save ").unwrap(), "my____save");
+        assert!(sanitize_slot_name("../").is_err());
+        assert!(sanitize_slot_name("").is_err());
+
+        let mut data = SaveData::new(
+            Some(SaveCamera {
+                translation: Vec2::ZERO,
+                pixel_zoom: 3,
+            }),
+            Vec::new(),
+        );
+        data.sanitize();
+        assert_eq!(data.header.camera.unwrap().pixel_zoom, 4);
+        data.header.camera.as_mut().unwrap().translation.x = f32::NAN;
+        data.sanitize();
+        assert_eq!(data.header.camera, None);
+    }
+}
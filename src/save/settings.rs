@@ -0,0 +1,139 @@
+use crate::input::{Input, KeyBindings};
+use crate::{AppConfig, Audio, AudioBus, Error, Storage};
+use serde::{Deserialize, Serialize};
+
+/// 设置在存储中的 key
+pub const SETTINGS_KEY: &str = "settings.json";
+
+/// 玩家的设置，启动时读取，在设置界面中修改后立即应用
+///
+/// 缺少的字段使用默认值，这样增加新的设置后旧的设置文件依旧可以读取
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    pub decorations: bool,
+    /// 新的一局游戏开始时镜头的缩放，只有 x1 x2 x4 x8 这几档
+    pub default_zoom: u8,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let config = AppConfig::default();
+        Self {
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            fullscreen: config.fullscreen,
+            decorations: config.decorations,
+            default_zoom: 2,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// 还没有保存过设置时使用默认设置
+    pub fn load(storage: &dyn Storage) -> Result<Settings, Error> {
+        let Some(bytes) = storage.read(SETTINGS_KEY)? else {
+            return Ok(Settings::default());
+        };
+        let mut settings: Settings = serde_json::from_slice(&bytes).map_err(Error::Settings)?;
+        settings.sanitize();
+        Ok(settings)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> Result<(), Error> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(Error::Settings)?;
+        storage.write(SETTINGS_KEY, &bytes)
+    }
+
+    /// 把手动修改过的设置限制在有效的范围内，并解决按键绑定的冲突
+    pub fn sanitize(&mut self) {
+        for volume in [
+            &mut self.master_volume,
+            &mut self.music_volume,
+            &mut self.sfx_volume,
+        ] {
+            *volume = if volume.is_finite() {
+                volume.clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+        }
+        self.default_zoom = self.default_zoom.clamp(1, 8).next_power_of_two().min(8);
+        self.key_bindings.resolve_conflicts();
+    }
+
+    /// 立即应用到窗口、音频和按键绑定，窗口的变化在下一次处理窗口事件时生效
+    pub fn apply(&self, config: &mut AppConfig, audio: &mut Audio, input: &mut Input) {
+        config.fullscreen = self.fullscreen;
+        config.decorations = self.decorations;
+        audio.set_bus_volume(AudioBus::Master, self.master_volume);
+        audio.set_bus_volume(AudioBus::Music, self.music_volume);
+        audio.set_bus_volume(AudioBus::Sfx, self.sfx_volume);
+        input.set_bindings(self.key_bindings.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Action;
+    use crate::MemoryStorage;
+    use winit::keyboard::KeyCode;
+
+    #[test]
+    fn settings_round_trip() {
+        let mut storage = MemoryStorage::default();
+        assert_eq!(Settings::load(&storage).unwrap(), Settings::default());
+
+        let mut settings = Settings {
+            music_volume: 0.3,
+            fullscreen: true,
+            default_zoom: 4,
+            ..Default::default()
+        };
+        settings.key_bindings.bind(Action::Pause, KeyCode::KeyP);
+        settings.save(&mut storage).unwrap();
+        assert_eq!(Settings::load(&storage).unwrap(), settings);
+
+        storage.write(SETTINGS_KEY, b"not json").unwrap();
+        assert!(matches!(Settings::load(&storage), Err(Error::Settings(_))));
+    }
+
+    #[test]
+    fn load_sanitizes_and_fills_missing_fields() {
+        let mut storage = MemoryStorage::default();
+        storage
+            .write(
+                SETTINGS_KEY,
+                br#"{"master_volume":3.0,"sfx_volume":-1.0,"default_zoom":3}"#,
+            )
+            .unwrap();
+        let settings = Settings::load(&storage).unwrap();
+        assert_eq!(settings.master_volume, 1.0);
+        assert_eq!(settings.sfx_volume, 0.0);
+        assert_eq!(settings.music_volume, 1.0);
+        assert_eq!(settings.default_zoom, 4);
+        assert!(settings.decorations);
+    }
+
+    #[test]
+    fn load_resolves_conflicting_bindings() {
+        let mut storage = MemoryStorage::default();
+        storage
+            .write(
+                SETTINGS_KEY,
+                br#"{"key_bindings":{"keys":{"QuickSave":"Escape"}}}"#,
+            )
+            .unwrap();
+        let settings = Settings::load(&storage).unwrap();
+        assert_eq!(settings.key_bindings.key(Action::Pause), KeyCode::Escape);
+        assert_eq!(settings.key_bindings.key(Action::QuickSave), KeyCode::KeyS);
+    }
+}
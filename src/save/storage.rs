@@ -0,0 +1,421 @@
+use crate::Error;
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+/// 持久化数据的存储，key 使用 `/` 分隔的相对路径，比如 `saves/autosave.sav`
+///
+/// native 上保存到平台的数据目录，web 上保存到 localStorage 和 IndexedDB，测试时使用 MemoryStorage
+pub trait Storage {
+    /// 读取 key 对应的数据，不存在时为 None
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// 写入数据，保证写入到一半失败时不会损坏原来的数据
+    fn write(&mut self, key: &str, bytes: &[u8]) -> Result<(), Error>;
+
+    /// 删除数据，不存在时什么都不做
+    fn remove(&mut self, key: &str) -> Result<(), Error>;
+
+    /// dir 下所有数据的名称，不包括 dir 本身，也不包括更深层的数据
+    fn list(&self, dir: &str) -> Result<Vec<String>, Error>;
+
+    /// 取出之前在后台写入时出现的错误
+    fn take_errors(&mut self) -> Vec<Error> {
+        Vec::new()
+    }
+}
+
+/// 取出 key 在 dir 下的名称，key 不是 dir 下的直接子项时为 None
+fn child_name<'a>(key: &'a str, dir: &str) -> Option<&'a str> {
+    let name = key.strip_prefix(dir)?.strip_prefix('/')?;
+    (!name.is_empty() && !name.contains('/')).then_some(name)
+}
+
+/// 保存在内存中，用于测试
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStorage {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn write(&mut self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.entries.insert(key.to_owned(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), Error> {
+        self.entries.remove(key);
+        Ok(())
+    }
+
+    fn list(&self, dir: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .entries
+            .keys()
+            .filter_map(|key| child_name(key, dir))
+            .map(str::to_owned)
+            .collect())
+    }
+}
+
+/// 保存到本地文件系统的目录中
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    root: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FsStorage {
+    pub fn new(root: impl Into<PathBuf>) -> FsStorage {
+        FsStorage { root: root.into() }
+    }
+
+    /// 平台的数据目录，比如 Linux 上的 `~/.local/share/webgpu-demo`
+    pub fn platform() -> Option<FsStorage> {
+        dirs::data_dir().map(|dir| FsStorage::new(dir.join(crate::PKG_NAME)))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        debug_assert!(
+            key.split('/').all(|part| !part.is_empty() && part != ".."),
+            "Invalid storage key {key:?}"
+        );
+        self.root.join(key)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FsStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let path = self.path(key);
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(Error::Io { path, source }),
+        }
+    }
+
+    /// 先写入临时文件再重命名，写入到一半时崩溃或者断电也不会损坏原来的文件
+    fn write(&mut self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        let path = self.path(key);
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|source| Error::Io {
+                path: dir.to_owned(),
+                source,
+            })?;
+        }
+        let write = || -> std::io::Result<()> {
+            use std::io::Write;
+            let mut file = std::fs::File::create(&temp_path)?;
+            file.write_all(bytes)?;
+            file.sync_all()
+        };
+        if let Err(source) = write() {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(Error::Io {
+                path: temp_path,
+                source,
+            });
+        }
+        std::fs::rename(&temp_path, &path).map_err(|source| Error::Io { path, source })
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), Error> {
+        let path = self.path(key);
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(Error::Io { path, source: err })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self, dir: &str) -> Result<Vec<String>, Error> {
+        let path = self.path(dir);
+        let entries = match std::fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(Error::Io { path, source }),
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            // 跳过写入到一半的临时文件
+            .filter(|name| !name.ends_with(".tmp"))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// 保存到浏览器中
+///
+/// 较小的文本数据（比如设置）保存到 localStorage，场景存档等较大的数据保存到 IndexedDB。
+/// IndexedDB 只有异步的接口，所以打开时把所有数据读到内存中，之后的写入先更新内存再在后台写入。
+#[cfg(target_arch = "wasm32")]
+pub struct WebStorage {
+    local_storage: web_sys::Storage,
+    database: web_sys::IdbDatabase,
+    /// IndexedDB 中的所有数据
+    blobs: BTreeMap<String, Vec<u8>>,
+    errors: std::rc::Rc<std::cell::RefCell<Vec<Error>>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::*;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    /// 超过这个长度或者不是文本的数据保存到 IndexedDB，localStorage 通常只有 5MB 的空间
+    pub const LOCAL_STORAGE_MAX_LEN: usize = 16 * 1024;
+    const DATABASE_VERSION: u32 = 1;
+    const BLOB_STORE: &str = "blobs";
+
+    pub fn js_error(context: &str, err: JsValue) -> Error {
+        Error::Storage(format!("{context}: {err:?}"))
+    }
+
+    /// 等待 IndexedDB 的请求完成，返回请求的结果
+    pub async fn request(request: &web_sys::IdbRequest) -> Result<JsValue, JsValue> {
+        let promise = js_sys::Promise::new(&mut |resolve, reject| {
+            let success_request = request.clone();
+            let onsuccess = wasm_bindgen::closure::Closure::once_into_js(move || {
+                let result = success_request.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::UNDEFINED, &result);
+            });
+            let error_request = request.clone();
+            let onerror = wasm_bindgen::closure::Closure::once_into_js(move || {
+                let error = error_request
+                    .error()
+                    .ok()
+                    .flatten()
+                    .map(JsValue::from)
+                    .unwrap_or(JsValue::UNDEFINED);
+                let _ = reject.call1(&JsValue::UNDEFINED, &error);
+            });
+            request.set_onsuccess(Some(onsuccess.unchecked_ref()));
+            request.set_onerror(Some(onerror.unchecked_ref()));
+        });
+        wasm_bindgen_futures::JsFuture::from(promise).await
+    }
+
+    impl WebStorage {
+        pub async fn open() -> Result<WebStorage, Error> {
+            let window = web_sys::window().ok_or(Error::Storage("No window".to_owned()))?;
+            let local_storage = window
+                .local_storage()
+                .map_err(|err| js_error("Failed to access localStorage", err))?
+                .ok_or(Error::Storage("localStorage is unavailable".to_owned()))?;
+            let factory = window
+                .indexed_db()
+                .map_err(|err| js_error("Failed to access IndexedDB", err))?
+                .ok_or(Error::Storage("IndexedDB is unavailable".to_owned()))?;
+
+            let open_request = factory
+                .open_with_u32(crate::PKG_NAME, DATABASE_VERSION)
+                .map_err(|err| js_error("Failed to open IndexedDB", err))?;
+            let upgrade_request = open_request.clone();
+            let onupgradeneeded = wasm_bindgen::closure::Closure::once_into_js(move || {
+                if let Some(database) = upgrade_request
+                    .result()
+                    .ok()
+                    .and_then(|result| result.dyn_into::<web_sys::IdbDatabase>().ok())
+                {
+                    if let Err(err) = database.create_object_store(BLOB_STORE) {
+                        log::error!("Failed to create IndexedDB store: {err:?}");
+                    }
+                }
+            });
+            open_request.set_onupgradeneeded(Some(onupgradeneeded.unchecked_ref()));
+            let database: web_sys::IdbDatabase = request(&open_request)
+                .await
+                .map_err(|err| js_error("Failed to open IndexedDB", err))?
+                .unchecked_into();
+
+            // 把所有数据读到内存中，get_all_keys 和 get_all 的结果都按 key 排序，顺序一致
+            let store = database
+                .transaction_with_str_and_mode(BLOB_STORE, web_sys::IdbTransactionMode::Readonly)
+                .and_then(|transaction| transaction.object_store(BLOB_STORE))
+                .map_err(|err| js_error("Failed to read IndexedDB", err))?;
+            let keys = store
+                .get_all_keys()
+                .map_err(|err| js_error("Failed to read IndexedDB", err))?;
+            let values = store
+                .get_all()
+                .map_err(|err| js_error("Failed to read IndexedDB", err))?;
+            let keys: js_sys::Array = request(&keys)
+                .await
+                .map_err(|err| js_error("Failed to read IndexedDB", err))?
+                .unchecked_into();
+            let values: js_sys::Array = request(&values)
+                .await
+                .map_err(|err| js_error("Failed to read IndexedDB", err))?
+                .unchecked_into();
+            let blobs = keys
+                .iter()
+                .zip(values.iter())
+                .filter_map(|(key, value)| {
+                    Some((key.as_string()?, js_sys::Uint8Array::new(&value).to_vec()))
+                })
+                .collect();
+
+            Ok(WebStorage {
+                local_storage,
+                database,
+                blobs,
+                errors: Default::default(),
+            })
+        }
+
+        pub(super) fn local_key(key: &str) -> String {
+            format!("{}/{key}", crate::PKG_NAME)
+        }
+
+        /// 在后台执行 IndexedDB 的写入，出现的错误之后通过 take_errors 取出
+        pub(super) fn spawn_blob_request(
+            &self,
+            key: &str,
+            bytes: Option<&[u8]>,
+        ) -> Result<(), Error> {
+            let store = self
+                .database
+                .transaction_with_str_and_mode(BLOB_STORE, web_sys::IdbTransactionMode::Readwrite)
+                .and_then(|transaction| transaction.object_store(BLOB_STORE))
+                .map_err(|err| js_error("Failed to write IndexedDB", err))?;
+            let key_value = JsValue::from_str(key);
+            let blob_request = match bytes {
+                Some(bytes) => {
+                    store.put_with_key(&js_sys::Uint8Array::from(bytes).into(), &key_value)
+                }
+                None => store.delete(&key_value),
+            }
+            .map_err(|err| js_error("Failed to write IndexedDB", err))?;
+            let errors = self.errors.clone();
+            let key = key.to_owned();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(err) = request(&blob_request).await {
+                    errors.borrow_mut().push(js_error(
+                        &format!("Failed to write {key} to IndexedDB"),
+                        err,
+                    ));
+                }
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for WebStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(bytes) = self.blobs.get(key) {
+            return Ok(Some(bytes.clone()));
+        }
+        self.local_storage
+            .get_item(&WebStorage::local_key(key))
+            .map(|value| value.map(String::into_bytes))
+            .map_err(|err| web::js_error("Failed to read localStorage", err))
+    }
+
+    fn write(&mut self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        let local_key = WebStorage::local_key(key);
+        match std::str::from_utf8(bytes) {
+            Ok(text) if bytes.len() <= web::LOCAL_STORAGE_MAX_LEN => {
+                self.local_storage
+                    .set_item(&local_key, text)
+                    .map_err(|err| web::js_error("Failed to write localStorage", err))?;
+                if self.blobs.remove(key).is_some() {
+                    self.spawn_blob_request(key, None)?;
+                }
+            }
+            _ => {
+                self.spawn_blob_request(key, Some(bytes))?;
+                self.blobs.insert(key.to_owned(), bytes.to_vec());
+                let _ = self.local_storage.remove_item(&local_key);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), Error> {
+        if self.blobs.remove(key).is_some() {
+            self.spawn_blob_request(key, None)?;
+        }
+        self.local_storage
+            .remove_item(&WebStorage::local_key(key))
+            .map_err(|err| web::js_error("Failed to write localStorage", err))
+    }
+
+    fn list(&self, dir: &str) -> Result<Vec<String>, Error> {
+        let local_dir = WebStorage::local_key(dir);
+        let len = self
+            .local_storage
+            .length()
+            .map_err(|err| web::js_error("Failed to read localStorage", err))?;
+        let mut names: Vec<String> = (0..len)
+            .filter_map(|index| self.local_storage.key(index).ok().flatten())
+            .filter_map(|key| child_name(&key, &local_dir).map(str::to_owned))
+            .chain(
+                self.blobs
+                    .keys()
+                    .filter_map(|key| child_name(key, dir))
+                    .map(str::to_owned),
+            )
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut *self.errors.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_storage(storage: &mut dyn Storage) {
+        assert_eq!(storage.read("saves/a.sav").unwrap(), None);
+        storage.write("saves/a.sav", b"first").unwrap();
+        storage.write("saves/a.sav", b"second").unwrap();
+        storage.write("saves/b.sav", b"b").unwrap();
+        storage.write("settings.json", b"{}").unwrap();
+        assert_eq!(storage.read("saves/a.sav").unwrap().unwrap(), b"second");
+        assert_eq!(storage.list("saves").unwrap(), ["a.sav", "b.sav"]);
+        storage.remove("saves/a.sav").unwrap();
+        storage.remove("saves/missing.sav").unwrap();
+        assert_eq!(storage.list("saves").unwrap(), ["b.sav"]);
+        assert!(storage.list("missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_and_fs_storage_behave_the_same() {
+        check_storage(&mut MemoryStorage::default());
+
+        let root = std::env::temp_dir().join(format!(
+            "{}-storage-{}",
+            crate::PKG_NAME,
+            std::process::id()
+        ));
+        let mut storage = FsStorage::new(&root);
+        check_storage(&mut storage);
+        // 临时文件已经被重命名
+        assert!(!root.join("saves/b.sav.tmp").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use instant::Duration;
+
+/// 固定时间步长的累加器
+///
+/// 每帧把经过的时间累加起来，每满一个步长就执行一次 fixed_update，
+/// 剩下不足一个步长的时间用于渲染时在前后两次 fixed_update 的状态之间插值。
+#[derive(Debug, Copy, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    /// 一帧内最多执行的步数，卡顿后不会为了追赶时间而越来越慢
+    max_steps: u32,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration, max_steps: u32) -> FixedTimestep {
+        assert!(!step.is_zero(), "Fixed timestep must not be zero");
+        FixedTimestep {
+            step,
+            max_steps: max_steps.max(1),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// 修改步长和最大步数，已经累计的时间不变
+    pub fn set_step(&mut self, step: Duration, max_steps: u32) {
+        *self = FixedTimestep {
+            accumulator: self.accumulator,
+            ..FixedTimestep::new(step, max_steps)
+        };
+    }
+
+    /// 累加经过的时间，返回这一帧需要执行 fixed_update 的次数
+    ///
+    /// 超过 max_steps 的部分直接丢弃，只保留不足一个步长的时间
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                self.accumulator = Duration::from_nanos(
+                    (self.accumulator.as_nanos() % self.step.as_nanos()) as u64,
+                );
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// 累计的时间占一个步长的比例，范围为 [0, 1)
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep::new(Duration::from_secs(1) / 60, 5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_partial_frames() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10), 5);
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-5);
+        assert_eq!(timestep.advance(Duration::from_millis(25)), 2);
+        assert!((timestep.alpha() - 0.7).abs() < 1e-5);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10), 3);
+        assert_eq!(timestep.advance(Duration::from_millis(1005)), 3);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-5);
+        // 丢弃的时间不会在之后的帧里补回来
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 1);
+    }
+}
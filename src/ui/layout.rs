@@ -0,0 +1,508 @@
+use crate::Rect;
+use glam::Vec2;
+
+/// UI 尺寸
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Val {
+    /// 由内容决定，或者在同时设置了两侧锚点时由锚点决定
+    #[default]
+    Auto,
+    /// 逻辑像素，实际的物理像素 = 逻辑像素 * scale_factor
+    Px(f32),
+    /// 父节点内容区域尺寸的百分比
+    Percent(f32),
+}
+
+impl Val {
+    #[inline]
+    pub fn resolve(&self, parent: f32, scale_factor: f32) -> Option<f32> {
+        match self {
+            Val::Auto => None,
+            Val::Px(value) => Some(value * scale_factor),
+            Val::Percent(percent) => Some(parent * percent / 100.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct UiRect {
+    pub left: Val,
+    pub right: Val,
+    pub top: Val,
+    pub bottom: Val,
+}
+
+impl UiRect {
+    pub const fn all(val: Val) -> UiRect {
+        UiRect {
+            left: val,
+            right: val,
+            top: val,
+            bottom: val,
+        }
+    }
+
+    fn resolve(&self, parent: Vec2, scale_factor: f32) -> [f32; 4] {
+        [
+            self.left.resolve(parent.x, scale_factor).unwrap_or(0.0),
+            self.right.resolve(parent.x, scale_factor).unwrap_or(0.0),
+            self.top.resolve(parent.y, scale_factor).unwrap_or(0.0),
+            self.bottom.resolve(parent.y, scale_factor).unwrap_or(0.0),
+        ]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PositionType {
+    /// 由父节点按 flex 规则排列，left/right/top/bottom 作为额外的偏移
+    #[default]
+    Relative,
+    /// 脱离父节点的排列，通过 left/right/top/bottom 锚定在父节点内容区域的边上
+    Absolute,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum FlexDirection {
+    #[default]
+    Row,
+    Column,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum JustifyContent {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum AlignItems {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// 交叉轴尺寸为 Auto 的子节点会被拉伸到父节点内容区域的尺寸
+    Stretch,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    pub position_type: PositionType,
+    pub left: Val,
+    pub right: Val,
+    pub top: Val,
+    pub bottom: Val,
+    pub width: Val,
+    pub height: Val,
+    pub padding: UiRect,
+    pub flex_direction: FlexDirection,
+    /// 子节点之间的间隔
+    pub gap: Val,
+    pub justify_content: JustifyContent,
+    pub align_items: AlignItems,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UiNodeId(usize);
+
+#[derive(Debug, Default)]
+struct UiNode {
+    style: Style,
+    /// 内容的尺寸（逻辑像素），比如图片的尺寸，用于计算 Auto 尺寸
+    content_size: Option<Vec2>,
+    children: Vec<UiNodeId>,
+    layout: Rect,
+}
+
+/// UI 布局树
+///
+/// 布局结果使用屏幕坐标，单位是物理像素，以左上角为原点，y 轴向下，
+/// 所以 layout 返回的 Rect 的 min 是左上角，max 是右下角。
+#[derive(Debug, Default)]
+pub struct UiTree {
+    nodes: Vec<UiNode>,
+    roots: Vec<UiNodeId>,
+}
+
+impl UiTree {
+    /// 创建一个根节点，根节点相对整个 viewport 进行定位
+    pub fn new_root(&mut self, style: Style) -> UiNodeId {
+        let id = self.new_node(style);
+        self.roots.push(id);
+        id
+    }
+
+    pub fn new_child(&mut self, parent: UiNodeId, style: Style) -> UiNodeId {
+        let id = self.new_node(style);
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+
+    fn new_node(&mut self, style: Style) -> UiNodeId {
+        self.nodes.push(UiNode {
+            style,
+            ..Default::default()
+        });
+        UiNodeId(self.nodes.len() - 1)
+    }
+
+    pub fn style(&self, id: UiNodeId) -> &Style {
+        &self.nodes[id.0].style
+    }
+
+    pub fn style_mut(&mut self, id: UiNodeId) -> &mut Style {
+        &mut self.nodes[id.0].style
+    }
+
+    pub fn set_content_size(&mut self, id: UiNodeId, content_size: Option<Vec2>) {
+        self.nodes[id.0].content_size = content_size;
+    }
+
+    /// 最近一次 compute_layout 的结果
+    pub fn layout(&self, id: UiNodeId) -> Rect {
+        self.nodes[id.0].layout
+    }
+
+    pub fn compute_layout(&mut self, viewport_size: Vec2, scale_factor: f32) {
+        let viewport = Rect::from_corners(Vec2::ZERO, viewport_size);
+        for index in 0..self.roots.len() {
+            let root = self.roots[index];
+            let size = self.measure(root, viewport_size, scale_factor);
+            let rect = self.place_absolute(root, viewport, size, scale_factor);
+            self.layout_node(root, rect, scale_factor);
+        }
+    }
+
+    /// 计算节点的尺寸，parent_size 是父节点内容区域的尺寸
+    fn measure(&self, id: UiNodeId, parent_size: Vec2, scale_factor: f32) -> Vec2 {
+        let node = &self.nodes[id.0];
+        let style = &node.style;
+        let mut width = style.width.resolve(parent_size.x, scale_factor);
+        let mut height = style.height.resolve(parent_size.y, scale_factor);
+
+        // 同时设置了两侧锚点，则尺寸由锚点决定
+        if style.position_type == PositionType::Absolute {
+            if width.is_none() {
+                if let (Some(left), Some(right)) = (
+                    style.left.resolve(parent_size.x, scale_factor),
+                    style.right.resolve(parent_size.x, scale_factor),
+                ) {
+                    width = Some((parent_size.x - left - right).max(0.0));
+                }
+            }
+            if height.is_none() {
+                if let (Some(top), Some(bottom)) = (
+                    style.top.resolve(parent_size.y, scale_factor),
+                    style.bottom.resolve(parent_size.y, scale_factor),
+                ) {
+                    height = Some((parent_size.y - top - bottom).max(0.0));
+                }
+            }
+        }
+
+        if let (Some(width), Some(height)) = (width, height) {
+            return Vec2::new(width, height);
+        }
+
+        let content = if let Some(content_size) = node.content_size {
+            content_size * scale_factor
+        } else {
+            let [left, right, top, bottom] = style.padding.resolve(parent_size, scale_factor);
+            let inner_size = Vec2::new(
+                width.unwrap_or(parent_size.x) - left - right,
+                height.unwrap_or(parent_size.y) - top - bottom,
+            )
+            .max(Vec2::ZERO);
+            let (main, cross) = self.measure_flow(id, inner_size, scale_factor);
+            let content = match style.flex_direction {
+                FlexDirection::Row => Vec2::new(main, cross),
+                FlexDirection::Column => Vec2::new(cross, main),
+            };
+            content + Vec2::new(left + right, top + bottom)
+        };
+
+        Vec2::new(width.unwrap_or(content.x), height.unwrap_or(content.y))
+    }
+
+    /// 计算参与 flex 排列的子节点在主轴上的总长度以及交叉轴上的最大长度
+    fn measure_flow(&self, id: UiNodeId, inner_size: Vec2, scale_factor: f32) -> (f32, f32) {
+        let style = &self.nodes[id.0].style;
+        let row = style.flex_direction == FlexDirection::Row;
+        let gap = style
+            .gap
+            .resolve(if row { inner_size.x } else { inner_size.y }, scale_factor)
+            .unwrap_or(0.0);
+        let mut main = 0.0_f32;
+        let mut cross = 0.0_f32;
+        let mut count = 0;
+        for child in self.flow_children(id) {
+            let size = self.measure(child, inner_size, scale_factor);
+            let (child_main, child_cross) = if row {
+                (size.x, size.y)
+            } else {
+                (size.y, size.x)
+            };
+            main += child_main;
+            cross = cross.max(child_cross);
+            count += 1;
+        }
+        if count > 1 {
+            main += gap * (count - 1) as f32;
+        }
+        (main, cross)
+    }
+
+    fn flow_children(&self, id: UiNodeId) -> impl Iterator<Item = UiNodeId> + '_ {
+        self.nodes[id.0]
+            .children
+            .iter()
+            .copied()
+            .filter(|child| self.nodes[child.0].style.position_type == PositionType::Relative)
+    }
+
+    fn place_absolute(&self, id: UiNodeId, parent: Rect, size: Vec2, scale_factor: f32) -> Rect {
+        let style = &self.nodes[id.0].style;
+        let parent_size = parent.size();
+        let x = if let Some(left) = style.left.resolve(parent_size.x, scale_factor) {
+            parent.min.x + left
+        } else if let Some(right) = style.right.resolve(parent_size.x, scale_factor) {
+            parent.max.x - right - size.x
+        } else {
+            parent.min.x
+        };
+        let y = if let Some(top) = style.top.resolve(parent_size.y, scale_factor) {
+            parent.min.y + top
+        } else if let Some(bottom) = style.bottom.resolve(parent_size.y, scale_factor) {
+            parent.max.y - bottom - size.y
+        } else {
+            parent.min.y
+        };
+        Rect::from_corners(Vec2::new(x, y), Vec2::new(x, y) + size)
+    }
+
+    fn layout_node(&mut self, id: UiNodeId, rect: Rect, scale_factor: f32) {
+        self.nodes[id.0].layout = rect;
+
+        let style = self.nodes[id.0].style;
+        let [left, right, top, bottom] = style.padding.resolve(rect.size(), scale_factor);
+        let content = Rect::from_corners(
+            rect.min + Vec2::new(left, top),
+            (rect.max - Vec2::new(right, bottom)).max(rect.min + Vec2::new(left, top)),
+        );
+        let inner_size = content.size();
+        let row = style.flex_direction == FlexDirection::Row;
+        let axis = |v: Vec2| if row { (v.x, v.y) } else { (v.y, v.x) };
+        let from_axis = |main: f32, cross: f32| {
+            if row {
+                Vec2::new(main, cross)
+            } else {
+                Vec2::new(cross, main)
+            }
+        };
+
+        let (main_size, cross_size) = axis(inner_size);
+        let gap = style.gap.resolve(main_size, scale_factor).unwrap_or(0.0);
+        let flow: Vec<(UiNodeId, Vec2)> = self
+            .flow_children(id)
+            .map(|child| (child, self.measure(child, inner_size, scale_factor)))
+            .collect();
+        let used_main = flow.iter().map(|(_, size)| axis(*size).0).sum::<f32>()
+            + gap * flow.len().saturating_sub(1) as f32;
+        let free_main = (main_size - used_main).max(0.0);
+        let (mut cursor, spacing) = match style.justify_content {
+            JustifyContent::Start => (0.0, gap),
+            JustifyContent::Center => (free_main / 2.0, gap),
+            JustifyContent::End => (free_main, gap),
+            JustifyContent::SpaceBetween if flow.len() > 1 => {
+                (0.0, gap + free_main / (flow.len() - 1) as f32)
+            }
+            JustifyContent::SpaceBetween => (0.0, gap),
+        };
+
+        for (child, size) in flow {
+            let child_style = self.nodes[child.0].style;
+            let (child_main, mut child_cross) = axis(size);
+            let cross_auto = if row {
+                child_style.height == Val::Auto
+            } else {
+                child_style.width == Val::Auto
+            };
+            if style.align_items == AlignItems::Stretch && cross_auto {
+                child_cross = cross_size;
+            }
+            let cross_offset = match style.align_items {
+                AlignItems::Start | AlignItems::Stretch => 0.0,
+                AlignItems::Center => (cross_size - child_cross) / 2.0,
+                AlignItems::End => cross_size - child_cross,
+            };
+            let mut min = content.min + from_axis(cursor, cross_offset);
+            // Relative 节点的 left/right/top/bottom 只是额外的偏移
+            if let Some(offset) = child_style.left.resolve(inner_size.x, scale_factor) {
+                min.x += offset;
+            } else if let Some(offset) = child_style.right.resolve(inner_size.x, scale_factor) {
+                min.x -= offset;
+            }
+            if let Some(offset) = child_style.top.resolve(inner_size.y, scale_factor) {
+                min.y += offset;
+            } else if let Some(offset) = child_style.bottom.resolve(inner_size.y, scale_factor) {
+                min.y -= offset;
+            }
+            let child_rect = Rect::from_corners(min, min + from_axis(child_main, child_cross));
+            self.layout_node(child, child_rect, scale_factor);
+            cursor += child_main + spacing;
+        }
+
+        let children = self.nodes[id.0].children.clone();
+        for child in children {
+            if self.nodes[child.0].style.position_type == PositionType::Absolute {
+                let size = self.measure(child, inner_size, scale_factor);
+                let child_rect = self.place_absolute(child, content, size, scale_factor);
+                self.layout_node(child, child_rect, scale_factor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: Vec2 = Vec2::new(1920.0, 1080.0);
+
+    fn absolute(style: Style) -> Style {
+        Style {
+            position_type: PositionType::Absolute,
+            ..style
+        }
+    }
+
+    fn fixed(width: f32, height: f32) -> Style {
+        Style {
+            width: Val::Px(width),
+            height: Val::Px(height),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn anchors_on_all_edges() {
+        let mut tree = UiTree::default();
+        let top_left = tree.new_root(absolute(Style {
+            left: Val::Px(10.0),
+            top: Val::Px(20.0),
+            ..fixed(30.0, 40.0)
+        }));
+        let bottom_right = tree.new_root(absolute(Style {
+            right: Val::Px(10.0),
+            bottom: Val::Px(20.0),
+            ..fixed(30.0, 40.0)
+        }));
+        tree.compute_layout(VIEWPORT, 1.0);
+
+        let rect = tree.layout(top_left);
+        assert_eq!(rect.min, Vec2::new(10.0, 20.0));
+        assert_eq!(rect.max, Vec2::new(40.0, 60.0));
+        let rect = tree.layout(bottom_right);
+        assert_eq!(rect.min, Vec2::new(1880.0, 1020.0));
+        assert_eq!(rect.max, Vec2::new(1910.0, 1060.0));
+    }
+
+    #[test]
+    fn scale_factor_applies_to_px_only() {
+        let mut tree = UiTree::default();
+        let node = tree.new_root(absolute(Style {
+            right: Val::Px(16.0),
+            top: Val::Percent(10.0),
+            width: Val::Px(36.0),
+            height: Val::Percent(50.0),
+            ..Default::default()
+        }));
+        tree.compute_layout(VIEWPORT, 2.0);
+
+        let rect = tree.layout(node);
+        assert_eq!(rect.size(), Vec2::new(72.0, 540.0));
+        assert_eq!(rect.min, Vec2::new(1920.0 - 32.0 - 72.0, 108.0));
+    }
+
+    #[test]
+    fn opposite_anchors_stretch_auto_size() {
+        let mut tree = UiTree::default();
+        let node = tree.new_root(absolute(Style {
+            left: Val::Px(100.0),
+            right: Val::Percent(10.0),
+            top: Val::Px(0.0),
+            height: Val::Px(50.0),
+            ..Default::default()
+        }));
+        tree.compute_layout(VIEWPORT, 1.0);
+
+        let rect = tree.layout(node);
+        assert_eq!(rect.min, Vec2::new(100.0, 0.0));
+        assert_eq!(rect.size(), Vec2::new(1920.0 - 100.0 - 192.0, 50.0));
+    }
+
+    #[test]
+    fn row_container_with_auto_size_and_gap() {
+        let mut tree = UiTree::default();
+        let row = tree.new_root(absolute(Style {
+            right: Val::Px(16.0),
+            top: Val::Px(16.0),
+            padding: UiRect::all(Val::Px(2.0)),
+            gap: Val::Px(8.0),
+            ..Default::default()
+        }));
+        let a = tree.new_child(row, fixed(36.0, 36.0));
+        let b = tree.new_child(row, Style::default());
+        tree.set_content_size(b, Some(Vec2::new(20.0, 10.0)));
+        tree.compute_layout(VIEWPORT, 1.0);
+
+        let row_rect = tree.layout(row);
+        assert_eq!(
+            row_rect.size(),
+            Vec2::new(2.0 + 36.0 + 8.0 + 20.0 + 2.0, 40.0)
+        );
+        assert_eq!(row_rect.max.x, 1920.0 - 16.0);
+        let a_rect = tree.layout(a);
+        assert_eq!(a_rect.min, row_rect.min + Vec2::splat(2.0));
+        let b_rect = tree.layout(b);
+        assert_eq!(b_rect.min.x, a_rect.max.x + 8.0);
+        assert_eq!(b_rect.size(), Vec2::new(20.0, 10.0));
+    }
+
+    #[test]
+    fn column_container_justify_and_align() {
+        let mut tree = UiTree::default();
+        let column = tree.new_root(absolute(Style {
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..fixed(200.0, 100.0)
+        }));
+        let a = tree.new_child(column, fixed(50.0, 20.0));
+        let b = tree.new_child(column, fixed(100.0, 20.0));
+        tree.compute_layout(VIEWPORT, 1.0);
+
+        assert_eq!(tree.layout(a).min, Vec2::new(75.0, 30.0));
+        assert_eq!(tree.layout(b).min, Vec2::new(50.0, 50.0));
+
+        tree.style_mut(column).justify_content = JustifyContent::SpaceBetween;
+        tree.style_mut(column).align_items = AlignItems::Stretch;
+        let c = tree.new_child(
+            column,
+            Style {
+                height: Val::Px(20.0),
+                ..Default::default()
+            },
+        );
+        tree.compute_layout(VIEWPORT, 1.0);
+
+        assert_eq!(tree.layout(a).min, Vec2::new(0.0, 0.0));
+        assert_eq!(tree.layout(b).min, Vec2::new(0.0, 40.0));
+        assert_eq!(tree.layout(c).min, Vec2::new(0.0, 80.0));
+        assert_eq!(tree.layout(c).size(), Vec2::new(200.0, 20.0));
+    }
+}
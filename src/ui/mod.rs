@@ -0,0 +1,7 @@
+mod layout;
+mod text;
+mod widget;
+
+pub use layout::*;
+pub use text::*;
+pub use widget::*;
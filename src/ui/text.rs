@@ -0,0 +1,266 @@
+use crate::{
+    AssetsId, Camera2D, Color, Render, Sprite, TextureStore, Transform, UiNodeId, UiSprite, UiTree,
+};
+use glam::Vec3;
+use std::time::Duration;
+
+/// 内置点阵字体中每个字符的宽度和高度，不包括字符之间的间隔
+pub const GLYPH_SIZE: [u32; 2] = [5, 7];
+
+/// 每个字符占用的宽度和高度，包括右边和下边一个像素的间隔
+const CELL_SIZE: [u32; 2] = [GLYPH_SIZE[0] + 1, GLYPH_SIZE[1] + 1];
+
+/// ASCII 0x20 到 0x7E 的 5x7 点阵，每个字节是一列，最低位为最上面一行
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x14, 0x08, 0x3E, 0x08, 0x14],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x10, 0x08, 0x08, 0x10, 0x08],
+];
+
+fn glyph(c: char) -> &'static [u8; 5] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        // 点阵字体只有 ASCII，其他字符显示为问号
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT[index]
+}
+
+/// 用内置的点阵字体把多行文字画成图片，用于没有 egui 时在屏幕上显示文字
+///
+/// 文字为白色，渲染时用 sprite 的 color 着色，scale 为每个点阵像素的边长
+pub fn rasterize_text<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    scale: u32,
+    background: [u8; 4],
+) -> image::RgbaImage {
+    let lines: Vec<&str> = lines.into_iter().collect();
+    let scale = scale.max(1);
+    let padding = 2;
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u32;
+    let width = (columns * CELL_SIZE[0] + padding * 2) * scale;
+    let height = (lines.len() as u32 * CELL_SIZE[1] + padding * 2) * scale;
+    let mut image = image::RgbaImage::from_pixel(width, height, image::Rgba(background));
+
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            let origin = [
+                padding + column as u32 * CELL_SIZE[0],
+                padding + row as u32 * CELL_SIZE[1],
+            ];
+            for (x, bits) in glyph(c).iter().enumerate() {
+                for y in 0..GLYPH_SIZE[1] {
+                    if bits & (1 << y) == 0 {
+                        continue;
+                    }
+                    let pixel = [(origin[0] + x as u32) * scale, (origin[1] + y) * scale];
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            image.put_pixel(
+                                pixel[0] + dx,
+                                pixel[1] + dy,
+                                image::Rgba([255, 255, 255, 255]),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+/// 用内置点阵字体显示的文字，大小由文字决定，作为 UiTree 中一个节点的内容
+///
+/// 文字或者 DPI 缩放变化后在 prepare 中取得新的纹理，纹理生成之前不显示。
+#[derive(Debug)]
+pub struct Label {
+    pub node: UiNodeId,
+    pub sprite: UiSprite,
+    text: String,
+    /// 每个点阵像素的边长（逻辑像素）
+    size: u32,
+    /// 生成纹理时的 DPI 缩放
+    scale_factor: f32,
+    if_dirty: bool,
+}
+
+impl Label {
+    pub fn new(node: UiNodeId, text: impl Into<String>, size: u32, color: Color, z: f32) -> Label {
+        Label {
+            node,
+            sprite: UiSprite {
+                sprite: Sprite {
+                    transform: Transform::from_translation(Vec3::new(0.0, 0.0, z)),
+                    texture_id: AssetsId::INVALID,
+                    color,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: text.into(),
+            size,
+            scale_factor: 1.0,
+            if_dirty: true,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text = text.to_owned();
+            self.if_dirty = true;
+        }
+    }
+
+    /// 需要时取得文字的纹理，并把纹理的大小设置为节点的内容大小
+    pub fn prepare(
+        &mut self,
+        ui: &mut UiTree,
+        render: &Render,
+        texture_store: &mut TextureStore,
+        scale_factor: f32,
+    ) {
+        if !self.if_dirty && self.scale_factor == scale_factor {
+            return;
+        }
+        self.scale_factor = scale_factor;
+        self.if_dirty = false;
+        let scale = (self.size as f32 * scale_factor).round().max(1.0) as u32;
+        let texture_id = texture_store.text_texture(render, &self.text, scale);
+        self.sprite.sprite.texture_id = texture_id;
+        let size = texture_store.get_or_missing(&texture_id).0;
+        ui.set_content_size(self.node, Some(size / scale_factor));
+    }
+
+    pub fn update(&mut self, ui: &UiTree, camera: &Camera2D) {
+        self.sprite
+            .update(ui.layout(self.node), camera, Duration::ZERO);
+    }
+
+    pub fn sprite(&self) -> Option<&Sprite> {
+        (self.sprite.sprite.texture_id != AssetsId::INVALID).then_some(&self.sprite.sprite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterizes_lines_with_padding() {
+        let image = rasterize_text(["I", "--"], 2, [0, 0, 0, 160]);
+        assert_eq!(image.dimensions(), ((2 * 6 + 4) * 2, (2 * 8 + 4) * 2));
+        let lit = |x: u32, y: u32| image.get_pixel(x * 2, y * 2).0 == [255, 255, 255, 255];
+        // I 的竖线在第三列，- 在第四行
+        assert!((2..9).all(|y| lit(4, y)));
+        assert!(!lit(3, 5));
+        assert!((2..7).all(|x| lit(x, 13)) && (8..13).all(|x| lit(x, 13)));
+        assert!(!lit(7, 13));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 160]);
+        // 非 ASCII 字符显示为问号
+        assert_eq!(
+            rasterize_text(["é"], 1, [0; 4]).as_raw(),
+            rasterize_text(["?"], 1, [0; 4]).as_raw()
+        );
+    }
+}
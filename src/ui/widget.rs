@@ -0,0 +1,686 @@
+use crate::assets::AssetsId;
+use crate::input::{GamepadButton, Input, InputLayer};
+use crate::{Camera2D, Rect, Sprite, UiNodeId, UiSprite, UiTree};
+use glam::Vec2;
+use std::time::Duration;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+/// 控件在某个状态下的外观
+#[derive(Copy, Clone, Debug)]
+pub struct WidgetVisual {
+    pub texture_id: AssetsId,
+    /// 选取 texture 的区域
+    pub rect: Option<Rect>,
+    pub opacity: f32,
+    /// 循环播放的帧动画
+    pub anima: Option<&'static [Rect]>,
+}
+
+impl WidgetVisual {
+    pub fn new(texture_id: AssetsId) -> WidgetVisual {
+        WidgetVisual {
+            texture_id,
+            rect: None,
+            opacity: 1.0,
+            anima: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct WidgetVisuals {
+    pub normal: WidgetVisual,
+    pub hovered: WidgetVisual,
+    pub pressed: WidgetVisual,
+    pub disabled: WidgetVisual,
+}
+
+impl WidgetVisuals {
+    /// 所有状态都使用同一个外观
+    pub fn splat(visual: WidgetVisual) -> WidgetVisuals {
+        WidgetVisuals {
+            normal: visual,
+            hovered: visual,
+            pressed: visual,
+            disabled: visual,
+        }
+    }
+
+    fn get(&self, state: WidgetState) -> &WidgetVisual {
+        match state {
+            WidgetState::Normal => &self.normal,
+            WidgetState::Hovered => &self.hovered,
+            WidgetState::Pressed => &self.pressed,
+            WidgetState::Disabled => &self.disabled,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum WidgetState {
+    #[default]
+    Normal,
+    /// 光标悬停或者获得了焦点
+    Hovered,
+    Pressed,
+    Disabled,
+}
+
+#[derive(Clone, Debug)]
+pub enum WidgetKind {
+    Button,
+    Toggle {
+        on: bool,
+        /// 打开状态下的外观，关闭状态使用 Widget 的 visuals
+        on_visuals: WidgetVisuals,
+    },
+    Slider {
+        /// 0 - 1
+        value: f32,
+        /// 键盘或手柄调整时的步长
+        step: f32,
+        handle: Box<UiSprite>,
+        handle_visuals: WidgetVisuals,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Widget {
+    pub node: UiNodeId,
+    pub sprite: UiSprite,
+    pub visuals: WidgetVisuals,
+    pub kind: WidgetKind,
+    pub enabled: bool,
+    state: WidgetState,
+}
+
+impl Widget {
+    pub fn state(&self) -> WidgetState {
+        self.state
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct WidgetId(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WidgetEvent {
+    Clicked(WidgetId),
+    Toggled(WidgetId, bool),
+    SliderChanged(WidgetId, f32),
+}
+
+/// 焦点导航操作，键盘和手柄都会转换为这些操作
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NavAction {
+    Next,
+    Previous,
+    Activate,
+    Decrease,
+    Increase,
+    Cancel,
+}
+
+/// 保留模式的控件层
+///
+/// 控件的位置和尺寸由 UiTree 中对应节点的布局结果决定，
+/// update 时只处理没有被 egui 占用的输入，光标在控件上或者有控件获得焦点时，
+/// 会在 Input 上以 `InputLayer::Ui` 占用对应的输入，这样输入就不会再传递给场景。
+#[derive(Debug, Default)]
+pub struct Widgets {
+    widgets: Vec<Widget>,
+    focus: Option<WidgetId>,
+    /// 鼠标按下时所在的控件
+    pressed: Option<WidgetId>,
+    events: Vec<WidgetEvent>,
+    pointer_consumed: bool,
+    keyboard_consumed: bool,
+}
+
+impl Widgets {
+    pub fn add_button(
+        &mut self,
+        node: UiNodeId,
+        sprite: Sprite,
+        visuals: WidgetVisuals,
+    ) -> WidgetId {
+        self.add(node, sprite, visuals, WidgetKind::Button)
+    }
+
+    pub fn add_toggle(
+        &mut self,
+        node: UiNodeId,
+        sprite: Sprite,
+        visuals: WidgetVisuals,
+        on_visuals: WidgetVisuals,
+        on: bool,
+    ) -> WidgetId {
+        self.add(node, sprite, visuals, WidgetKind::Toggle { on, on_visuals })
+    }
+
+    /// handle 的尺寸与滑轨的高度相同
+    pub fn add_slider(
+        &mut self,
+        node: UiNodeId,
+        sprite: Sprite,
+        visuals: WidgetVisuals,
+        handle_visuals: WidgetVisuals,
+        value: f32,
+        step: f32,
+    ) -> WidgetId {
+        let handle = Box::new(UiSprite {
+            sprite: Sprite {
+                transform: sprite.transform,
+                layers: sprite.layers,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        self.add(
+            node,
+            sprite,
+            visuals,
+            WidgetKind::Slider {
+                value: value.clamp(0.0, 1.0),
+                step,
+                handle,
+                handle_visuals,
+            },
+        )
+    }
+
+    fn add(
+        &mut self,
+        node: UiNodeId,
+        sprite: Sprite,
+        visuals: WidgetVisuals,
+        kind: WidgetKind,
+    ) -> WidgetId {
+        let mut sprite = UiSprite {
+            sprite,
+            ..Default::default()
+        };
+        apply_visual(&mut sprite, visuals.get(WidgetState::Normal));
+        self.widgets.push(Widget {
+            node,
+            sprite,
+            visuals,
+            kind,
+            enabled: true,
+            state: WidgetState::Normal,
+        });
+        WidgetId(self.widgets.len() - 1)
+    }
+
+    pub fn get(&self, id: WidgetId) -> &Widget {
+        &self.widgets[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: WidgetId) -> &mut Widget {
+        &mut self.widgets[id.0]
+    }
+
+    pub fn set_enabled(&mut self, id: WidgetId, enabled: bool) {
+        self.widgets[id.0].enabled = enabled;
+        if !enabled && self.focus == Some(id) {
+            self.focus = None;
+        }
+    }
+
+    pub fn focus(&self) -> Option<WidgetId> {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, focus: Option<WidgetId>) {
+        self.focus = focus.filter(|id| self.widgets[id.0].enabled);
+    }
+
+    /// 光标在控件上或者正在拖动控件，鼠标输入不应该再传递给场景
+    pub fn pointer_consumed(&self) -> bool {
+        self.pointer_consumed
+    }
+
+    /// 有控件获得了焦点，键盘输入不应该再传递给场景
+    pub fn keyboard_consumed(&self) -> bool {
+        self.keyboard_consumed
+    }
+
+    pub fn events(&self) -> &[WidgetEvent] {
+        &self.events
+    }
+
+    pub fn clicked(&self, id: WidgetId) -> bool {
+        self.events.contains(&WidgetEvent::Clicked(id))
+    }
+
+    pub fn toggled(&self, id: WidgetId) -> Option<bool> {
+        self.events.iter().find_map(|event| match event {
+            WidgetEvent::Toggled(target, on) if *target == id => Some(*on),
+            _ => None,
+        })
+    }
+
+    pub fn slider_changed(&self, id: WidgetId) -> Option<f32> {
+        self.events.iter().find_map(|event| match event {
+            WidgetEvent::SliderChanged(target, value) if *target == id => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// 所有需要渲染的 sprite
+    pub fn sprites(&self) -> impl Iterator<Item = &Sprite> {
+        self.widgets.iter().flat_map(|widget| {
+            let handle = match &widget.kind {
+                WidgetKind::Slider { handle, .. } => Some(&handle.sprite),
+                _ => None,
+            };
+            std::iter::once(&widget.sprite.sprite).chain(handle)
+        })
+    }
+
+    /// 将键盘输入转换为焦点导航操作
+    pub fn keyboard_nav_actions(input: &Input) -> Vec<NavAction> {
+        let shift = input.if_keyboard_pressed(&KeyCode::ShiftLeft)
+            || input.if_keyboard_just_pressed(&KeyCode::ShiftLeft)
+            || input.if_keyboard_pressed(&KeyCode::ShiftRight)
+            || input.if_keyboard_just_pressed(&KeyCode::ShiftRight);
+        let mut actions = Vec::new();
+        if input.if_keyboard_just_pressed(&KeyCode::Tab) {
+            actions.push(if shift {
+                NavAction::Previous
+            } else {
+                NavAction::Next
+            });
+        }
+        if input.if_keyboard_just_pressed(&KeyCode::Enter)
+            || input.if_keyboard_just_pressed(&KeyCode::Space)
+        {
+            actions.push(NavAction::Activate);
+        }
+        if input.if_keyboard_just_pressed(&KeyCode::ArrowLeft) {
+            actions.push(NavAction::Decrease);
+        }
+        if input.if_keyboard_just_pressed(&KeyCode::ArrowRight) {
+            actions.push(NavAction::Increase);
+        }
+        if input.if_keyboard_just_pressed(&KeyCode::Escape) {
+            actions.push(NavAction::Cancel);
+        }
+        actions
+    }
+
+    /// 将手柄的方向键和按键转换为焦点导航操作
+    pub fn gamepad_nav_actions(input: &Input) -> Vec<NavAction> {
+        [
+            (GamepadButton::DPadDown, NavAction::Next),
+            (GamepadButton::DPadUp, NavAction::Previous),
+            (GamepadButton::South, NavAction::Activate),
+            (GamepadButton::DPadLeft, NavAction::Decrease),
+            (GamepadButton::DPadRight, NavAction::Increase),
+            (GamepadButton::East, NavAction::Cancel),
+        ]
+        .into_iter()
+        .filter(|(button, _)| input.if_gamepad_just_pressed(button))
+        .map(|(_, action)| action)
+        .collect()
+    }
+
+    /// tree 需要先完成 compute_layout，camera 是渲染 UI 使用的镜头
+    pub fn update(&mut self, tree: &UiTree, input: &mut Input, camera: &Camera2D, delta: Duration) {
+        let actions = if input.keyboard_available(InputLayer::Ui) {
+            let mut actions = Self::keyboard_nav_actions(input);
+            actions.extend(Self::gamepad_nav_actions(input));
+            actions
+        } else {
+            Vec::new()
+        };
+        self.update_with_actions(tree, input, &actions, camera, delta);
+    }
+
+    /// 与 update 相同，只是导航操作由外部提供，比如同时合并了键盘和手柄的操作
+    pub fn update_with_actions(
+        &mut self,
+        tree: &UiTree,
+        input: &mut Input,
+        actions: &[NavAction],
+        camera: &Camera2D,
+        delta: Duration,
+    ) {
+        self.events.clear();
+
+        let pointer_available = input.pointer_available(InputLayer::Ui);
+        // 触摸的轻点相当于在同一帧内按下并松开
+        let tap = input.tap().filter(|_| pointer_available);
+        let cursor = tap.unwrap_or(input.cursor_pos());
+        let just_pressed =
+            pointer_available && (input.if_mouse_just_pressed(&MouseButton::Left) || tap.is_some());
+        let held = just_pressed || input.if_mouse_pressed(&MouseButton::Left);
+        let just_released = input.if_mouse_just_released(&MouseButton::Left) || tap.is_some();
+        // 触摸没有悬停，手指抬起后光标虽然还停在原处，但不再算作悬停
+        let touch_hover = !input.if_touch_pointer() || input.touch_count() > 0 || tap.is_some();
+
+        // 光标被 egui 挡住时，视为没有悬停在任何控件上
+        let hovered = self
+            .widgets
+            .iter()
+            .rposition(|widget| tree.layout(widget.node).contains(cursor))
+            .map(WidgetId)
+            .filter(|_| pointer_available && touch_hover);
+
+        // 鼠标输入
+        if just_pressed {
+            self.pressed = hovered.filter(|id| self.widgets[id.0].enabled);
+            if self.pressed.is_some() {
+                self.focus = None;
+            }
+        }
+        if let Some(id) = self.pressed {
+            let track = tree.layout(self.widgets[id.0].node);
+            if let WidgetKind::Slider { value, .. } = &mut self.widgets[id.0].kind {
+                if held && track.size().x > 0.0 {
+                    let new_value = ((cursor.x - track.min.x) / track.size().x).clamp(0.0, 1.0);
+                    if new_value != *value {
+                        *value = new_value;
+                        self.events.push(WidgetEvent::SliderChanged(id, new_value));
+                    }
+                }
+            }
+            if just_released || !held {
+                if hovered == Some(id) {
+                    self.activate(id);
+                }
+                self.pressed = None;
+            }
+        }
+
+        // 焦点导航
+        for action in actions {
+            match action {
+                NavAction::Next => self.move_focus(true),
+                NavAction::Previous => self.move_focus(false),
+                NavAction::Cancel => self.focus = None,
+                NavAction::Activate => {
+                    if let Some(id) = self.focus {
+                        self.activate(id);
+                    }
+                }
+                NavAction::Decrease | NavAction::Increase => {
+                    if let Some(id) = self.focus {
+                        if let WidgetKind::Slider { value, step, .. } = &mut self.widgets[id.0].kind
+                        {
+                            let sign = if *action == NavAction::Increase {
+                                1.0
+                            } else {
+                                -1.0
+                            };
+                            let new_value = (*value + *step * sign).clamp(0.0, 1.0);
+                            if new_value != *value {
+                                *value = new_value;
+                                self.events.push(WidgetEvent::SliderChanged(id, new_value));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.pointer_consumed = hovered.is_some() || self.pressed.is_some();
+        self.keyboard_consumed = self.focus.is_some();
+        if self.pointer_consumed {
+            input.claim_pointer(InputLayer::Ui);
+        }
+        if self.keyboard_consumed {
+            input.claim_keyboard(InputLayer::Ui);
+        }
+
+        // 更新外观和位置
+        for (index, widget) in self.widgets.iter_mut().enumerate() {
+            let id = WidgetId(index);
+            widget.state = if !widget.enabled {
+                WidgetState::Disabled
+            } else if self.pressed == Some(id) && hovered == Some(id) {
+                WidgetState::Pressed
+            } else if hovered == Some(id) || self.focus == Some(id) {
+                WidgetState::Hovered
+            } else {
+                WidgetState::Normal
+            };
+
+            let layout = tree.layout(widget.node);
+            let visuals = match &widget.kind {
+                WidgetKind::Toggle {
+                    on: true,
+                    on_visuals,
+                } => on_visuals,
+                _ => &widget.visuals,
+            };
+            apply_visual(&mut widget.sprite, visuals.get(widget.state));
+            widget.sprite.update(layout, camera, delta);
+
+            if let WidgetKind::Slider {
+                value,
+                handle,
+                handle_visuals,
+                ..
+            } = &mut widget.kind
+            {
+                let handle_size = Vec2::splat(layout.size().y);
+                let center = Vec2::new(layout.min.x + layout.size().x * *value, layout.center().y);
+                apply_visual(handle, handle_visuals.get(widget.state));
+                handle.sprite.transform.translation.z =
+                    widget.sprite.sprite.transform.translation.z + 1.0;
+                handle.update(Rect::from_center_size(center, handle_size), camera, delta);
+            }
+        }
+    }
+
+    fn activate(&mut self, id: WidgetId) {
+        let widget = &mut self.widgets[id.0];
+        if !widget.enabled {
+            return;
+        }
+        match &mut widget.kind {
+            WidgetKind::Button => self.events.push(WidgetEvent::Clicked(id)),
+            WidgetKind::Toggle { on, .. } => {
+                *on = !*on;
+                self.events.push(WidgetEvent::Toggled(id, *on));
+            }
+            WidgetKind::Slider { .. } => {}
+        }
+    }
+
+    /// 在可用的控件之间循环切换焦点，顺序与添加控件的顺序相同
+    fn move_focus(&mut self, forward: bool) {
+        let count = self.widgets.len();
+        if count == 0 {
+            return;
+        }
+        let start = match self.focus {
+            Some(WidgetId(index)) => index,
+            None if forward => count - 1,
+            None => 0,
+        };
+        for offset in 1..=count {
+            let index = if forward {
+                (start + offset) % count
+            } else {
+                (start + count - offset) % count
+            };
+            if self.widgets[index].enabled {
+                self.focus = Some(WidgetId(index));
+                return;
+            }
+        }
+        self.focus = None;
+    }
+}
+
+fn apply_visual(sprite: &mut UiSprite, visual: &WidgetVisual) {
+    sprite.sprite.texture_id = visual.texture_id;
+    sprite.sprite.color.set_opacity(visual.opacity);
+    if let Some(frames) = visual.anima {
+        sprite.start_anima(frames);
+    } else {
+        sprite.stop_anima();
+        sprite.sprite.rect = visual.rect;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputEvent;
+    use crate::{Style, Val};
+
+    const VIEWPORT: Vec2 = Vec2::new(200.0, 100.0);
+
+    fn setup() -> (UiTree, Widgets, WidgetId, Camera2D) {
+        let mut tree = UiTree::default();
+        let node = tree.new_root(Style {
+            width: Val::Px(50.0),
+            height: Val::Px(20.0),
+            ..Default::default()
+        });
+        tree.compute_layout(VIEWPORT, 1.0);
+        let mut widgets = Widgets::default();
+        let visuals = WidgetVisuals::splat(WidgetVisual::new(crate::assets::START_NORMAL.id));
+        let button = widgets.add_button(node, Sprite::default(), visuals);
+        (tree, widgets, button, Camera2D::new(VIEWPORT))
+    }
+
+    fn frame(
+        tree: &UiTree,
+        widgets: &mut Widgets,
+        camera: &Camera2D,
+        input: &mut Input,
+        events: &[InputEvent],
+        egui_hovered: bool,
+    ) {
+        input.fresh();
+        for event in events {
+            input.handle_event(*event);
+        }
+        if egui_hovered {
+            input.claim_pointer(InputLayer::Egui);
+        }
+        widgets.update(tree, input, camera, Duration::from_millis(16));
+    }
+
+    fn mouse(pressed: bool) -> InputEvent {
+        InputEvent::Mouse {
+            button: MouseButton::Left,
+            pressed,
+        }
+    }
+
+    #[test]
+    fn click_on_widget_does_not_reach_scene() {
+        let (tree, mut widgets, button, camera) = setup();
+        let mut input = Input::default();
+        let over = InputEvent::CursorMoved(Vec2::new(10.0, 10.0));
+
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[over, mouse(true)],
+            false,
+        );
+        assert!(!input.pointer_available(InputLayer::Scene));
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[mouse(false)],
+            false,
+        );
+        assert!(widgets.clicked(button));
+        assert!(!input.pointer_available(InputLayer::Scene));
+
+        // 光标不在控件上时输入继续传递给场景
+        let away = InputEvent::CursorMoved(Vec2::new(150.0, 80.0));
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[away, mouse(true)],
+            false,
+        );
+        assert!(input.pointer_available(InputLayer::Scene));
+        assert!(input.if_mouse_just_pressed(&MouseButton::Left));
+    }
+
+    #[test]
+    fn egui_claim_blocks_widgets() {
+        let (tree, mut widgets, button, camera) = setup();
+        let mut input = Input::default();
+        let over = InputEvent::CursorMoved(Vec2::new(10.0, 10.0));
+
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[over, mouse(true)],
+            true,
+        );
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[mouse(false)],
+            true,
+        );
+        assert!(!widgets.clicked(button));
+        assert_eq!(widgets.get(button).state(), WidgetState::Normal);
+        assert_eq!(input.pointer_claim(), Some(InputLayer::Egui));
+    }
+
+    #[test]
+    fn focused_widget_claims_keyboard() {
+        let (tree, mut widgets, button, camera) = setup();
+        let mut input = Input::default();
+        let key = |key_code, pressed| InputEvent::Keyboard {
+            key_code,
+            pressed,
+            repeat: false,
+        };
+
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[key(KeyCode::Tab, true)],
+            false,
+        );
+        assert_eq!(widgets.focus(), Some(button));
+        assert!(!input.keyboard_available(InputLayer::Scene));
+
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[key(KeyCode::Tab, false), key(KeyCode::Enter, true)],
+            false,
+        );
+        assert!(widgets.clicked(button));
+
+        frame(
+            &tree,
+            &mut widgets,
+            &camera,
+            &mut input,
+            &[key(KeyCode::Escape, true)],
+            false,
+        );
+        assert_eq!(widgets.focus(), None);
+        assert!(input.keyboard_available(InputLayer::Scene));
+    }
+}
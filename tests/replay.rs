@@ -0,0 +1,164 @@
+use glam::Vec2;
+use isometric_engine::SerdeFrom;
+use std::time::Duration;
+use webgpu_demo_lib::{
+    replay_headless, AssetServer, AssetSource, InputEvent, InputFrame, InputRecording,
+    InputSnapshot, ReplayOutcome, ViewportInfo, PACKAGE_SIDEBOARD, SCENE_SIDEBOARD,
+};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+const FRAME: Duration = Duration::from_micros(16_667);
+
+fn frame(events: Vec<InputEvent>) -> InputFrame {
+    InputFrame {
+        delta: FRAME,
+        events,
+        fixed_steps: 1,
+        ..Default::default()
+    }
+}
+
+fn click(button: MouseButton, pressed: bool) -> InputEvent {
+    InputEvent::Mouse { button, pressed }
+}
+
+/// 移动光标、平移镜头并在场景中点击几次
+fn session() -> InputRecording {
+    let viewport = ViewportInfo {
+        size: [1280, 720],
+        scale_factor: 1.0,
+    };
+    let mut recording = InputRecording::new(viewport, FRAME, 2, InputSnapshot::default());
+    let frames = &mut recording.frames;
+    frames.push(frame(vec![InputEvent::CursorMoved(Vec2::new(
+        640.0, 360.0,
+    ))]));
+    frames.push(frame(vec![InputEvent::Keyboard {
+        key_code: KeyCode::ArrowLeft,
+        pressed: true,
+        repeat: false,
+    }]));
+    frames.extend((0..10).map(|_| frame(Vec::new())));
+    frames.push(frame(vec![InputEvent::Keyboard {
+        key_code: KeyCode::ArrowLeft,
+        pressed: false,
+        repeat: false,
+    }]));
+    for (x, y) in [(600.0, 300.0), (700.0, 420.0), (520.0, 380.0)] {
+        frames.push(frame(vec![
+            InputEvent::CursorMoved(Vec2::new(x, y)),
+            click(MouseButton::Left, true),
+        ]));
+        frames.extend((0..5).map(|_| frame(Vec::new())));
+        frames.push(frame(vec![click(MouseButton::Left, false)]));
+    }
+    frames.push(frame(vec![click(MouseButton::Right, true)]));
+    frames.push(frame(vec![click(MouseButton::Right, false)]));
+    recording
+}
+
+/// 从源码中的资源目录加载场景和资源包
+fn assets() -> AssetServer {
+    let mut assets = AssetServer::new(AssetSource::Directory(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/src/assets").into(),
+    ));
+    assets.load_blocking(&SCENE_SIDEBOARD).unwrap();
+    assets.load_blocking(&PACKAGE_SIDEBOARD).unwrap();
+    assets
+}
+
+fn assert_same(a: &ReplayOutcome, b: &ReplayOutcome) {
+    assert_eq!(a.camera_translation, b.camera_translation);
+    assert_eq!(a.camera_offset, b.camera_offset);
+    assert_eq!(a.clicks, b.clicks);
+    assert_eq!(a.fixed_steps, b.fixed_steps);
+    assert_eq!(
+        a.scene.as_ref().expect("scene").to_bytes(),
+        b.scene.as_ref().expect("scene").to_bytes()
+    );
+}
+
+#[test]
+fn replay_reproduces_scene_state() {
+    let recording = session();
+    let assets = assets();
+    let first = replay_headless(&recording, &assets);
+    assert_same(&first, &replay_headless(&recording, &assets));
+    // 三次左键和一次右键
+    assert_eq!(first.clicks, 4);
+    assert_eq!(first.fixed_steps, recording.frames.len());
+}
+
+#[test]
+fn replay_differs_from_a_fresh_game() {
+    let recording = session();
+    // 光标的移动相同，但是没有按键和点击
+    let mut idle = recording.clone();
+    for frame in &mut idle.frames {
+        frame
+            .events
+            .retain(|event| matches!(event, InputEvent::CursorMoved(_)));
+    }
+    let assets = assets();
+    let played = replay_headless(&recording, &assets);
+    let fresh = replay_headless(&idle, &assets);
+    assert_eq!(fresh.clicks, 0);
+    assert_eq!(played.fixed_steps, fresh.fixed_steps);
+    // 按住 ArrowLeft 的 11 帧里镜头向左移动，场景比画面小时位置会被限制在场景中间
+    assert_eq!(fresh.camera_offset, Vec2::ZERO);
+    assert!(played.camera_offset.x < 0.0, "{}", played.camera_offset);
+    assert_eq!(played.camera_offset.y, 0.0);
+    assert!(played.camera_translation.x <= fresh.camera_translation.x);
+}
+
+#[test]
+fn recording_survives_serialization() {
+    let recording = session();
+    let loaded = InputRecording::from_bytes(&recording.to_bytes()).unwrap();
+    assert_eq!(loaded, recording);
+    let assets = assets();
+    assert_same(
+        &replay_headless(&loaded, &assets),
+        &replay_headless(&recording, &assets),
+    );
+}
+
+#[test]
+fn rejects_unknown_version() {
+    let mut recording = session();
+    recording.version += 1;
+    assert!(InputRecording::from_bytes(&recording.to_bytes()).is_err());
+}
+
+#[test]
+fn paused_frames_do_not_reach_the_game() {
+    let recording = session();
+    let mut paused = recording.clone();
+    // 在最后插入一段暂停，暂停时的点击交给了暂停菜单
+    let index = paused.frames.len() - 2;
+    let mut pause = frame(Vec::new());
+    pause.paused = true;
+    let mut clicks = vec![pause.clone(); 3];
+    clicks[1].events = vec![
+        InputEvent::CursorMoved(Vec2::new(640.0, 360.0)),
+        click(MouseButton::Left, true),
+    ];
+    clicks[2].events = vec![click(MouseButton::Left, false)];
+    // 关闭暂停菜单的一帧，输入同样交给了暂停菜单
+    clicks.push(InputFrame {
+        fixed_steps: 0,
+        ..frame(Vec::new())
+    });
+    paused.frames.splice(index..index, clicks);
+
+    let assets = assets();
+    let played = replay_headless(&recording, &assets);
+    assert_same(&replay_headless(&paused, &assets), &played);
+
+    // 同样的帧没有暂停时，点击会交给场景
+    for frame in &mut paused.frames[index..index + 3] {
+        frame.paused = false;
+    }
+    assert_eq!(replay_headless(&paused, &assets).clicks, played.clicks + 1);
+}